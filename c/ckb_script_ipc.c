@@ -28,6 +28,7 @@
 #define PANIC(e) g_csi_context.panic(e)
 int csi_vlq_encode(void* buf, size_t len, uint64_t value, size_t* out_len);
 int csi_vlq_decode(const void* buf, size_t len, uint64_t* value, size_t* out_len);
+int csi_vlq_decode_canonical(const void* buf, size_t len, uint64_t* value, size_t* out_len);
 int csi_read_vlq(CSIReader* reader, uint64_t* value);
 int csi_write_vlq(CSIWriter* writer, uint64_t value);
 
@@ -38,6 +39,7 @@ typedef struct CSIContext {
     CSIMalloc iobuf_malloc;
     CSIFree iobuf_free;
     CSIPanic panic;
+    bool canonical_vlq;
 } CSIContext;
 
 static CSIContext g_csi_context = {.panic = csi_default_panic};
@@ -77,11 +79,11 @@ static void* fixed_allocator_malloc(FixedAllocator* allocator, size_t size) {
     if (index >= allocator->block_count) {
         return NULL;
     }
-    allocator->used_flags |= (1 << index);
     size_t block_size = allocator->len / allocator->block_count;
     if (size > block_size) {
         return NULL;
     }
+    allocator->used_flags |= (1ULL << index);
     return allocator->buf + block_size * index;
 }
 
@@ -98,7 +100,7 @@ static void fixed_allocator_free(FixedAllocator* allocator, void* ptr) {
         PANIC(CSI_ERROR_INTERNAL);
     }
     size_t index = offset / block_size;
-    allocator->used_flags &= ~(1 << index);
+    allocator->used_flags &= ~(1ULL << index);
 }
 
 static void* payload_malloc(size_t len) { return fixed_allocator_malloc(&g_payload_allocator, len); }
@@ -124,6 +126,8 @@ void csi_init_malloc(CSIMalloc malloc, CSIFree free) {
 
 void csi_init_panic(CSIPanic panic) { g_csi_context.panic = panic; }
 
+void csi_init_canonical_vlq(bool canonical) { g_csi_context.canonical_vlq = canonical; }
+
 void csi_default_panic(int exit_code) {
     printf("panic in IPC, error code: %d", exit_code);
     ckb_exit(exit_code);
@@ -144,7 +148,13 @@ void csi_init_iobuf(void* buf, size_t len, size_t block_count) {
 
 static int csi_read_pipe(void* ctx, void* buf, size_t len, size_t* read_len) {
     *read_len = len;
-    return ckb_read((uint64_t)ctx, buf, read_len);
+    int err = ckb_read((uint64_t)ctx, buf, read_len);
+    // the end of the stream
+    if (err == CKB_OTHER_END_CLOSED) {
+        *read_len = 0;
+        return 0;
+    }
+    return err;
 }
 
 static int csi_write_pipe(void* ctx, const void* buf, size_t len, size_t* written_len) {
@@ -216,6 +226,10 @@ static int buf_read(void* ctx, void* buf, size_t len, size_t* read_len) {
         err = reader.read(reader.ctx, buffer->buf, buffer->max_len, &buffer->filled_len);
         CHECK(err);
         buffer->pos = 0;
+        if (buffer->filled_len == 0) {
+            *read_len = 0;
+            return 0;
+        }
     }
     if (buffer->pos >= buffer->filled_len || buffer->filled_len > buffer->max_len) {
         PANIC(CSI_ERROR_INTERNAL);
@@ -287,13 +301,16 @@ static void new_buf_writer(const CSIWriter writer, CSIWriter* buf_writer) {
 }
 
 int csi_read_exact(CSIReader* reader, void* buf, size_t len) {
-    int remaining_len = len;
+    size_t remaining_len = len;
     while (remaining_len > 0) {
         size_t read_len = 0;
         int err = reader->read(reader->ctx, buf, remaining_len, &read_len);
         if (err) {
             return err;
         }
+        if (read_len == 0) {
+            return CSI_ERROR_UNEXPECTED_EOF;
+        }
         remaining_len -= read_len;
         buf += read_len;
     }
@@ -348,10 +365,16 @@ exit:
 int csi_receive_request(CSIChannel* channel, CSIRequestPacket* request) {
     int err = 0;
     err = csi_read_vlq(&channel->reader, &request->version);
+    // the end of the stream between requests, not an error for servers
+    if (err == CSI_ERROR_EOF) {
+        return err;
+    }
     CHECK(err);
     err = csi_read_vlq(&channel->reader, &request->method_id);
+    CHECK2(err != CSI_ERROR_EOF, CSI_ERROR_UNEXPECTED_EOF);
     CHECK(err);
     err = csi_read_vlq(&channel->reader, &request->payload_len);
+    CHECK2(err != CSI_ERROR_EOF, CSI_ERROR_UNEXPECTED_EOF);
     CHECK(err);
 
     if (request->payload_len > 0) {
@@ -371,8 +394,10 @@ int csi_receive_response(CSIChannel* channel, CSIResponsePacket* response) {
     err = csi_read_vlq(&channel->reader, &response->version);
     CHECK(err);
     err = csi_read_vlq(&channel->reader, &response->error_code);
+    CHECK2(err != CSI_ERROR_EOF, CSI_ERROR_UNEXPECTED_EOF);
     CHECK(err);
     err = csi_read_vlq(&channel->reader, &response->payload_len);
+    CHECK2(err != CSI_ERROR_EOF, CSI_ERROR_UNEXPECTED_EOF);
     CHECK(err);
 
     if (response->payload_len > 0) {
@@ -409,8 +434,11 @@ int csi_read_vlq(CSIReader* reader, uint64_t* value) {
 
     while (1) {
         size_t read_len = 0;
-        int err = reader->read(reader->ctx, &peek, 1, &read_len);
+        err = reader->read(reader->ctx, &peek, 1, &read_len);
         CHECK(err);
+        if (read_len == 0) {
+            return buf_len == 0 ? CSI_ERROR_EOF : CSI_ERROR_UNEXPECTED_EOF;
+        }
         if (buf_len >= sizeof(buf)) {
             return CSI_ERROR_READ_VLQ;
         }
@@ -421,7 +449,11 @@ int csi_read_vlq(CSIReader* reader, uint64_t* value) {
         }
     }
     size_t read_len = 0;
-    err = csi_vlq_decode(buf, buf_len, value, &read_len);
+    if (g_csi_context.canonical_vlq) {
+        err = csi_vlq_decode_canonical(buf, buf_len, value, &read_len);
+    } else {
+        err = csi_vlq_decode(buf, buf_len, value, &read_len);
+    }
     CHECK(err);
     if (read_len != buf_len) {
         return CSI_ERROR_READ_VLQ;
@@ -453,7 +485,7 @@ int csi_vlq_encode(void* buf, size_t len, uint64_t value, size_t* out_len) {
     return 0;
 }
 
-int csi_vlq_decode(const void* buf, size_t len, uint64_t* value, size_t* out_len) {
+static int vlq_decode(const void* buf, size_t len, bool canonical, uint64_t* value, size_t* out_len) {
     const uint8_t* bytes = (const uint8_t*)buf;
     uint64_t result = 0;
     size_t shift = 0;
@@ -461,23 +493,37 @@ int csi_vlq_decode(const void* buf, size_t len, uint64_t* value, size_t* out_len
 
     while (read < len) {
         uint8_t byte = bytes[read++];
-        result |= ((uint64_t)(byte & 0x7F) << shift);
+        uint64_t bits = byte & 0x7F;
+        // the 10th byte holds the highest bit of a uint64_t, and must be the last one
+        if (shift >= 63 && (bits > 1 || (byte & 0x80) != 0)) {
+            return CSI_ERROR_VLQ;
+        }
+        result |= bits << shift;
 
         if ((byte & 0x80) == 0) {
+            // a trailing zero group makes the encoding longer than needed
+            if (canonical && byte == 0 && read > 1) {
+                return CSI_ERROR_VLQ_NOT_CANONICAL;
+            }
             *value = result;
             *out_len = read;
             return 0;
         }
 
         shift += 7;
-        if (shift >= 64) {
-            return CSI_ERROR_VLQ;
-        }
     }
 
     return CSI_ERROR_VLQ;
 }
 
+int csi_vlq_decode(const void* buf, size_t len, uint64_t* value, size_t* out_len) {
+    return vlq_decode(buf, len, false, value, out_len);
+}
+
+int csi_vlq_decode_canonical(const void* buf, size_t len, uint64_t* value, size_t* out_len) {
+    return vlq_decode(buf, len, true, value, out_len);
+}
+
 int csi_call(CSIChannel* channel, const CSIRequestPacket* request, CSIResponsePacket* response) {
     int err = 0;
     err = csi_send_request(channel, request);
@@ -507,8 +553,8 @@ void csi_server_malloc_response_payload(CSIResponsePacket* response) {
     }
 }
 
-int csi_spawn_server(uint64_t index, uint64_t source, size_t offset, size_t length, const char* argv[], int argc,
-                     CSIChannel* client_channel) {
+int csi_spawn_server_handle(uint64_t index, uint64_t source, size_t offset, size_t length, const char* argv[],
+                            int argc, CSIServerHandle* server) {
     int err = 0;
     uint64_t fds[2];
     uint64_t fds2[2];
@@ -533,24 +579,77 @@ int csi_spawn_server(uint64_t index, uint64_t source, size_t offset, size_t leng
     CSIReader reader = {0};
     err = new_pipe_reader(fds[0], &reader);
     CHECK(err);
-    new_buf_reader(reader, &client_channel->reader);
+    new_buf_reader(reader, &server->channel.reader);
 
     CSIWriter writer = {0};
     err = new_pipe_writer(fds2[1], &writer);
     CHECK(err);
-    new_buf_writer(writer, &client_channel->writer);
+    new_buf_writer(writer, &server->channel.writer);
+
+    server->pid = pid;
+    server->read_fd = fds[0];
+    server->write_fd = fds2[1];
+    server->closed = false;
 
 exit:
     return err;
 }
 
-int csi_spawn_cell_server(void* code_hash, uint64_t hash_type, const char* argv[], int argc,
-                          CSIChannel* client_channel) {
+int csi_spawn_cell_server_handle(void* code_hash, uint64_t hash_type, const char* argv[], int argc,
+                                 CSIServerHandle* server) {
     int err = 0;
     size_t index = SIZE_MAX;
     err = ckb_look_for_dep_with_hash2(code_hash, hash_type, &index);
     CHECK(err);
-    err = csi_spawn_server(index, CKB_SOURCE_CELL_DEP, 0, 0, argv, argc, client_channel);
+    err = csi_spawn_server_handle(index, CKB_SOURCE_CELL_DEP, 0, 0, argv, argc, server);
+    CHECK(err);
+
+exit:
+    return err;
+}
+
+int csi_spawn_server(uint64_t index, uint64_t source, size_t offset, size_t length, const char* argv[], int argc,
+                     CSIChannel* client_channel) {
+    CSIServerHandle server = {0};
+    int err = csi_spawn_server_handle(index, source, offset, length, argv, argc, &server);
+    if (err == 0) {
+        *client_channel = server.channel;
+    }
+    return err;
+}
+
+int csi_spawn_cell_server(void* code_hash, uint64_t hash_type, const char* argv[], int argc,
+                          CSIChannel* client_channel) {
+    CSIServerHandle server = {0};
+    int err = csi_spawn_cell_server_handle(code_hash, hash_type, argv, argc, &server);
+    if (err == 0) {
+        *client_channel = server.channel;
+    }
+    return err;
+}
+
+int csi_close_server_handle(CSIServerHandle* server) {
+    int err = 0;
+    int err2 = 0;
+    if (server->closed) {
+        return 0;
+    }
+    csi_free_channel(&server->channel);
+    // Close both pipes even if the first close fails, and report the first error.
+    err = ckb_close(server->write_fd);
+    err2 = ckb_close(server->read_fd);
+    server->closed = true;
+    if (err == 0) {
+        err = err2;
+    }
+    return err;
+}
+
+int csi_wait_server_handle(CSIServerHandle* server, int8_t* exit_code) {
+    int err = 0;
+    err = csi_close_server_handle(server);
+    CHECK(err);
+    err = ckb_wait(server->pid, exit_code);
     CHECK(err);
 
 exit:
@@ -580,6 +679,11 @@ int csi_run_server(CSIServe serve) {
         CSIRequestPacket request = {0};
         CSIResponsePacket response = {0};
         err = csi_receive_request(&server_channel, &request);
+        if (err == CSI_ERROR_EOF) {
+            // the client closed the channel
+            err = CSI_SUCCESS;
+            break;
+        }
         CHECK(err);
         err = serve(&request, &response);
         CHECK(err);
@@ -592,7 +696,862 @@ exit:
     return err;
 }
 
+typedef struct CSIMethod {
+    uint64_t id;
+    CSIMethodHandler handler;
+    void* ctx;
+} CSIMethod;
+
+static CSIMethod g_csi_methods[CSI_MAX_METHODS];
+static size_t g_csi_method_count = 0;
+
+int csi_register_method(uint64_t method_id, CSIMethodHandler handler, void* ctx) {
+    for (size_t i = 0; i < g_csi_method_count; i++) {
+        if (g_csi_methods[i].id == method_id) {
+            g_csi_methods[i].handler = handler;
+            g_csi_methods[i].ctx = ctx;
+            return 0;
+        }
+    }
+    if (g_csi_method_count == CSI_MAX_METHODS) {
+        return CSI_ERROR_TOO_MANY_METHODS;
+    }
+    g_csi_methods[g_csi_method_count++] = (CSIMethod){.id = method_id, .handler = handler, .ctx = ctx};
+    return 0;
+}
+
+int csi_dispatch(const CSIRequestPacket* request, CSIResponsePacket* response) {
+    for (size_t i = 0; i < g_csi_method_count; i++) {
+        if (g_csi_methods[i].id == request->method_id) {
+            return g_csi_methods[i].handler(g_csi_methods[i].ctx, request, response);
+        }
+    }
+    response->error_code = CSI_UNKNOWN_METHOD;
+    response->payload_len = 0;
+    response->payload = NULL;
+    return 0;
+}
+
 void csi_free_channel(CSIChannel* ch) {
     iobuf_free(ch->reader.ctx);
     iobuf_free(ch->writer.ctx);
 }
+
+static int json_write_raw(CSIJsonWriter* writer, const void* buf, size_t len) {
+    if (writer->err) {
+        return writer->err;
+    }
+    if (len > writer->len - writer->pos) {
+        writer->err = CSI_ERROR_JSON_BUFFER_FULL;
+        return writer->err;
+    }
+    memcpy(writer->buf + writer->pos, buf, len);
+    writer->pos += len;
+    return 0;
+}
+
+static int json_write_char(CSIJsonWriter* writer, char c) { return json_write_raw(writer, &c, 1); }
+
+// Writes the comma separating a value from the previous element, if any.
+static int json_begin_value(CSIJsonWriter* writer) {
+    if (writer->err) {
+        return writer->err;
+    }
+    if (writer->after_key) {
+        writer->after_key = false;
+        return 0;
+    }
+    if (writer->depth == 0) {
+        return 0;
+    }
+    uint64_t bit = 1ULL << (writer->depth - 1);
+    if (writer->has_element & bit) {
+        return json_write_char(writer, ',');
+    }
+    writer->has_element |= bit;
+    return 0;
+}
+
+static int json_begin_container(CSIJsonWriter* writer, char open) {
+    int err = json_begin_value(writer);
+    if (err) {
+        return err;
+    }
+    if (writer->depth >= CSI_JSON_MAX_DEPTH) {
+        writer->err = CSI_ERROR_JSON_TOO_DEEP;
+        return writer->err;
+    }
+    err = json_write_char(writer, open);
+    if (err) {
+        return err;
+    }
+    writer->depth++;
+    writer->has_element &= ~(1ULL << (writer->depth - 1));
+    return 0;
+}
+
+static int json_end_container(CSIJsonWriter* writer, char close) {
+    if (writer->err) {
+        return writer->err;
+    }
+    if (writer->depth == 0 || writer->after_key) {
+        writer->err = CSI_ERROR_JSON;
+        return writer->err;
+    }
+    writer->depth--;
+    return json_write_char(writer, close);
+}
+
+void csi_json_writer_init(CSIJsonWriter* writer, void* buf, size_t len) {
+    memset(writer, 0, sizeof(CSIJsonWriter));
+    writer->buf = buf;
+    writer->len = len;
+}
+
+int csi_json_writer_finish(CSIJsonWriter* writer, size_t* len) {
+    if (writer->err) {
+        return writer->err;
+    }
+    if (writer->depth != 0 || writer->after_key) {
+        return CSI_ERROR_JSON;
+    }
+    *len = writer->pos;
+    return 0;
+}
+
+int csi_json_begin_object(CSIJsonWriter* writer) { return json_begin_container(writer, '{'); }
+
+int csi_json_end_object(CSIJsonWriter* writer) { return json_end_container(writer, '}'); }
+
+int csi_json_begin_array(CSIJsonWriter* writer) { return json_begin_container(writer, '['); }
+
+int csi_json_end_array(CSIJsonWriter* writer) { return json_end_container(writer, ']'); }
+
+int csi_json_write_key(CSIJsonWriter* writer, const char* key) {
+    if (writer->err) {
+        return writer->err;
+    }
+    if (writer->depth == 0 || writer->after_key) {
+        writer->err = CSI_ERROR_JSON;
+        return writer->err;
+    }
+    int err = csi_json_write_string(writer, key, strlen(key));
+    if (err) {
+        return err;
+    }
+    err = json_write_char(writer, ':');
+    if (err) {
+        return err;
+    }
+    writer->after_key = true;
+    return 0;
+}
+
+static int json_write_digits(CSIJsonWriter* writer, uint64_t value) {
+    char digits[20];
+    size_t len = 0;
+    do {
+        digits[sizeof(digits) - 1 - len] = '0' + (char)(value % 10);
+        value /= 10;
+        len++;
+    } while (value != 0);
+    return json_write_raw(writer, digits + sizeof(digits) - len, len);
+}
+
+int csi_json_write_uint(CSIJsonWriter* writer, uint64_t value) {
+    int err = json_begin_value(writer);
+    if (err) {
+        return err;
+    }
+    return json_write_digits(writer, value);
+}
+
+int csi_json_write_int(CSIJsonWriter* writer, int64_t value) {
+    int err = json_begin_value(writer);
+    if (err) {
+        return err;
+    }
+    if (value >= 0) {
+        return json_write_digits(writer, (uint64_t)value);
+    }
+    err = json_write_char(writer, '-');
+    if (err) {
+        return err;
+    }
+    // negate in unsigned arithmetic, as -INT64_MIN overflows
+    return json_write_digits(writer, ~(uint64_t)value + 1);
+}
+
+int csi_json_write_bool(CSIJsonWriter* writer, bool value) {
+    int err = json_begin_value(writer);
+    if (err) {
+        return err;
+    }
+    return value ? json_write_raw(writer, "true", 4) : json_write_raw(writer, "false", 5);
+}
+
+int csi_json_write_null(CSIJsonWriter* writer) {
+    int err = json_begin_value(writer);
+    if (err) {
+        return err;
+    }
+    return json_write_raw(writer, "null", 4);
+}
+
+static const char g_hex_digits[] = "0123456789abcdef";
+
+int csi_json_write_string(CSIJsonWriter* writer, const char* str, size_t len) {
+    int err = json_begin_value(writer);
+    if (err) {
+        return err;
+    }
+    err = json_write_char(writer, '"');
+    for (size_t i = 0; i < len && err == 0; i++) {
+        uint8_t c = (uint8_t)str[i];
+        if (c == '"' || c == '\\') {
+            char escaped[2] = {'\\', (char)c};
+            err = json_write_raw(writer, escaped, 2);
+        } else if (c == '\n') {
+            err = json_write_raw(writer, "\\n", 2);
+        } else if (c == '\r') {
+            err = json_write_raw(writer, "\\r", 2);
+        } else if (c == '\t') {
+            err = json_write_raw(writer, "\\t", 2);
+        } else if (c < 0x20) {
+            char escaped[6] = {'\\', 'u', '0', '0', g_hex_digits[c >> 4], g_hex_digits[c & 0xF]};
+            err = json_write_raw(writer, escaped, 6);
+        } else {
+            err = json_write_char(writer, (char)c);
+        }
+    }
+    if (err) {
+        return err;
+    }
+    return json_write_char(writer, '"');
+}
+
+int csi_json_write_bytes(CSIJsonWriter* writer, const void* bytes, size_t len) {
+    int err = csi_json_begin_array(writer);
+    for (size_t i = 0; i < len && err == 0; i++) {
+        err = csi_json_write_uint(writer, ((const uint8_t*)bytes)[i]);
+    }
+    if (err) {
+        return err;
+    }
+    return csi_json_end_array(writer);
+}
+
+int csi_json_write_hex(CSIJsonWriter* writer, const void* bytes, size_t len) {
+    int err = json_begin_value(writer);
+    if (err) {
+        return err;
+    }
+    err = json_write_char(writer, '"');
+    for (size_t i = 0; i < len && err == 0; i++) {
+        uint8_t b = ((const uint8_t*)bytes)[i];
+        char hex[2] = {g_hex_digits[b >> 4], g_hex_digits[b & 0xF]};
+        err = json_write_raw(writer, hex, 2);
+    }
+    if (err) {
+        return err;
+    }
+    return json_write_char(writer, '"');
+}
+
+int csi_json_begin_request(CSIJsonWriter* writer, const char* method) {
+    csi_json_begin_response(writer, method);
+    return csi_json_begin_object(writer);
+}
+
+int csi_json_end_request(CSIJsonWriter* writer) {
+    csi_json_end_object(writer);
+    return csi_json_end_response(writer);
+}
+
+int csi_json_begin_response(CSIJsonWriter* writer, const char* method) {
+    csi_json_begin_object(writer);
+    return csi_json_write_key(writer, method);
+}
+
+int csi_json_end_response(CSIJsonWriter* writer) { return csi_json_end_object(writer); }
+
+void csi_json_reader_init(CSIJsonReader* reader, const void* buf, size_t len) {
+    memset(reader, 0, sizeof(CSIJsonReader));
+    reader->buf = buf;
+    reader->len = len;
+}
+
+static void json_skip_whitespace(CSIJsonReader* reader) {
+    while (reader->pos < reader->len) {
+        uint8_t c = reader->buf[reader->pos];
+        if (c != ' ' && c != '\t' && c != '\n' && c != '\r') {
+            break;
+        }
+        reader->pos++;
+    }
+}
+
+// Returns the next non-whitespace byte without consuming it, or 0 at the end.
+static uint8_t json_peek(CSIJsonReader* reader) {
+    json_skip_whitespace(reader);
+    return reader->pos < reader->len ? reader->buf[reader->pos] : 0;
+}
+
+static int json_expect(CSIJsonReader* reader, uint8_t c) {
+    if (json_peek(reader) != c) {
+        return CSI_ERROR_JSON;
+    }
+    reader->pos++;
+    return 0;
+}
+
+static int json_expect_literal(CSIJsonReader* reader, const char* literal) {
+    size_t len = strlen(literal);
+    json_skip_whitespace(reader);
+    if (reader->len - reader->pos < len || memcmp(reader->buf + reader->pos, literal, len) != 0) {
+        return CSI_ERROR_JSON;
+    }
+    reader->pos += len;
+    return 0;
+}
+
+static int json_read_begin_container(CSIJsonReader* reader, uint8_t open) {
+    if (reader->depth >= CSI_JSON_MAX_DEPTH) {
+        return CSI_ERROR_JSON_TOO_DEEP;
+    }
+    int err = json_expect(reader, open);
+    if (err) {
+        return err;
+    }
+    reader->depth++;
+    reader->has_element &= ~(1ULL << (reader->depth - 1));
+    return 0;
+}
+
+// Consumes the end of the current container, or the comma before its next element.
+static int json_read_separator(CSIJsonReader* reader, uint8_t close, bool* end) {
+    if (reader->depth == 0) {
+        return CSI_ERROR_JSON;
+    }
+    uint64_t bit = 1ULL << (reader->depth - 1);
+    if (json_peek(reader) == close) {
+        reader->pos++;
+        reader->depth--;
+        *end = true;
+        return 0;
+    }
+    *end = false;
+    if (reader->has_element & bit) {
+        return json_expect(reader, ',');
+    }
+    reader->has_element |= bit;
+    return 0;
+}
+
+int csi_json_read_begin_object(CSIJsonReader* reader) { return json_read_begin_container(reader, '{'); }
+
+int csi_json_read_key(CSIJsonReader* reader, const char** key, size_t* key_len, bool* end) {
+    int err = json_read_separator(reader, '}', end);
+    if (err || *end) {
+        return err;
+    }
+    err = json_expect(reader, '"');
+    if (err) {
+        return err;
+    }
+    size_t start = reader->pos;
+    while (reader->pos < reader->len && reader->buf[reader->pos] != '"') {
+        if (reader->buf[reader->pos] == '\\' || reader->buf[reader->pos] < 0x20) {
+            return CSI_ERROR_JSON;
+        }
+        reader->pos++;
+    }
+    if (reader->pos == reader->len) {
+        return CSI_ERROR_JSON;
+    }
+    *key = (const char*)reader->buf + start;
+    *key_len = reader->pos - start;
+    reader->pos++;
+    return json_expect(reader, ':');
+}
+
+int csi_json_read_begin_array(CSIJsonReader* reader) { return json_read_begin_container(reader, '['); }
+
+int csi_json_read_next(CSIJsonReader* reader, bool* end) { return json_read_separator(reader, ']', end); }
+
+// Reads the digits of a JSON integer without sign. Fractions and exponents are rejected.
+static int json_read_digits(CSIJsonReader* reader, uint64_t* value) {
+    const uint8_t* buf = reader->buf;
+    size_t start = reader->pos;
+    uint64_t result = 0;
+    while (reader->pos < reader->len && buf[reader->pos] >= '0' && buf[reader->pos] <= '9') {
+        uint64_t digit = buf[reader->pos] - '0';
+        if (result > (UINT64_MAX - digit) / 10) {
+            return CSI_ERROR_JSON;
+        }
+        result = result * 10 + digit;
+        reader->pos++;
+    }
+    size_t len = reader->pos - start;
+    // no digits, or a leading zero
+    if (len == 0 || (len > 1 && buf[start] == '0')) {
+        return CSI_ERROR_JSON;
+    }
+    if (reader->pos < reader->len) {
+        uint8_t c = buf[reader->pos];
+        if (c == '.' || c == 'e' || c == 'E') {
+            return CSI_ERROR_JSON;
+        }
+    }
+    *value = result;
+    return 0;
+}
+
+int csi_json_read_uint(CSIJsonReader* reader, uint64_t* value) {
+    json_skip_whitespace(reader);
+    return json_read_digits(reader, value);
+}
+
+int csi_json_read_int(CSIJsonReader* reader, int64_t* value) {
+    bool negative = json_peek(reader) == '-';
+    if (negative) {
+        reader->pos++;
+    }
+    uint64_t magnitude = 0;
+    int err = json_read_digits(reader, &magnitude);
+    if (err) {
+        return err;
+    }
+    if (negative) {
+        if (magnitude > (uint64_t)INT64_MAX + 1) {
+            return CSI_ERROR_JSON;
+        }
+        *value = (int64_t)(~magnitude + 1);
+    } else {
+        if (magnitude > INT64_MAX) {
+            return CSI_ERROR_JSON;
+        }
+        *value = (int64_t)magnitude;
+    }
+    return 0;
+}
+
+int csi_json_read_bool(CSIJsonReader* reader, bool* value) {
+    uint8_t c = json_peek(reader);
+    if (c == 't') {
+        *value = true;
+        return json_expect_literal(reader, "true");
+    }
+    *value = false;
+    return json_expect_literal(reader, "false");
+}
+
+int csi_json_read_null(CSIJsonReader* reader, bool* is_null) {
+    *is_null = json_peek(reader) == 'n';
+    if (*is_null) {
+        return json_expect_literal(reader, "null");
+    }
+    return 0;
+}
+
+static int json_hex_value(uint8_t c) {
+    if (c >= '0' && c <= '9') return c - '0';
+    if (c >= 'a' && c <= 'f') return c - 'a' + 10;
+    if (c >= 'A' && c <= 'F') return c - 'A' + 10;
+    return -1;
+}
+
+// Reads the 4 hex digits of a \u escape sequence.
+static int json_read_unicode_escape(CSIJsonReader* reader, uint32_t* code) {
+    if (reader->len - reader->pos < 4) {
+        return CSI_ERROR_JSON;
+    }
+    *code = 0;
+    for (size_t i = 0; i < 4; i++) {
+        int v = json_hex_value(reader->buf[reader->pos++]);
+        if (v < 0) {
+            return CSI_ERROR_JSON;
+        }
+        *code = (*code << 4) | (uint32_t)v;
+    }
+    return 0;
+}
+
+// Reads a string into `buf` if it's not NULL, otherwise only checks and skips it.
+static int json_read_string(CSIJsonReader* reader, char* buf, size_t buf_len, size_t* len) {
+    int err = json_expect(reader, '"');
+    if (err) {
+        return err;
+    }
+    size_t out = 0;
+    while (true) {
+        if (reader->pos >= reader->len) {
+            return CSI_ERROR_JSON;
+        }
+        uint8_t c = reader->buf[reader->pos++];
+        if (c == '"') {
+            break;
+        }
+        if (c < 0x20) {
+            return CSI_ERROR_JSON;
+        }
+        uint8_t utf8[4] = {c};
+        size_t utf8_len = 1;
+        if (c == '\\') {
+            if (reader->pos >= reader->len) {
+                return CSI_ERROR_JSON;
+            }
+            c = reader->buf[reader->pos++];
+            switch (c) {
+                case '"':
+                case '\\':
+                case '/':
+                    utf8[0] = c;
+                    break;
+                case 'b':
+                    utf8[0] = '\b';
+                    break;
+                case 'f':
+                    utf8[0] = '\f';
+                    break;
+                case 'n':
+                    utf8[0] = '\n';
+                    break;
+                case 'r':
+                    utf8[0] = '\r';
+                    break;
+                case 't':
+                    utf8[0] = '\t';
+                    break;
+                case 'u': {
+                    uint32_t code = 0;
+                    err = json_read_unicode_escape(reader, &code);
+                    if (err) {
+                        return err;
+                    }
+                    if (code >= 0xD800 && code < 0xDC00) {
+                        // a high surrogate must be followed by a low one
+                        uint32_t low = 0;
+                        if (reader->len - reader->pos < 2 || reader->buf[reader->pos] != '\\' ||
+                            reader->buf[reader->pos + 1] != 'u') {
+                            return CSI_ERROR_JSON;
+                        }
+                        reader->pos += 2;
+                        err = json_read_unicode_escape(reader, &low);
+                        if (err) {
+                            return err;
+                        }
+                        if (low < 0xDC00 || low >= 0xE000) {
+                            return CSI_ERROR_JSON;
+                        }
+                        code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                    } else if (code >= 0xDC00 && code < 0xE000) {
+                        return CSI_ERROR_JSON;
+                    }
+                    if (code < 0x80) {
+                        utf8[0] = (uint8_t)code;
+                    } else if (code < 0x800) {
+                        utf8[0] = (uint8_t)(0xC0 | (code >> 6));
+                        utf8[1] = (uint8_t)(0x80 | (code & 0x3F));
+                        utf8_len = 2;
+                    } else if (code < 0x10000) {
+                        utf8[0] = (uint8_t)(0xE0 | (code >> 12));
+                        utf8[1] = (uint8_t)(0x80 | ((code >> 6) & 0x3F));
+                        utf8[2] = (uint8_t)(0x80 | (code & 0x3F));
+                        utf8_len = 3;
+                    } else {
+                        utf8[0] = (uint8_t)(0xF0 | (code >> 18));
+                        utf8[1] = (uint8_t)(0x80 | ((code >> 12) & 0x3F));
+                        utf8[2] = (uint8_t)(0x80 | ((code >> 6) & 0x3F));
+                        utf8[3] = (uint8_t)(0x80 | (code & 0x3F));
+                        utf8_len = 4;
+                    }
+                    break;
+                }
+                default:
+                    return CSI_ERROR_JSON;
+            }
+        }
+        if (buf != NULL) {
+            if (utf8_len > buf_len - out) {
+                return CSI_ERROR_JSON_BUFFER_FULL;
+            }
+            memcpy(buf + out, utf8, utf8_len);
+        }
+        out += utf8_len;
+    }
+    *len = out;
+    return 0;
+}
+
+int csi_json_read_string(CSIJsonReader* reader, char* buf, size_t buf_len, size_t* len) {
+    return json_read_string(reader, buf, buf_len, len);
+}
+
+int csi_json_read_bytes(CSIJsonReader* reader, void* buf, size_t buf_len, size_t* len) {
+    int err = csi_json_read_begin_array(reader);
+    size_t out = 0;
+    while (err == 0) {
+        bool end = false;
+        err = csi_json_read_next(reader, &end);
+        if (err || end) {
+            break;
+        }
+        uint64_t value = 0;
+        err = csi_json_read_uint(reader, &value);
+        if (err) {
+            break;
+        }
+        if (value > 0xFF) {
+            return CSI_ERROR_JSON;
+        }
+        if (out == buf_len) {
+            return CSI_ERROR_JSON_BUFFER_FULL;
+        }
+        ((uint8_t*)buf)[out++] = (uint8_t)value;
+    }
+    if (err) {
+        return err;
+    }
+    *len = out;
+    return 0;
+}
+
+int csi_json_read_hex(CSIJsonReader* reader, void* buf, size_t buf_len, size_t* len) {
+    int err = json_expect(reader, '"');
+    if (err) {
+        return err;
+    }
+    size_t out = 0;
+    while (reader->pos < reader->len && reader->buf[reader->pos] != '"') {
+        if (reader->len - reader->pos < 2) {
+            return CSI_ERROR_JSON;
+        }
+        int high = json_hex_value(reader->buf[reader->pos]);
+        int low = json_hex_value(reader->buf[reader->pos + 1]);
+        if (high < 0 || low < 0) {
+            return CSI_ERROR_JSON;
+        }
+        if (out == buf_len) {
+            return CSI_ERROR_JSON_BUFFER_FULL;
+        }
+        ((uint8_t*)buf)[out++] = (uint8_t)((high << 4) | low);
+        reader->pos += 2;
+    }
+    err = json_expect(reader, '"');
+    if (err) {
+        return err;
+    }
+    *len = out;
+    return 0;
+}
+
+// Skips a number, which may have a fraction or an exponent.
+static int json_skip_number(CSIJsonReader* reader) {
+    if (json_peek(reader) == '-') {
+        reader->pos++;
+    }
+    size_t start = reader->pos;
+    while (reader->pos < reader->len) {
+        uint8_t c = reader->buf[reader->pos];
+        if (!((c >= '0' && c <= '9') || c == '.' || c == 'e' || c == 'E' || c == '+' || c == '-')) {
+            break;
+        }
+        reader->pos++;
+    }
+    if (reader->pos == start || reader->buf[start] < '0' || reader->buf[start] > '9') {
+        return CSI_ERROR_JSON;
+    }
+    return 0;
+}
+
+int csi_json_skip_value(CSIJsonReader* reader) {
+    int err = 0;
+    bool end = false;
+    size_t len = 0;
+    switch (json_peek(reader)) {
+        case '{':
+            err = csi_json_read_begin_object(reader);
+            while (err == 0) {
+                const char* key = NULL;
+                err = csi_json_read_key(reader, &key, &len, &end);
+                if (err || end) {
+                    break;
+                }
+                err = csi_json_skip_value(reader);
+            }
+            return err;
+        case '[':
+            err = csi_json_read_begin_array(reader);
+            while (err == 0) {
+                err = csi_json_read_next(reader, &end);
+                if (err || end) {
+                    break;
+                }
+                err = csi_json_skip_value(reader);
+            }
+            return err;
+        case '"':
+            return json_read_string(reader, NULL, 0, &len);
+        case 't':
+            return json_expect_literal(reader, "true");
+        case 'f':
+            return json_expect_literal(reader, "false");
+        case 'n':
+            return json_expect_literal(reader, "null");
+        default:
+            return json_skip_number(reader);
+    }
+}
+
+int csi_json_read_begin_envelope(CSIJsonReader* reader, const char** method, size_t* method_len) {
+    int err = csi_json_read_begin_object(reader);
+    if (err) {
+        return err;
+    }
+    bool end = false;
+    err = csi_json_read_key(reader, method, method_len, &end);
+    if (err) {
+        return err;
+    }
+    return end ? CSI_ERROR_JSON : 0;
+}
+
+int csi_json_read_end_envelope(CSIJsonReader* reader) {
+    bool end = false;
+    const char* key = NULL;
+    size_t key_len = 0;
+    int err = csi_json_read_key(reader, &key, &key_len, &end);
+    if (err) {
+        return err;
+    }
+    json_skip_whitespace(reader);
+    if (!end || reader->depth != 0 || reader->pos != reader->len) {
+        return CSI_ERROR_JSON;
+    }
+    return 0;
+}
+
+bool csi_json_key_equals(const char* key, size_t key_len, const char* expected) {
+    return strlen(expected) == key_len && memcmp(key, expected, key_len) == 0;
+}
+
+void* csi_scratch_alloc(CSIScratch* scratch, size_t len) {
+    if (len > scratch->len - scratch->pos) {
+        return NULL;
+    }
+    void* ptr = scratch->buf + scratch->pos;
+    scratch->pos += len;
+    return ptr;
+}
+
+int csi_json_write_raw(CSIJsonWriter* writer, const char* json, size_t len) {
+    int err = json_begin_value(writer);
+    if (err) {
+        return err;
+    }
+    return json_write_raw(writer, json, len);
+}
+
+int csi_json_read_uint_max(CSIJsonReader* reader, uint64_t max, uint64_t* value) {
+    int err = csi_json_read_uint(reader, value);
+    if (err == 0 && *value > max) {
+        err = CSI_ERROR_JSON;
+    }
+    return err;
+}
+
+int csi_json_read_int_range(CSIJsonReader* reader, int64_t min, int64_t max, int64_t* value) {
+    int err = csi_json_read_int(reader, value);
+    if (err == 0 && (*value < min || *value > max)) {
+        err = CSI_ERROR_JSON;
+    }
+    return err;
+}
+
+int csi_json_read_unit(CSIJsonReader* reader) { return json_expect_literal(reader, "null"); }
+
+int csi_json_read_end_object(CSIJsonReader* reader) {
+    const char* key = NULL;
+    size_t key_len = 0;
+    bool end = false;
+    int err = csi_json_read_key(reader, &key, &key_len, &end);
+    if (err == 0 && !end) {
+        err = CSI_ERROR_JSON;
+    }
+    return err;
+}
+
+int csi_json_read_bytes_in(CSIJsonReader* reader, CSIScratch* scratch, CSIBytes* value) {
+    uint8_t* buf = scratch->buf + scratch->pos;
+    int err = csi_json_read_bytes(reader, buf, scratch->len - scratch->pos, &value->len);
+    if (err == 0) {
+        value->ptr = buf;
+        scratch->pos += value->len;
+    }
+    return err;
+}
+
+int csi_json_read_string_in(CSIJsonReader* reader, CSIScratch* scratch, CSIString* value) {
+    char* buf = (char*)scratch->buf + scratch->pos;
+    int err = csi_json_read_string(reader, buf, scratch->len - scratch->pos, &value->len);
+    if (err == 0) {
+        value->ptr = buf;
+        scratch->pos += value->len;
+    }
+    return err;
+}
+
+int csi_json_read_raw_in(CSIJsonReader* reader, CSIScratch* scratch, CSIRawJson* value) {
+    json_skip_whitespace(reader);
+    size_t start = reader->pos;
+    int err = csi_json_skip_value(reader);
+    if (err) {
+        return err;
+    }
+    size_t len = reader->pos - start;
+    void* buf = csi_scratch_alloc(scratch, len);
+    if (buf == NULL) {
+        return CSI_ERROR_JSON_BUFFER_FULL;
+    }
+    memcpy(buf, reader->buf + start, len);
+    value->ptr = buf;
+    value->len = len;
+    return 0;
+}
+
+int csi_json_call(CSIChannel* channel, uint64_t method_id, CSIJsonWriter* writer, const char* method,
+                  CSIResponsePacket* response, CSIJsonReader* reader) {
+    size_t len = 0;
+    int err = csi_json_writer_finish(writer, &len);
+    if (err) {
+        return err;
+    }
+    CSIRequestPacket request = {.version = 0, .method_id = method_id, .payload_len = len, .payload = writer->buf};
+    err = csi_call(channel, &request, response);
+    if (err) {
+        return err;
+    }
+    csi_json_reader_init(reader, response->payload, response->payload_len);
+    const char* name = NULL;
+    size_t name_len = 0;
+    err = csi_json_read_begin_envelope(reader, &name, &name_len);
+    if (err == 0 && !csi_json_key_equals(name, name_len, method)) {
+        err = CSI_ERROR_JSON;
+    }
+    return err;
+}
+
+int csi_json_set_response_payload(CSIJsonWriter* writer, CSIResponsePacket* response) {
+    size_t len = 0;
+    int err = csi_json_writer_finish(writer, &len);
+    if (err) {
+        return err;
+    }
+    response->payload_len = len;
+    csi_server_malloc_response_payload(response);
+    if (len > 0) {
+        memcpy(response->payload, writer->buf, len);
+    }
+    return 0;
+}
@@ -27,6 +27,15 @@ typedef enum CSIErrorCode {
     CSI_ERROR_IOBUF_TOO_SMALL,
     CSI_ERROR_FA_TOO_MANY_BLOCK,
     CSI_ERROR_FA_NOT_ALIGNED,
+    CSI_ERROR_VLQ_NOT_CANONICAL,
+    CSI_ERROR_JSON,
+    CSI_ERROR_JSON_BUFFER_FULL,
+    CSI_ERROR_JSON_TOO_DEEP,
+    CSI_ERROR_TOO_MANY_METHODS,
+    // The peer closed the channel between two packets.
+    CSI_ERROR_EOF,
+    // The peer closed the channel in the middle of a packet.
+    CSI_ERROR_UNEXPECTED_EOF,
 } CSIErrorCode;
 
 typedef void* (*CSIMalloc)(size_t len);
@@ -83,12 +92,22 @@ void csi_init_panic(CSIPanic panic);
 
 void csi_default_panic(int exit_code);
 
+/**
+ * Reject received packets whose VLQs are not canonical, i.e. not in their shortest form,
+ * e.g. `0x80 0x00` for 0. Such packets fail with CSI_ERROR_VLQ_NOT_CANONICAL.
+ * Packets are always sent with canonical VLQs, so when both sides enable this, every packet has
+ * exactly one byte representation. Disabled by default.
+ *
+ * @param canonical Whether to reject non-canonical VLQs
+ */
+void csi_init_canonical_vlq(bool canonical);
+
 /**
  * Read data interface
  * @param ctx: Implementation-specific context (similar to 'this' in C++)
  * @param buf: Destination buffer to store read data
  * @param len: length of `buf`
- * @param read_len: Number of bytes actually read (output parameter)
+ * @param read_len: Number of bytes actually read (output parameter), 0 at the end of the stream
  * @return 0 for success, non-zero for failure
  */
 typedef int (*CSIRead)(void* ctx, void* buf, size_t len, size_t* read_len);
@@ -170,6 +189,49 @@ int csi_spawn_server(uint64_t index, uint64_t source, size_t offset, size_t leng
 int csi_spawn_cell_server(void* code_hash, uint64_t hash_type, const char* argv[], int argc,
                           CSIChannel* client_channel);
 
+/**
+ * A spawned server, with the client side of its channel.
+ *
+ * - pid: The process ID of the server
+ * - read_fd: The pipe responses are read from
+ * - write_fd: The pipe requests are written to
+ * - channel: The channel to call the server with
+ * - closed: Whether the pipes are closed, see csi_close_server_handle
+ */
+typedef struct CSIServerHandle {
+    uint64_t pid;
+    uint64_t read_fd;
+    uint64_t write_fd;
+    CSIChannel channel;
+    bool closed;
+} CSIServerHandle;
+
+/**
+ * Same as csi_spawn_server and csi_spawn_cell_server, returning a handle which can close the channel and wait for
+ * the server to exit.
+ */
+int csi_spawn_server_handle(uint64_t index, uint64_t source, size_t offset, size_t length, const char* argv[],
+                            int argc, CSIServerHandle* server);
+int csi_spawn_cell_server_handle(void* code_hash, uint64_t hash_type, const char* argv[], int argc,
+                                 CSIServerHandle* server);
+
+/**
+ * Closes the pipes of the server's channel and frees its buffers. A server running csi_run_server sees the end of
+ * the stream after the last request, and returns CSI_SUCCESS. Both pipes are closed even if closing the first one
+ * fails, and the handle is marked closed either way. Closing a closed handle does nothing.
+ *
+ * @return 0 for success, or the first error of the close syscalls
+ */
+int csi_close_server_handle(CSIServerHandle* server);
+
+/**
+ * Closes the channel if it isn't closed yet, and waits for the server to exit.
+ *
+ * @param exit_code: The exit code of the server (output parameter)
+ * @return 0 for success, or the error of the close or wait syscall
+ */
+int csi_wait_server_handle(CSIServerHandle* server, int8_t* exit_code);
+
 /**
  * Frees the resources associated with a CSIChannel.
  *
@@ -222,13 +284,266 @@ void csi_server_malloc_response_payload(CSIResponsePacket* response);
  * @param serve: The callback function that will handle each incoming request
  * @return 0 for success, non-zero for failure
  *
- * This function enters an infinite loop that:
+ * This function enters a loop that:
  * 1. Receives requests from clients
  * 2. Calls the provided serve callback to process each request
  * 3. Sends responses back to clients
  *
- * The server will continue running until an error occurs or the process is terminated.
+ * The server will continue running until an error occurs, or the client closes the channel. Returns CSI_SUCCESS
+ * when the channel is closed between two requests, e.g. by csi_close_server_handle or by the client exiting.
  */
 int csi_run_server(CSIServe serve);
 
+/**
+ * Error code of the responses to requests whose method is unknown, the same as `ProtocolErrorCode::UnknownMethod`
+ * in Rust. csi_call() returns it on client side.
+ */
+#define CSI_UNKNOWN_METHOD 31
+
+#ifndef CSI_MAX_METHODS
+#define CSI_MAX_METHODS 32
+#endif
+
+/**
+ * Handler of a method registered with csi_register_method. Same as `CSIServe`, with the context passed at
+ * registration.
+ */
+typedef int (*CSIMethodHandler)(void* ctx, const CSIRequestPacket* request, CSIResponsePacket* response);
+
+/**
+ * Registers the handler of requests with `method_id`, for csi_dispatch. Registering a method again replaces its
+ * handler and context.
+ *
+ * @param method_id: The method id of the requests to handle
+ * @param handler: The handler, called with `ctx`
+ * @param ctx: Any user data, e.g. the state of the service
+ * @return 0 for success, CSI_ERROR_TOO_MANY_METHODS if CSI_MAX_METHODS methods are registered already
+ */
+int csi_register_method(uint64_t method_id, CSIMethodHandler handler, void* ctx);
+
+/**
+ * A `CSIServe` callback dispatching requests to the handlers registered with csi_register_method, by method id.
+ * Requests with an unknown method id are answered with the error code CSI_UNKNOWN_METHOD, and the server keeps
+ * running:
+ *     csi_register_method(1, handle_sum, &state);
+ *     csi_register_method(2, handle_reset, &state);
+ *     return csi_run_server(csi_dispatch);
+ *
+ * Rust clients always send method id 0, and tell methods apart by the payload. Use the dispatcher of the
+ * generated headers to serve them instead, see `<SERVICE>_C_HEADER` of `#[ckb_script_ipc::service]`.
+ */
+int csi_dispatch(const CSIRequestPacket* request, CSIResponsePacket* response);
+
+/**
+ * JSON payloads.
+ *
+ * Rust services defined by `#[ckb_script_ipc::service]` exchange payloads encoded by serde_json. A request is an
+ * object with a single key, the method name in CamelCase, whose value is an object of the arguments:
+ *     {"TestVec":{"vec":[1,2,3]}}
+ * A response is an object with the same key, whose value is the returned value:
+ *     {"TestVec":null}
+ * `Vec<u8>` and `[u8; N]` are arrays of numbers, `Option` is `null` or the value, and `Result` is {"Ok":...} or
+ * {"Err":...}.
+ *
+ * The functions below build and parse such payloads in caller-provided buffers, without allocating. Nesting is
+ * limited to 64 levels.
+ */
+#define CSI_JSON_MAX_DEPTH 64
+
+typedef struct CSIJsonWriter {
+    uint8_t* buf;
+    size_t len;
+    size_t pos;
+    size_t depth;
+    // Bit `i` is set when the object or array at depth `i + 1` already has an element.
+    uint64_t has_element;
+    // A key was just written, its value follows without a comma.
+    bool after_key;
+    // The first error, later writes are ignored.
+    int err;
+} CSIJsonWriter;
+
+/**
+ * Initializes a writer encoding into `buf`.
+ */
+void csi_json_writer_init(CSIJsonWriter* writer, void* buf, size_t len);
+
+/**
+ * Returns the length of the encoded JSON. Fails with the first error of the previous writes, or CSI_ERROR_JSON if
+ * an object or array is not ended.
+ *
+ * Every write function returns the first error of the writer too, so errors may be checked once here. Writes
+ * fail with CSI_ERROR_JSON_BUFFER_FULL when `buf` is too small.
+ */
+int csi_json_writer_finish(CSIJsonWriter* writer, size_t* len);
+
+int csi_json_begin_object(CSIJsonWriter* writer);
+int csi_json_end_object(CSIJsonWriter* writer);
+int csi_json_begin_array(CSIJsonWriter* writer);
+int csi_json_end_array(CSIJsonWriter* writer);
+/**
+ * Writes the key of the next field of an object. Its value must be written next.
+ */
+int csi_json_write_key(CSIJsonWriter* writer, const char* key);
+int csi_json_write_uint(CSIJsonWriter* writer, uint64_t value);
+int csi_json_write_int(CSIJsonWriter* writer, int64_t value);
+int csi_json_write_bool(CSIJsonWriter* writer, bool value);
+int csi_json_write_null(CSIJsonWriter* writer);
+/**
+ * Writes a UTF-8 string of `len` bytes, escaping it as needed.
+ */
+int csi_json_write_string(CSIJsonWriter* writer, const char* str, size_t len);
+/**
+ * Writes bytes as an array of numbers, like serde_json does for `Vec<u8>` and `[u8; N]`.
+ */
+int csi_json_write_bytes(CSIJsonWriter* writer, const void* bytes, size_t len);
+/**
+ * Writes bytes as a lowercase hex string, like `serde_with::hex::Hex`.
+ */
+int csi_json_write_hex(CSIJsonWriter* writer, const void* bytes, size_t len);
+
+/**
+ * Begins a request to `method`, e.g. "TestVec". The arguments follow as fields, see csi_json_write_key.
+ */
+int csi_json_begin_request(CSIJsonWriter* writer, const char* method);
+int csi_json_end_request(CSIJsonWriter* writer);
+/**
+ * Begins a response of `method`. Exactly one value, the returned one, follows.
+ */
+int csi_json_begin_response(CSIJsonWriter* writer, const char* method);
+int csi_json_end_response(CSIJsonWriter* writer);
+
+typedef struct CSIJsonReader {
+    const uint8_t* buf;
+    size_t len;
+    size_t pos;
+    size_t depth;
+    // Bit `i` is set when the object or array at depth `i + 1` already had an element.
+    uint64_t has_element;
+} CSIJsonReader;
+
+/**
+ * Initializes a reader decoding `buf`. Read functions fail with CSI_ERROR_JSON on invalid JSON, or when the next
+ * value has another type than the expected one.
+ */
+void csi_json_reader_init(CSIJsonReader* reader, const void* buf, size_t len);
+
+int csi_json_read_begin_object(CSIJsonReader* reader);
+/**
+ * Reads the key of the next field of an object, or its end. On return, `end` tells whether the object ended;
+ * otherwise `key` points to the `key_len` bytes of the key inside the buffer, and its value must be read or
+ * skipped next. Keys with escape sequences are not supported.
+ */
+int csi_json_read_key(CSIJsonReader* reader, const char** key, size_t* key_len, bool* end);
+int csi_json_read_begin_array(CSIJsonReader* reader);
+/**
+ * Moves to the next element of an array, or its end, see csi_json_read_key.
+ */
+int csi_json_read_next(CSIJsonReader* reader, bool* end);
+int csi_json_read_uint(CSIJsonReader* reader, uint64_t* value);
+int csi_json_read_int(CSIJsonReader* reader, int64_t* value);
+int csi_json_read_bool(CSIJsonReader* reader, bool* value);
+/**
+ * Reads a `null` if it's next, e.g. for a `None`. Otherwise, `is_null` is set to false and nothing is read.
+ */
+int csi_json_read_null(CSIJsonReader* reader, bool* is_null);
+/**
+ * Reads and unescapes a string into `buf`, setting `len` to its length in bytes. It isn't NUL-terminated. Fails
+ * with CSI_ERROR_JSON_BUFFER_FULL if it doesn't fit.
+ */
+int csi_json_read_string(CSIJsonReader* reader, char* buf, size_t buf_len, size_t* len);
+/**
+ * Reads an array of numbers from 0 to 255 into `buf`, see csi_json_write_bytes.
+ */
+int csi_json_read_bytes(CSIJsonReader* reader, void* buf, size_t buf_len, size_t* len);
+/**
+ * Reads a hex string into `buf`, see csi_json_write_hex. Both cases are accepted.
+ */
+int csi_json_read_hex(CSIJsonReader* reader, void* buf, size_t buf_len, size_t* len);
+/**
+ * Skips the next value, e.g. of an unknown field.
+ */
+int csi_json_skip_value(CSIJsonReader* reader);
+
+/**
+ * Begins reading a request or a response, setting `method` to the method name, e.g. "TestVec". For a request,
+ * the arguments follow in an object; for a response, the returned value follows.
+ */
+int csi_json_read_begin_envelope(CSIJsonReader* reader, const char** method, size_t* method_len);
+/**
+ * Ends reading a request or a response. Fails if anything but whitespace follows.
+ */
+int csi_json_read_end_envelope(CSIJsonReader* reader);
+
+/**
+ * Returns whether a key or method name read from JSON equals the NUL-terminated `expected`.
+ */
+bool csi_json_key_equals(const char* key, size_t key_len, const char* expected);
+
+/**
+ * Values of generated C headers, see `<SERVICE>_C_HEADER` in the Rust `#[ckb_script_ipc::service]` macro.
+ *
+ * Decoded bytes, strings and raw JSON are stored in a scratch buffer provided by the caller. Types which have no C
+ * counterpart, e.g. structs and enums, are kept as raw JSON text, to be encoded and decoded with the functions above.
+ */
+typedef struct CSIBytes {
+    const uint8_t* ptr;
+    size_t len;
+} CSIBytes;
+
+typedef struct CSIString {
+    const char* ptr;
+    size_t len;
+} CSIString;
+
+typedef struct CSIRawJson {
+    const char* ptr;
+    size_t len;
+} CSIRawJson;
+
+typedef struct CSIScratch {
+    uint8_t* buf;
+    size_t len;
+    size_t pos;
+} CSIScratch;
+
+/**
+ * Allocates `len` bytes from `scratch`, or returns NULL if it's full.
+ */
+void* csi_scratch_alloc(CSIScratch* scratch, size_t len);
+
+/**
+ * Writes a value which is already encoded as JSON, as is.
+ */
+int csi_json_write_raw(CSIJsonWriter* writer, const char* json, size_t len);
+
+int csi_json_read_uint_max(CSIJsonReader* reader, uint64_t max, uint64_t* value);
+int csi_json_read_int_range(CSIJsonReader* reader, int64_t min, int64_t max, int64_t* value);
+/**
+ * Reads a `null`, e.g. the `()` returned by a method. Fails if anything else is next.
+ */
+int csi_json_read_unit(CSIJsonReader* reader);
+/**
+ * Reads the end of an object, failing if it has more fields.
+ */
+int csi_json_read_end_object(CSIJsonReader* reader);
+/**
+ * Like csi_json_read_bytes, csi_json_read_string and csi_json_skip_value, storing the value in `scratch`. Fails
+ * with CSI_ERROR_JSON_BUFFER_FULL if it doesn't fit.
+ */
+int csi_json_read_bytes_in(CSIJsonReader* reader, CSIScratch* scratch, CSIBytes* value);
+int csi_json_read_string_in(CSIJsonReader* reader, CSIScratch* scratch, CSIString* value);
+int csi_json_read_raw_in(CSIJsonReader* reader, CSIScratch* scratch, CSIRawJson* value);
+
+/**
+ * Sends the request in `writer` and begins reading its response, checking that it's one of `method`. The response
+ * payload must be freed with csi_client_free_response_payload() once `reader` is not used anymore.
+ */
+int csi_json_call(CSIChannel* channel, uint64_t method_id, CSIJsonWriter* writer, const char* method,
+                  CSIResponsePacket* response, CSIJsonReader* reader);
+/**
+ * Copies the response in `writer` into the payload of `response`, on server side only.
+ */
+int csi_json_set_response_payload(CSIJsonWriter* writer, CSIResponsePacket* response);
+
 #endif
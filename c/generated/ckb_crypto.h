@@ -0,0 +1,1711 @@
+// Generated from the `CkbCrypto` service by `#[ckb_script_ipc::service]`, do not edit.
+#ifndef __CKB_CRYPTO_IPC_H__
+#define __CKB_CRYPTO_IPC_H__
+#include "ckb_script_ipc.h"
+
+// Method IDs, sent in requests. Rust servers tell methods apart by their names in payloads instead.
+// IDs follow the declaration order of the methods, so new methods must be appended to the service.
+#define CKB_CRYPTO_HASHER_NEW 0
+#define CKB_CRYPTO_HASHER_UPDATE 1
+#define CKB_CRYPTO_HASHER_FINALIZE 2
+#define CKB_CRYPTO_SECP256K1_RECOVERY 3
+#define CKB_CRYPTO_SECP256K1_VERIFY 4
+#define CKB_CRYPTO_SCHNORR_VERIFY 5
+#define CKB_CRYPTO_ED25519_VERIFY 6
+#define CKB_CRYPTO_HASHER_DROP 7
+#define CKB_CRYPTO_HASH 8
+#define CKB_CRYPTO_SECP256R1_VERIFY 9
+#define CKB_CRYPTO_WEBAUTHN_VERIFY 10
+#define CKB_CRYPTO_ETH_PERSONAL_RECOVER 11
+#define CKB_CRYPTO_ETH_TYPED_DATA_RECOVER 12
+#define CKB_CRYPTO_BITCOIN_MESSAGE_VERIFY 13
+#define CKB_CRYPTO_BIP322_VERIFY 14
+#define CKB_CRYPTO_BATCH_VERIFY 15
+#define CKB_CRYPTO_MULTISIG_VERIFY 16
+#define CKB_CRYPTO_BLS_VERIFY 17
+#define CKB_CRYPTO_BLS_AGGREGATE_VERIFY 18
+#define CKB_CRYPTO_BLS_FAST_AGGREGATE_VERIFY 19
+
+// hasher_new(hash_type: HasherType) -> Result<HasherCtx, CryptoError>
+typedef struct CkbCryptoHasherNewRequest {
+    CSIRawJson hash_type;
+} CkbCryptoHasherNewRequest;
+typedef struct CkbCryptoHasherNewResponse {
+    bool is_ok;
+    CSIRawJson ok;
+    CSIRawJson err;
+} CkbCryptoHasherNewResponse;
+
+// hasher_update(ctx: HasherCtx, data: Vec<u8>) -> Result<(), CryptoError>
+typedef struct CkbCryptoHasherUpdateRequest {
+    CSIRawJson ctx;
+    CSIBytes data;
+} CkbCryptoHasherUpdateRequest;
+typedef struct CkbCryptoHasherUpdateResponse {
+    bool is_ok;
+    CSIRawJson err;
+} CkbCryptoHasherUpdateResponse;
+
+// hasher_finalize(ctx: HasherCtx) -> Result<Vec<u8>, CryptoError>
+typedef struct CkbCryptoHasherFinalizeRequest {
+    CSIRawJson ctx;
+} CkbCryptoHasherFinalizeRequest;
+typedef struct CkbCryptoHasherFinalizeResponse {
+    bool is_ok;
+    CSIBytes ok;
+    CSIRawJson err;
+} CkbCryptoHasherFinalizeResponse;
+
+// secp256k1_recovery(prehash: Vec<u8>, signature: Vec<u8>, recovery_id: u8) -> Result<Vec<u8>, CryptoError>
+typedef struct CkbCryptoSecp256k1RecoveryRequest {
+    CSIBytes prehash;
+    CSIBytes signature;
+    uint8_t recovery_id;
+} CkbCryptoSecp256k1RecoveryRequest;
+typedef struct CkbCryptoSecp256k1RecoveryResponse {
+    bool is_ok;
+    CSIBytes ok;
+    CSIRawJson err;
+} CkbCryptoSecp256k1RecoveryResponse;
+
+// secp256k1_verify(public_key: Vec<u8>, prehash: Vec<u8>, signature: Vec<u8>) -> Result<(), CryptoError>
+typedef struct CkbCryptoSecp256k1VerifyRequest {
+    CSIBytes public_key;
+    CSIBytes prehash;
+    CSIBytes signature;
+} CkbCryptoSecp256k1VerifyRequest;
+typedef struct CkbCryptoSecp256k1VerifyResponse {
+    bool is_ok;
+    CSIRawJson err;
+} CkbCryptoSecp256k1VerifyResponse;
+
+// schnorr_verify(public_key: Vec<u8>, prehash: Vec<u8>, signature: Vec<u8>) -> Result<(), CryptoError>
+typedef struct CkbCryptoSchnorrVerifyRequest {
+    CSIBytes public_key;
+    CSIBytes prehash;
+    CSIBytes signature;
+} CkbCryptoSchnorrVerifyRequest;
+typedef struct CkbCryptoSchnorrVerifyResponse {
+    bool is_ok;
+    CSIRawJson err;
+} CkbCryptoSchnorrVerifyResponse;
+
+// ed25519_verify(public_key: Vec<u8>, prehash: Vec<u8>, signature: Vec<u8>) -> Result<(), CryptoError>
+typedef struct CkbCryptoEd25519VerifyRequest {
+    CSIBytes public_key;
+    CSIBytes prehash;
+    CSIBytes signature;
+} CkbCryptoEd25519VerifyRequest;
+typedef struct CkbCryptoEd25519VerifyResponse {
+    bool is_ok;
+    CSIRawJson err;
+} CkbCryptoEd25519VerifyResponse;
+
+// hasher_drop(ctx: HasherCtx) -> Result<(), CryptoError>
+typedef struct CkbCryptoHasherDropRequest {
+    CSIRawJson ctx;
+} CkbCryptoHasherDropRequest;
+typedef struct CkbCryptoHasherDropResponse {
+    bool is_ok;
+    CSIRawJson err;
+} CkbCryptoHasherDropResponse;
+
+// hash(hash_type: HasherType, data: Vec<u8>) -> Result<Vec<u8>, CryptoError>
+typedef struct CkbCryptoHashRequest {
+    CSIRawJson hash_type;
+    CSIBytes data;
+} CkbCryptoHashRequest;
+typedef struct CkbCryptoHashResponse {
+    bool is_ok;
+    CSIBytes ok;
+    CSIRawJson err;
+} CkbCryptoHashResponse;
+
+// secp256r1_verify(public_key: Vec<u8>, prehash: Vec<u8>, signature: Vec<u8>) -> Result<(), CryptoError>
+typedef struct CkbCryptoSecp256r1VerifyRequest {
+    CSIBytes public_key;
+    CSIBytes prehash;
+    CSIBytes signature;
+} CkbCryptoSecp256r1VerifyRequest;
+typedef struct CkbCryptoSecp256r1VerifyResponse {
+    bool is_ok;
+    CSIRawJson err;
+} CkbCryptoSecp256r1VerifyResponse;
+
+// webauthn_verify(public_key: Vec<u8>, authenticator_data: Vec<u8>, client_data_json: Vec<u8>, challenge: Vec<u8>, signature: Vec<u8>) -> Result<(), CryptoError>
+typedef struct CkbCryptoWebauthnVerifyRequest {
+    CSIBytes public_key;
+    CSIBytes authenticator_data;
+    CSIBytes client_data_json;
+    CSIBytes challenge;
+    CSIBytes signature;
+} CkbCryptoWebauthnVerifyRequest;
+typedef struct CkbCryptoWebauthnVerifyResponse {
+    bool is_ok;
+    CSIRawJson err;
+} CkbCryptoWebauthnVerifyResponse;
+
+// eth_personal_recover(message: Vec<u8>, signature: Vec<u8>, expected_address: Option<Vec<u8>>) -> Result<Vec<u8>, CryptoError>
+typedef struct CkbCryptoEthPersonalRecoverRequest {
+    CSIBytes message;
+    CSIBytes signature;
+    bool has_expected_address;
+    CSIBytes expected_address;
+} CkbCryptoEthPersonalRecoverRequest;
+typedef struct CkbCryptoEthPersonalRecoverResponse {
+    bool is_ok;
+    CSIBytes ok;
+    CSIRawJson err;
+} CkbCryptoEthPersonalRecoverResponse;
+
+// eth_typed_data_recover(domain_separator: Vec<u8>, struct_hash: Vec<u8>, signature: Vec<u8>, expected_address: Option<Vec<u8>>) -> Result<Vec<u8>, CryptoError>
+typedef struct CkbCryptoEthTypedDataRecoverRequest {
+    CSIBytes domain_separator;
+    CSIBytes struct_hash;
+    CSIBytes signature;
+    bool has_expected_address;
+    CSIBytes expected_address;
+} CkbCryptoEthTypedDataRecoverRequest;
+typedef struct CkbCryptoEthTypedDataRecoverResponse {
+    bool is_ok;
+    CSIBytes ok;
+    CSIRawJson err;
+} CkbCryptoEthTypedDataRecoverResponse;
+
+// bitcoin_message_verify(address_type: BitcoinAddressType, message: Vec<u8>, signature: Vec<u8>, pubkey_hash: Vec<u8>) -> Result<(), CryptoError>
+typedef struct CkbCryptoBitcoinMessageVerifyRequest {
+    CSIRawJson address_type;
+    CSIBytes message;
+    CSIBytes signature;
+    CSIBytes pubkey_hash;
+} CkbCryptoBitcoinMessageVerifyRequest;
+typedef struct CkbCryptoBitcoinMessageVerifyResponse {
+    bool is_ok;
+    CSIRawJson err;
+} CkbCryptoBitcoinMessageVerifyResponse;
+
+// bip322_verify(address_type: BitcoinAddressType, message: Vec<u8>, signature: Vec<u8>, pubkey_hash: Vec<u8>) -> Result<(), CryptoError>
+typedef struct CkbCryptoBip322VerifyRequest {
+    CSIRawJson address_type;
+    CSIBytes message;
+    CSIBytes signature;
+    CSIBytes pubkey_hash;
+} CkbCryptoBip322VerifyRequest;
+typedef struct CkbCryptoBip322VerifyResponse {
+    bool is_ok;
+    CSIRawJson err;
+} CkbCryptoBip322VerifyResponse;
+
+// batch_verify(items: Vec<VerifyItem>) -> Vec<Result<(), CryptoError>>
+typedef struct CkbCryptoBatchVerifyRequest {
+    CSIRawJson items;
+} CkbCryptoBatchVerifyRequest;
+typedef struct CkbCryptoBatchVerifyResponse {
+    CSIRawJson ret;
+} CkbCryptoBatchVerifyResponse;
+
+// multisig_verify(multisig_script: Vec<u8>, message: Vec<u8>, signatures: Vec<u8>) -> Result<Vec<u8>, CryptoError>
+typedef struct CkbCryptoMultisigVerifyRequest {
+    CSIBytes multisig_script;
+    CSIBytes message;
+    CSIBytes signatures;
+} CkbCryptoMultisigVerifyRequest;
+typedef struct CkbCryptoMultisigVerifyResponse {
+    bool is_ok;
+    CSIBytes ok;
+    CSIRawJson err;
+} CkbCryptoMultisigVerifyResponse;
+
+// bls_verify(public_key: Vec<u8>, message: Vec<u8>, signature: Vec<u8>) -> Result<(), CryptoError>
+typedef struct CkbCryptoBlsVerifyRequest {
+    CSIBytes public_key;
+    CSIBytes message;
+    CSIBytes signature;
+} CkbCryptoBlsVerifyRequest;
+typedef struct CkbCryptoBlsVerifyResponse {
+    bool is_ok;
+    CSIRawJson err;
+} CkbCryptoBlsVerifyResponse;
+
+// bls_aggregate_verify(public_keys: Vec<Vec<u8>>, messages: Vec<Vec<u8>>, signature: Vec<u8>) -> Result<(), CryptoError>
+typedef struct CkbCryptoBlsAggregateVerifyRequest {
+    CSIRawJson public_keys;
+    CSIRawJson messages;
+    CSIBytes signature;
+} CkbCryptoBlsAggregateVerifyRequest;
+typedef struct CkbCryptoBlsAggregateVerifyResponse {
+    bool is_ok;
+    CSIRawJson err;
+} CkbCryptoBlsAggregateVerifyResponse;
+
+// bls_fast_aggregate_verify(public_keys: Vec<Vec<u8>>, message: Vec<u8>, signature: Vec<u8>) -> Result<(), CryptoError>
+typedef struct CkbCryptoBlsFastAggregateVerifyRequest {
+    CSIRawJson public_keys;
+    CSIBytes message;
+    CSIBytes signature;
+} CkbCryptoBlsFastAggregateVerifyRequest;
+typedef struct CkbCryptoBlsFastAggregateVerifyResponse {
+    bool is_ok;
+    CSIRawJson err;
+} CkbCryptoBlsFastAggregateVerifyResponse;
+
+// Client side. Each call encodes its request into `scratch`, and then decodes the bytes, strings and
+// raw JSON of its response into `scratch`. They stay valid until `scratch` is used by another call.
+static inline int ckb_crypto_hasher_new(CSIChannel* channel, const CkbCryptoHasherNewRequest* request,
+                                        CkbCryptoHasherNewResponse* response, CSIScratch* scratch) {
+    int err = 0;
+    CSIJsonWriter writer;
+    CSIJsonReader reader;
+    CSIResponsePacket packet = {0};
+    scratch->pos = 0;
+    csi_json_writer_init(&writer, scratch->buf, scratch->len);
+    csi_json_begin_request(&writer, "HasherNew");
+    csi_json_write_key(&writer, "hash_type");
+    csi_json_write_raw(&writer, request->hash_type.ptr, request->hash_type.len);
+    csi_json_end_request(&writer);
+    err = csi_json_call(channel, CKB_CRYPTO_HASHER_NEW, &writer, "HasherNew", &packet, &reader);
+    if (err == 0) {
+        const char* variant = NULL;
+        size_t variant_len = 0;
+        err = csi_json_read_begin_envelope(&reader, &variant, &variant_len);
+        response->is_ok = err == 0 && csi_json_key_equals(variant, variant_len, "Ok");
+        if (err == 0 && !response->is_ok && !csi_json_key_equals(variant, variant_len, "Err")) err = CSI_ERROR_JSON;
+    }
+    if (err == 0 && response->is_ok) err = csi_json_read_raw_in(&reader, scratch, &response->ok);
+    if (err == 0 && !response->is_ok) err = csi_json_read_raw_in(&reader, scratch, &response->err);
+    if (err == 0) err = csi_json_read_end_object(&reader);
+    if (err == 0) err = csi_json_read_end_envelope(&reader);
+    csi_client_free_response_payload(&packet);
+    return err;
+}
+
+static inline int ckb_crypto_hasher_update(CSIChannel* channel, const CkbCryptoHasherUpdateRequest* request,
+                                           CkbCryptoHasherUpdateResponse* response, CSIScratch* scratch) {
+    int err = 0;
+    CSIJsonWriter writer;
+    CSIJsonReader reader;
+    CSIResponsePacket packet = {0};
+    scratch->pos = 0;
+    csi_json_writer_init(&writer, scratch->buf, scratch->len);
+    csi_json_begin_request(&writer, "HasherUpdate");
+    csi_json_write_key(&writer, "ctx");
+    csi_json_write_raw(&writer, request->ctx.ptr, request->ctx.len);
+    csi_json_write_key(&writer, "data");
+    csi_json_write_bytes(&writer, request->data.ptr, request->data.len);
+    csi_json_end_request(&writer);
+    err = csi_json_call(channel, CKB_CRYPTO_HASHER_UPDATE, &writer, "HasherUpdate", &packet, &reader);
+    if (err == 0) {
+        const char* variant = NULL;
+        size_t variant_len = 0;
+        err = csi_json_read_begin_envelope(&reader, &variant, &variant_len);
+        response->is_ok = err == 0 && csi_json_key_equals(variant, variant_len, "Ok");
+        if (err == 0 && !response->is_ok && !csi_json_key_equals(variant, variant_len, "Err")) err = CSI_ERROR_JSON;
+    }
+    if (err == 0 && response->is_ok) err = csi_json_read_unit(&reader);
+    if (err == 0 && !response->is_ok) err = csi_json_read_raw_in(&reader, scratch, &response->err);
+    if (err == 0) err = csi_json_read_end_object(&reader);
+    if (err == 0) err = csi_json_read_end_envelope(&reader);
+    csi_client_free_response_payload(&packet);
+    return err;
+}
+
+static inline int ckb_crypto_hasher_finalize(CSIChannel* channel, const CkbCryptoHasherFinalizeRequest* request,
+                                             CkbCryptoHasherFinalizeResponse* response, CSIScratch* scratch) {
+    int err = 0;
+    CSIJsonWriter writer;
+    CSIJsonReader reader;
+    CSIResponsePacket packet = {0};
+    scratch->pos = 0;
+    csi_json_writer_init(&writer, scratch->buf, scratch->len);
+    csi_json_begin_request(&writer, "HasherFinalize");
+    csi_json_write_key(&writer, "ctx");
+    csi_json_write_raw(&writer, request->ctx.ptr, request->ctx.len);
+    csi_json_end_request(&writer);
+    err = csi_json_call(channel, CKB_CRYPTO_HASHER_FINALIZE, &writer, "HasherFinalize", &packet, &reader);
+    if (err == 0) {
+        const char* variant = NULL;
+        size_t variant_len = 0;
+        err = csi_json_read_begin_envelope(&reader, &variant, &variant_len);
+        response->is_ok = err == 0 && csi_json_key_equals(variant, variant_len, "Ok");
+        if (err == 0 && !response->is_ok && !csi_json_key_equals(variant, variant_len, "Err")) err = CSI_ERROR_JSON;
+    }
+    if (err == 0 && response->is_ok) err = csi_json_read_bytes_in(&reader, scratch, &response->ok);
+    if (err == 0 && !response->is_ok) err = csi_json_read_raw_in(&reader, scratch, &response->err);
+    if (err == 0) err = csi_json_read_end_object(&reader);
+    if (err == 0) err = csi_json_read_end_envelope(&reader);
+    csi_client_free_response_payload(&packet);
+    return err;
+}
+
+static inline int ckb_crypto_secp256k1_recovery(CSIChannel* channel, const CkbCryptoSecp256k1RecoveryRequest* request,
+                                                CkbCryptoSecp256k1RecoveryResponse* response, CSIScratch* scratch) {
+    int err = 0;
+    CSIJsonWriter writer;
+    CSIJsonReader reader;
+    CSIResponsePacket packet = {0};
+    scratch->pos = 0;
+    csi_json_writer_init(&writer, scratch->buf, scratch->len);
+    csi_json_begin_request(&writer, "Secp256k1Recovery");
+    csi_json_write_key(&writer, "prehash");
+    csi_json_write_bytes(&writer, request->prehash.ptr, request->prehash.len);
+    csi_json_write_key(&writer, "signature");
+    csi_json_write_bytes(&writer, request->signature.ptr, request->signature.len);
+    csi_json_write_key(&writer, "recovery_id");
+    csi_json_write_uint(&writer, request->recovery_id);
+    csi_json_end_request(&writer);
+    err = csi_json_call(channel, CKB_CRYPTO_SECP256K1_RECOVERY, &writer, "Secp256k1Recovery", &packet, &reader);
+    if (err == 0) {
+        const char* variant = NULL;
+        size_t variant_len = 0;
+        err = csi_json_read_begin_envelope(&reader, &variant, &variant_len);
+        response->is_ok = err == 0 && csi_json_key_equals(variant, variant_len, "Ok");
+        if (err == 0 && !response->is_ok && !csi_json_key_equals(variant, variant_len, "Err")) err = CSI_ERROR_JSON;
+    }
+    if (err == 0 && response->is_ok) err = csi_json_read_bytes_in(&reader, scratch, &response->ok);
+    if (err == 0 && !response->is_ok) err = csi_json_read_raw_in(&reader, scratch, &response->err);
+    if (err == 0) err = csi_json_read_end_object(&reader);
+    if (err == 0) err = csi_json_read_end_envelope(&reader);
+    csi_client_free_response_payload(&packet);
+    return err;
+}
+
+static inline int ckb_crypto_secp256k1_verify(CSIChannel* channel, const CkbCryptoSecp256k1VerifyRequest* request,
+                                              CkbCryptoSecp256k1VerifyResponse* response, CSIScratch* scratch) {
+    int err = 0;
+    CSIJsonWriter writer;
+    CSIJsonReader reader;
+    CSIResponsePacket packet = {0};
+    scratch->pos = 0;
+    csi_json_writer_init(&writer, scratch->buf, scratch->len);
+    csi_json_begin_request(&writer, "Secp256k1Verify");
+    csi_json_write_key(&writer, "public_key");
+    csi_json_write_bytes(&writer, request->public_key.ptr, request->public_key.len);
+    csi_json_write_key(&writer, "prehash");
+    csi_json_write_bytes(&writer, request->prehash.ptr, request->prehash.len);
+    csi_json_write_key(&writer, "signature");
+    csi_json_write_bytes(&writer, request->signature.ptr, request->signature.len);
+    csi_json_end_request(&writer);
+    err = csi_json_call(channel, CKB_CRYPTO_SECP256K1_VERIFY, &writer, "Secp256k1Verify", &packet, &reader);
+    if (err == 0) {
+        const char* variant = NULL;
+        size_t variant_len = 0;
+        err = csi_json_read_begin_envelope(&reader, &variant, &variant_len);
+        response->is_ok = err == 0 && csi_json_key_equals(variant, variant_len, "Ok");
+        if (err == 0 && !response->is_ok && !csi_json_key_equals(variant, variant_len, "Err")) err = CSI_ERROR_JSON;
+    }
+    if (err == 0 && response->is_ok) err = csi_json_read_unit(&reader);
+    if (err == 0 && !response->is_ok) err = csi_json_read_raw_in(&reader, scratch, &response->err);
+    if (err == 0) err = csi_json_read_end_object(&reader);
+    if (err == 0) err = csi_json_read_end_envelope(&reader);
+    csi_client_free_response_payload(&packet);
+    return err;
+}
+
+static inline int ckb_crypto_schnorr_verify(CSIChannel* channel, const CkbCryptoSchnorrVerifyRequest* request,
+                                            CkbCryptoSchnorrVerifyResponse* response, CSIScratch* scratch) {
+    int err = 0;
+    CSIJsonWriter writer;
+    CSIJsonReader reader;
+    CSIResponsePacket packet = {0};
+    scratch->pos = 0;
+    csi_json_writer_init(&writer, scratch->buf, scratch->len);
+    csi_json_begin_request(&writer, "SchnorrVerify");
+    csi_json_write_key(&writer, "public_key");
+    csi_json_write_bytes(&writer, request->public_key.ptr, request->public_key.len);
+    csi_json_write_key(&writer, "prehash");
+    csi_json_write_bytes(&writer, request->prehash.ptr, request->prehash.len);
+    csi_json_write_key(&writer, "signature");
+    csi_json_write_bytes(&writer, request->signature.ptr, request->signature.len);
+    csi_json_end_request(&writer);
+    err = csi_json_call(channel, CKB_CRYPTO_SCHNORR_VERIFY, &writer, "SchnorrVerify", &packet, &reader);
+    if (err == 0) {
+        const char* variant = NULL;
+        size_t variant_len = 0;
+        err = csi_json_read_begin_envelope(&reader, &variant, &variant_len);
+        response->is_ok = err == 0 && csi_json_key_equals(variant, variant_len, "Ok");
+        if (err == 0 && !response->is_ok && !csi_json_key_equals(variant, variant_len, "Err")) err = CSI_ERROR_JSON;
+    }
+    if (err == 0 && response->is_ok) err = csi_json_read_unit(&reader);
+    if (err == 0 && !response->is_ok) err = csi_json_read_raw_in(&reader, scratch, &response->err);
+    if (err == 0) err = csi_json_read_end_object(&reader);
+    if (err == 0) err = csi_json_read_end_envelope(&reader);
+    csi_client_free_response_payload(&packet);
+    return err;
+}
+
+static inline int ckb_crypto_ed25519_verify(CSIChannel* channel, const CkbCryptoEd25519VerifyRequest* request,
+                                            CkbCryptoEd25519VerifyResponse* response, CSIScratch* scratch) {
+    int err = 0;
+    CSIJsonWriter writer;
+    CSIJsonReader reader;
+    CSIResponsePacket packet = {0};
+    scratch->pos = 0;
+    csi_json_writer_init(&writer, scratch->buf, scratch->len);
+    csi_json_begin_request(&writer, "Ed25519Verify");
+    csi_json_write_key(&writer, "public_key");
+    csi_json_write_bytes(&writer, request->public_key.ptr, request->public_key.len);
+    csi_json_write_key(&writer, "prehash");
+    csi_json_write_bytes(&writer, request->prehash.ptr, request->prehash.len);
+    csi_json_write_key(&writer, "signature");
+    csi_json_write_bytes(&writer, request->signature.ptr, request->signature.len);
+    csi_json_end_request(&writer);
+    err = csi_json_call(channel, CKB_CRYPTO_ED25519_VERIFY, &writer, "Ed25519Verify", &packet, &reader);
+    if (err == 0) {
+        const char* variant = NULL;
+        size_t variant_len = 0;
+        err = csi_json_read_begin_envelope(&reader, &variant, &variant_len);
+        response->is_ok = err == 0 && csi_json_key_equals(variant, variant_len, "Ok");
+        if (err == 0 && !response->is_ok && !csi_json_key_equals(variant, variant_len, "Err")) err = CSI_ERROR_JSON;
+    }
+    if (err == 0 && response->is_ok) err = csi_json_read_unit(&reader);
+    if (err == 0 && !response->is_ok) err = csi_json_read_raw_in(&reader, scratch, &response->err);
+    if (err == 0) err = csi_json_read_end_object(&reader);
+    if (err == 0) err = csi_json_read_end_envelope(&reader);
+    csi_client_free_response_payload(&packet);
+    return err;
+}
+
+static inline int ckb_crypto_hasher_drop(CSIChannel* channel, const CkbCryptoHasherDropRequest* request,
+                                         CkbCryptoHasherDropResponse* response, CSIScratch* scratch) {
+    int err = 0;
+    CSIJsonWriter writer;
+    CSIJsonReader reader;
+    CSIResponsePacket packet = {0};
+    scratch->pos = 0;
+    csi_json_writer_init(&writer, scratch->buf, scratch->len);
+    csi_json_begin_request(&writer, "HasherDrop");
+    csi_json_write_key(&writer, "ctx");
+    csi_json_write_raw(&writer, request->ctx.ptr, request->ctx.len);
+    csi_json_end_request(&writer);
+    err = csi_json_call(channel, CKB_CRYPTO_HASHER_DROP, &writer, "HasherDrop", &packet, &reader);
+    if (err == 0) {
+        const char* variant = NULL;
+        size_t variant_len = 0;
+        err = csi_json_read_begin_envelope(&reader, &variant, &variant_len);
+        response->is_ok = err == 0 && csi_json_key_equals(variant, variant_len, "Ok");
+        if (err == 0 && !response->is_ok && !csi_json_key_equals(variant, variant_len, "Err")) err = CSI_ERROR_JSON;
+    }
+    if (err == 0 && response->is_ok) err = csi_json_read_unit(&reader);
+    if (err == 0 && !response->is_ok) err = csi_json_read_raw_in(&reader, scratch, &response->err);
+    if (err == 0) err = csi_json_read_end_object(&reader);
+    if (err == 0) err = csi_json_read_end_envelope(&reader);
+    csi_client_free_response_payload(&packet);
+    return err;
+}
+
+static inline int ckb_crypto_hash(CSIChannel* channel, const CkbCryptoHashRequest* request,
+                                  CkbCryptoHashResponse* response, CSIScratch* scratch) {
+    int err = 0;
+    CSIJsonWriter writer;
+    CSIJsonReader reader;
+    CSIResponsePacket packet = {0};
+    scratch->pos = 0;
+    csi_json_writer_init(&writer, scratch->buf, scratch->len);
+    csi_json_begin_request(&writer, "Hash");
+    csi_json_write_key(&writer, "hash_type");
+    csi_json_write_raw(&writer, request->hash_type.ptr, request->hash_type.len);
+    csi_json_write_key(&writer, "data");
+    csi_json_write_bytes(&writer, request->data.ptr, request->data.len);
+    csi_json_end_request(&writer);
+    err = csi_json_call(channel, CKB_CRYPTO_HASH, &writer, "Hash", &packet, &reader);
+    if (err == 0) {
+        const char* variant = NULL;
+        size_t variant_len = 0;
+        err = csi_json_read_begin_envelope(&reader, &variant, &variant_len);
+        response->is_ok = err == 0 && csi_json_key_equals(variant, variant_len, "Ok");
+        if (err == 0 && !response->is_ok && !csi_json_key_equals(variant, variant_len, "Err")) err = CSI_ERROR_JSON;
+    }
+    if (err == 0 && response->is_ok) err = csi_json_read_bytes_in(&reader, scratch, &response->ok);
+    if (err == 0 && !response->is_ok) err = csi_json_read_raw_in(&reader, scratch, &response->err);
+    if (err == 0) err = csi_json_read_end_object(&reader);
+    if (err == 0) err = csi_json_read_end_envelope(&reader);
+    csi_client_free_response_payload(&packet);
+    return err;
+}
+
+static inline int ckb_crypto_secp256r1_verify(CSIChannel* channel, const CkbCryptoSecp256r1VerifyRequest* request,
+                                              CkbCryptoSecp256r1VerifyResponse* response, CSIScratch* scratch) {
+    int err = 0;
+    CSIJsonWriter writer;
+    CSIJsonReader reader;
+    CSIResponsePacket packet = {0};
+    scratch->pos = 0;
+    csi_json_writer_init(&writer, scratch->buf, scratch->len);
+    csi_json_begin_request(&writer, "Secp256r1Verify");
+    csi_json_write_key(&writer, "public_key");
+    csi_json_write_bytes(&writer, request->public_key.ptr, request->public_key.len);
+    csi_json_write_key(&writer, "prehash");
+    csi_json_write_bytes(&writer, request->prehash.ptr, request->prehash.len);
+    csi_json_write_key(&writer, "signature");
+    csi_json_write_bytes(&writer, request->signature.ptr, request->signature.len);
+    csi_json_end_request(&writer);
+    err = csi_json_call(channel, CKB_CRYPTO_SECP256R1_VERIFY, &writer, "Secp256r1Verify", &packet, &reader);
+    if (err == 0) {
+        const char* variant = NULL;
+        size_t variant_len = 0;
+        err = csi_json_read_begin_envelope(&reader, &variant, &variant_len);
+        response->is_ok = err == 0 && csi_json_key_equals(variant, variant_len, "Ok");
+        if (err == 0 && !response->is_ok && !csi_json_key_equals(variant, variant_len, "Err")) err = CSI_ERROR_JSON;
+    }
+    if (err == 0 && response->is_ok) err = csi_json_read_unit(&reader);
+    if (err == 0 && !response->is_ok) err = csi_json_read_raw_in(&reader, scratch, &response->err);
+    if (err == 0) err = csi_json_read_end_object(&reader);
+    if (err == 0) err = csi_json_read_end_envelope(&reader);
+    csi_client_free_response_payload(&packet);
+    return err;
+}
+
+static inline int ckb_crypto_webauthn_verify(CSIChannel* channel, const CkbCryptoWebauthnVerifyRequest* request,
+                                             CkbCryptoWebauthnVerifyResponse* response, CSIScratch* scratch) {
+    int err = 0;
+    CSIJsonWriter writer;
+    CSIJsonReader reader;
+    CSIResponsePacket packet = {0};
+    scratch->pos = 0;
+    csi_json_writer_init(&writer, scratch->buf, scratch->len);
+    csi_json_begin_request(&writer, "WebauthnVerify");
+    csi_json_write_key(&writer, "public_key");
+    csi_json_write_bytes(&writer, request->public_key.ptr, request->public_key.len);
+    csi_json_write_key(&writer, "authenticator_data");
+    csi_json_write_bytes(&writer, request->authenticator_data.ptr, request->authenticator_data.len);
+    csi_json_write_key(&writer, "client_data_json");
+    csi_json_write_bytes(&writer, request->client_data_json.ptr, request->client_data_json.len);
+    csi_json_write_key(&writer, "challenge");
+    csi_json_write_bytes(&writer, request->challenge.ptr, request->challenge.len);
+    csi_json_write_key(&writer, "signature");
+    csi_json_write_bytes(&writer, request->signature.ptr, request->signature.len);
+    csi_json_end_request(&writer);
+    err = csi_json_call(channel, CKB_CRYPTO_WEBAUTHN_VERIFY, &writer, "WebauthnVerify", &packet, &reader);
+    if (err == 0) {
+        const char* variant = NULL;
+        size_t variant_len = 0;
+        err = csi_json_read_begin_envelope(&reader, &variant, &variant_len);
+        response->is_ok = err == 0 && csi_json_key_equals(variant, variant_len, "Ok");
+        if (err == 0 && !response->is_ok && !csi_json_key_equals(variant, variant_len, "Err")) err = CSI_ERROR_JSON;
+    }
+    if (err == 0 && response->is_ok) err = csi_json_read_unit(&reader);
+    if (err == 0 && !response->is_ok) err = csi_json_read_raw_in(&reader, scratch, &response->err);
+    if (err == 0) err = csi_json_read_end_object(&reader);
+    if (err == 0) err = csi_json_read_end_envelope(&reader);
+    csi_client_free_response_payload(&packet);
+    return err;
+}
+
+static inline int ckb_crypto_eth_personal_recover(CSIChannel* channel,
+                                                  const CkbCryptoEthPersonalRecoverRequest* request,
+                                                  CkbCryptoEthPersonalRecoverResponse* response, CSIScratch* scratch) {
+    int err = 0;
+    CSIJsonWriter writer;
+    CSIJsonReader reader;
+    CSIResponsePacket packet = {0};
+    scratch->pos = 0;
+    csi_json_writer_init(&writer, scratch->buf, scratch->len);
+    csi_json_begin_request(&writer, "EthPersonalRecover");
+    csi_json_write_key(&writer, "message");
+    csi_json_write_bytes(&writer, request->message.ptr, request->message.len);
+    csi_json_write_key(&writer, "signature");
+    csi_json_write_bytes(&writer, request->signature.ptr, request->signature.len);
+    csi_json_write_key(&writer, "expected_address");
+    if (request->has_expected_address) {
+        csi_json_write_bytes(&writer, request->expected_address.ptr, request->expected_address.len);
+    } else {
+        csi_json_write_null(&writer);
+    }
+    csi_json_end_request(&writer);
+    err = csi_json_call(channel, CKB_CRYPTO_ETH_PERSONAL_RECOVER, &writer, "EthPersonalRecover", &packet, &reader);
+    if (err == 0) {
+        const char* variant = NULL;
+        size_t variant_len = 0;
+        err = csi_json_read_begin_envelope(&reader, &variant, &variant_len);
+        response->is_ok = err == 0 && csi_json_key_equals(variant, variant_len, "Ok");
+        if (err == 0 && !response->is_ok && !csi_json_key_equals(variant, variant_len, "Err")) err = CSI_ERROR_JSON;
+    }
+    if (err == 0 && response->is_ok) err = csi_json_read_bytes_in(&reader, scratch, &response->ok);
+    if (err == 0 && !response->is_ok) err = csi_json_read_raw_in(&reader, scratch, &response->err);
+    if (err == 0) err = csi_json_read_end_object(&reader);
+    if (err == 0) err = csi_json_read_end_envelope(&reader);
+    csi_client_free_response_payload(&packet);
+    return err;
+}
+
+static inline int ckb_crypto_eth_typed_data_recover(CSIChannel* channel,
+                                                    const CkbCryptoEthTypedDataRecoverRequest* request,
+                                                    CkbCryptoEthTypedDataRecoverResponse* response,
+                                                    CSIScratch* scratch) {
+    int err = 0;
+    CSIJsonWriter writer;
+    CSIJsonReader reader;
+    CSIResponsePacket packet = {0};
+    scratch->pos = 0;
+    csi_json_writer_init(&writer, scratch->buf, scratch->len);
+    csi_json_begin_request(&writer, "EthTypedDataRecover");
+    csi_json_write_key(&writer, "domain_separator");
+    csi_json_write_bytes(&writer, request->domain_separator.ptr, request->domain_separator.len);
+    csi_json_write_key(&writer, "struct_hash");
+    csi_json_write_bytes(&writer, request->struct_hash.ptr, request->struct_hash.len);
+    csi_json_write_key(&writer, "signature");
+    csi_json_write_bytes(&writer, request->signature.ptr, request->signature.len);
+    csi_json_write_key(&writer, "expected_address");
+    if (request->has_expected_address) {
+        csi_json_write_bytes(&writer, request->expected_address.ptr, request->expected_address.len);
+    } else {
+        csi_json_write_null(&writer);
+    }
+    csi_json_end_request(&writer);
+    err = csi_json_call(channel, CKB_CRYPTO_ETH_TYPED_DATA_RECOVER, &writer, "EthTypedDataRecover", &packet, &reader);
+    if (err == 0) {
+        const char* variant = NULL;
+        size_t variant_len = 0;
+        err = csi_json_read_begin_envelope(&reader, &variant, &variant_len);
+        response->is_ok = err == 0 && csi_json_key_equals(variant, variant_len, "Ok");
+        if (err == 0 && !response->is_ok && !csi_json_key_equals(variant, variant_len, "Err")) err = CSI_ERROR_JSON;
+    }
+    if (err == 0 && response->is_ok) err = csi_json_read_bytes_in(&reader, scratch, &response->ok);
+    if (err == 0 && !response->is_ok) err = csi_json_read_raw_in(&reader, scratch, &response->err);
+    if (err == 0) err = csi_json_read_end_object(&reader);
+    if (err == 0) err = csi_json_read_end_envelope(&reader);
+    csi_client_free_response_payload(&packet);
+    return err;
+}
+
+static inline int ckb_crypto_bitcoin_message_verify(CSIChannel* channel,
+                                                    const CkbCryptoBitcoinMessageVerifyRequest* request,
+                                                    CkbCryptoBitcoinMessageVerifyResponse* response,
+                                                    CSIScratch* scratch) {
+    int err = 0;
+    CSIJsonWriter writer;
+    CSIJsonReader reader;
+    CSIResponsePacket packet = {0};
+    scratch->pos = 0;
+    csi_json_writer_init(&writer, scratch->buf, scratch->len);
+    csi_json_begin_request(&writer, "BitcoinMessageVerify");
+    csi_json_write_key(&writer, "address_type");
+    csi_json_write_raw(&writer, request->address_type.ptr, request->address_type.len);
+    csi_json_write_key(&writer, "message");
+    csi_json_write_bytes(&writer, request->message.ptr, request->message.len);
+    csi_json_write_key(&writer, "signature");
+    csi_json_write_bytes(&writer, request->signature.ptr, request->signature.len);
+    csi_json_write_key(&writer, "pubkey_hash");
+    csi_json_write_bytes(&writer, request->pubkey_hash.ptr, request->pubkey_hash.len);
+    csi_json_end_request(&writer);
+    err = csi_json_call(channel, CKB_CRYPTO_BITCOIN_MESSAGE_VERIFY, &writer, "BitcoinMessageVerify", &packet, &reader);
+    if (err == 0) {
+        const char* variant = NULL;
+        size_t variant_len = 0;
+        err = csi_json_read_begin_envelope(&reader, &variant, &variant_len);
+        response->is_ok = err == 0 && csi_json_key_equals(variant, variant_len, "Ok");
+        if (err == 0 && !response->is_ok && !csi_json_key_equals(variant, variant_len, "Err")) err = CSI_ERROR_JSON;
+    }
+    if (err == 0 && response->is_ok) err = csi_json_read_unit(&reader);
+    if (err == 0 && !response->is_ok) err = csi_json_read_raw_in(&reader, scratch, &response->err);
+    if (err == 0) err = csi_json_read_end_object(&reader);
+    if (err == 0) err = csi_json_read_end_envelope(&reader);
+    csi_client_free_response_payload(&packet);
+    return err;
+}
+
+static inline int ckb_crypto_bip322_verify(CSIChannel* channel, const CkbCryptoBip322VerifyRequest* request,
+                                           CkbCryptoBip322VerifyResponse* response, CSIScratch* scratch) {
+    int err = 0;
+    CSIJsonWriter writer;
+    CSIJsonReader reader;
+    CSIResponsePacket packet = {0};
+    scratch->pos = 0;
+    csi_json_writer_init(&writer, scratch->buf, scratch->len);
+    csi_json_begin_request(&writer, "Bip322Verify");
+    csi_json_write_key(&writer, "address_type");
+    csi_json_write_raw(&writer, request->address_type.ptr, request->address_type.len);
+    csi_json_write_key(&writer, "message");
+    csi_json_write_bytes(&writer, request->message.ptr, request->message.len);
+    csi_json_write_key(&writer, "signature");
+    csi_json_write_bytes(&writer, request->signature.ptr, request->signature.len);
+    csi_json_write_key(&writer, "pubkey_hash");
+    csi_json_write_bytes(&writer, request->pubkey_hash.ptr, request->pubkey_hash.len);
+    csi_json_end_request(&writer);
+    err = csi_json_call(channel, CKB_CRYPTO_BIP322_VERIFY, &writer, "Bip322Verify", &packet, &reader);
+    if (err == 0) {
+        const char* variant = NULL;
+        size_t variant_len = 0;
+        err = csi_json_read_begin_envelope(&reader, &variant, &variant_len);
+        response->is_ok = err == 0 && csi_json_key_equals(variant, variant_len, "Ok");
+        if (err == 0 && !response->is_ok && !csi_json_key_equals(variant, variant_len, "Err")) err = CSI_ERROR_JSON;
+    }
+    if (err == 0 && response->is_ok) err = csi_json_read_unit(&reader);
+    if (err == 0 && !response->is_ok) err = csi_json_read_raw_in(&reader, scratch, &response->err);
+    if (err == 0) err = csi_json_read_end_object(&reader);
+    if (err == 0) err = csi_json_read_end_envelope(&reader);
+    csi_client_free_response_payload(&packet);
+    return err;
+}
+
+static inline int ckb_crypto_batch_verify(CSIChannel* channel, const CkbCryptoBatchVerifyRequest* request,
+                                          CkbCryptoBatchVerifyResponse* response, CSIScratch* scratch) {
+    int err = 0;
+    CSIJsonWriter writer;
+    CSIJsonReader reader;
+    CSIResponsePacket packet = {0};
+    scratch->pos = 0;
+    csi_json_writer_init(&writer, scratch->buf, scratch->len);
+    csi_json_begin_request(&writer, "BatchVerify");
+    csi_json_write_key(&writer, "items");
+    csi_json_write_raw(&writer, request->items.ptr, request->items.len);
+    csi_json_end_request(&writer);
+    err = csi_json_call(channel, CKB_CRYPTO_BATCH_VERIFY, &writer, "BatchVerify", &packet, &reader);
+    if (err == 0) err = csi_json_read_raw_in(&reader, scratch, &response->ret);
+    if (err == 0) err = csi_json_read_end_envelope(&reader);
+    csi_client_free_response_payload(&packet);
+    return err;
+}
+
+static inline int ckb_crypto_multisig_verify(CSIChannel* channel, const CkbCryptoMultisigVerifyRequest* request,
+                                             CkbCryptoMultisigVerifyResponse* response, CSIScratch* scratch) {
+    int err = 0;
+    CSIJsonWriter writer;
+    CSIJsonReader reader;
+    CSIResponsePacket packet = {0};
+    scratch->pos = 0;
+    csi_json_writer_init(&writer, scratch->buf, scratch->len);
+    csi_json_begin_request(&writer, "MultisigVerify");
+    csi_json_write_key(&writer, "multisig_script");
+    csi_json_write_bytes(&writer, request->multisig_script.ptr, request->multisig_script.len);
+    csi_json_write_key(&writer, "message");
+    csi_json_write_bytes(&writer, request->message.ptr, request->message.len);
+    csi_json_write_key(&writer, "signatures");
+    csi_json_write_bytes(&writer, request->signatures.ptr, request->signatures.len);
+    csi_json_end_request(&writer);
+    err = csi_json_call(channel, CKB_CRYPTO_MULTISIG_VERIFY, &writer, "MultisigVerify", &packet, &reader);
+    if (err == 0) {
+        const char* variant = NULL;
+        size_t variant_len = 0;
+        err = csi_json_read_begin_envelope(&reader, &variant, &variant_len);
+        response->is_ok = err == 0 && csi_json_key_equals(variant, variant_len, "Ok");
+        if (err == 0 && !response->is_ok && !csi_json_key_equals(variant, variant_len, "Err")) err = CSI_ERROR_JSON;
+    }
+    if (err == 0 && response->is_ok) err = csi_json_read_bytes_in(&reader, scratch, &response->ok);
+    if (err == 0 && !response->is_ok) err = csi_json_read_raw_in(&reader, scratch, &response->err);
+    if (err == 0) err = csi_json_read_end_object(&reader);
+    if (err == 0) err = csi_json_read_end_envelope(&reader);
+    csi_client_free_response_payload(&packet);
+    return err;
+}
+
+static inline int ckb_crypto_bls_verify(CSIChannel* channel, const CkbCryptoBlsVerifyRequest* request,
+                                        CkbCryptoBlsVerifyResponse* response, CSIScratch* scratch) {
+    int err = 0;
+    CSIJsonWriter writer;
+    CSIJsonReader reader;
+    CSIResponsePacket packet = {0};
+    scratch->pos = 0;
+    csi_json_writer_init(&writer, scratch->buf, scratch->len);
+    csi_json_begin_request(&writer, "BlsVerify");
+    csi_json_write_key(&writer, "public_key");
+    csi_json_write_bytes(&writer, request->public_key.ptr, request->public_key.len);
+    csi_json_write_key(&writer, "message");
+    csi_json_write_bytes(&writer, request->message.ptr, request->message.len);
+    csi_json_write_key(&writer, "signature");
+    csi_json_write_bytes(&writer, request->signature.ptr, request->signature.len);
+    csi_json_end_request(&writer);
+    err = csi_json_call(channel, CKB_CRYPTO_BLS_VERIFY, &writer, "BlsVerify", &packet, &reader);
+    if (err == 0) {
+        const char* variant = NULL;
+        size_t variant_len = 0;
+        err = csi_json_read_begin_envelope(&reader, &variant, &variant_len);
+        response->is_ok = err == 0 && csi_json_key_equals(variant, variant_len, "Ok");
+        if (err == 0 && !response->is_ok && !csi_json_key_equals(variant, variant_len, "Err")) err = CSI_ERROR_JSON;
+    }
+    if (err == 0 && response->is_ok) err = csi_json_read_unit(&reader);
+    if (err == 0 && !response->is_ok) err = csi_json_read_raw_in(&reader, scratch, &response->err);
+    if (err == 0) err = csi_json_read_end_object(&reader);
+    if (err == 0) err = csi_json_read_end_envelope(&reader);
+    csi_client_free_response_payload(&packet);
+    return err;
+}
+
+static inline int ckb_crypto_bls_aggregate_verify(CSIChannel* channel,
+                                                  const CkbCryptoBlsAggregateVerifyRequest* request,
+                                                  CkbCryptoBlsAggregateVerifyResponse* response, CSIScratch* scratch) {
+    int err = 0;
+    CSIJsonWriter writer;
+    CSIJsonReader reader;
+    CSIResponsePacket packet = {0};
+    scratch->pos = 0;
+    csi_json_writer_init(&writer, scratch->buf, scratch->len);
+    csi_json_begin_request(&writer, "BlsAggregateVerify");
+    csi_json_write_key(&writer, "public_keys");
+    csi_json_write_raw(&writer, request->public_keys.ptr, request->public_keys.len);
+    csi_json_write_key(&writer, "messages");
+    csi_json_write_raw(&writer, request->messages.ptr, request->messages.len);
+    csi_json_write_key(&writer, "signature");
+    csi_json_write_bytes(&writer, request->signature.ptr, request->signature.len);
+    csi_json_end_request(&writer);
+    err = csi_json_call(channel, CKB_CRYPTO_BLS_AGGREGATE_VERIFY, &writer, "BlsAggregateVerify", &packet, &reader);
+    if (err == 0) {
+        const char* variant = NULL;
+        size_t variant_len = 0;
+        err = csi_json_read_begin_envelope(&reader, &variant, &variant_len);
+        response->is_ok = err == 0 && csi_json_key_equals(variant, variant_len, "Ok");
+        if (err == 0 && !response->is_ok && !csi_json_key_equals(variant, variant_len, "Err")) err = CSI_ERROR_JSON;
+    }
+    if (err == 0 && response->is_ok) err = csi_json_read_unit(&reader);
+    if (err == 0 && !response->is_ok) err = csi_json_read_raw_in(&reader, scratch, &response->err);
+    if (err == 0) err = csi_json_read_end_object(&reader);
+    if (err == 0) err = csi_json_read_end_envelope(&reader);
+    csi_client_free_response_payload(&packet);
+    return err;
+}
+
+static inline int ckb_crypto_bls_fast_aggregate_verify(CSIChannel* channel,
+                                                       const CkbCryptoBlsFastAggregateVerifyRequest* request,
+                                                       CkbCryptoBlsFastAggregateVerifyResponse* response,
+                                                       CSIScratch* scratch) {
+    int err = 0;
+    CSIJsonWriter writer;
+    CSIJsonReader reader;
+    CSIResponsePacket packet = {0};
+    scratch->pos = 0;
+    csi_json_writer_init(&writer, scratch->buf, scratch->len);
+    csi_json_begin_request(&writer, "BlsFastAggregateVerify");
+    csi_json_write_key(&writer, "public_keys");
+    csi_json_write_raw(&writer, request->public_keys.ptr, request->public_keys.len);
+    csi_json_write_key(&writer, "message");
+    csi_json_write_bytes(&writer, request->message.ptr, request->message.len);
+    csi_json_write_key(&writer, "signature");
+    csi_json_write_bytes(&writer, request->signature.ptr, request->signature.len);
+    csi_json_end_request(&writer);
+    err = csi_json_call(channel, CKB_CRYPTO_BLS_FAST_AGGREGATE_VERIFY, &writer, "BlsFastAggregateVerify", &packet, &reader);
+    if (err == 0) {
+        const char* variant = NULL;
+        size_t variant_len = 0;
+        err = csi_json_read_begin_envelope(&reader, &variant, &variant_len);
+        response->is_ok = err == 0 && csi_json_key_equals(variant, variant_len, "Ok");
+        if (err == 0 && !response->is_ok && !csi_json_key_equals(variant, variant_len, "Err")) err = CSI_ERROR_JSON;
+    }
+    if (err == 0 && response->is_ok) err = csi_json_read_unit(&reader);
+    if (err == 0 && !response->is_ok) err = csi_json_read_raw_in(&reader, scratch, &response->err);
+    if (err == 0) err = csi_json_read_end_object(&reader);
+    if (err == 0) err = csi_json_read_end_envelope(&reader);
+    csi_client_free_response_payload(&packet);
+    return err;
+}
+
+// Server side. Implement these handlers, and dispatch requests from the callback of csi_run_server:
+//     static int serve(const CSIRequestPacket* request, CSIResponsePacket* response) {
+//         static uint8_t buf[4096];
+//         CSIScratch scratch = {buf, sizeof(buf), 0};
+//         return ckb_crypto_dispatch(request, response, &scratch);
+//     }
+// The bytes, strings and raw JSON of requests are stored in `scratch`, and handlers may allocate those of
+// their responses from it with csi_scratch_alloc.
+int ckb_crypto_serve_hasher_new(const CkbCryptoHasherNewRequest* request, CkbCryptoHasherNewResponse* response,
+                                CSIScratch* scratch);
+int ckb_crypto_serve_hasher_update(const CkbCryptoHasherUpdateRequest* request, CkbCryptoHasherUpdateResponse* response,
+                                   CSIScratch* scratch);
+int ckb_crypto_serve_hasher_finalize(const CkbCryptoHasherFinalizeRequest* request,
+                                     CkbCryptoHasherFinalizeResponse* response, CSIScratch* scratch);
+int ckb_crypto_serve_secp256k1_recovery(const CkbCryptoSecp256k1RecoveryRequest* request,
+                                        CkbCryptoSecp256k1RecoveryResponse* response, CSIScratch* scratch);
+int ckb_crypto_serve_secp256k1_verify(const CkbCryptoSecp256k1VerifyRequest* request,
+                                      CkbCryptoSecp256k1VerifyResponse* response, CSIScratch* scratch);
+int ckb_crypto_serve_schnorr_verify(const CkbCryptoSchnorrVerifyRequest* request,
+                                    CkbCryptoSchnorrVerifyResponse* response, CSIScratch* scratch);
+int ckb_crypto_serve_ed25519_verify(const CkbCryptoEd25519VerifyRequest* request,
+                                    CkbCryptoEd25519VerifyResponse* response, CSIScratch* scratch);
+int ckb_crypto_serve_hasher_drop(const CkbCryptoHasherDropRequest* request, CkbCryptoHasherDropResponse* response,
+                                 CSIScratch* scratch);
+int ckb_crypto_serve_hash(const CkbCryptoHashRequest* request, CkbCryptoHashResponse* response, CSIScratch* scratch);
+int ckb_crypto_serve_secp256r1_verify(const CkbCryptoSecp256r1VerifyRequest* request,
+                                      CkbCryptoSecp256r1VerifyResponse* response, CSIScratch* scratch);
+int ckb_crypto_serve_webauthn_verify(const CkbCryptoWebauthnVerifyRequest* request,
+                                     CkbCryptoWebauthnVerifyResponse* response, CSIScratch* scratch);
+int ckb_crypto_serve_eth_personal_recover(const CkbCryptoEthPersonalRecoverRequest* request,
+                                          CkbCryptoEthPersonalRecoverResponse* response, CSIScratch* scratch);
+int ckb_crypto_serve_eth_typed_data_recover(const CkbCryptoEthTypedDataRecoverRequest* request,
+                                            CkbCryptoEthTypedDataRecoverResponse* response, CSIScratch* scratch);
+int ckb_crypto_serve_bitcoin_message_verify(const CkbCryptoBitcoinMessageVerifyRequest* request,
+                                            CkbCryptoBitcoinMessageVerifyResponse* response, CSIScratch* scratch);
+int ckb_crypto_serve_bip322_verify(const CkbCryptoBip322VerifyRequest* request, CkbCryptoBip322VerifyResponse* response,
+                                   CSIScratch* scratch);
+int ckb_crypto_serve_batch_verify(const CkbCryptoBatchVerifyRequest* request, CkbCryptoBatchVerifyResponse* response,
+                                  CSIScratch* scratch);
+int ckb_crypto_serve_multisig_verify(const CkbCryptoMultisigVerifyRequest* request,
+                                     CkbCryptoMultisigVerifyResponse* response, CSIScratch* scratch);
+int ckb_crypto_serve_bls_verify(const CkbCryptoBlsVerifyRequest* request, CkbCryptoBlsVerifyResponse* response,
+                                CSIScratch* scratch);
+int ckb_crypto_serve_bls_aggregate_verify(const CkbCryptoBlsAggregateVerifyRequest* request,
+                                          CkbCryptoBlsAggregateVerifyResponse* response, CSIScratch* scratch);
+int ckb_crypto_serve_bls_fast_aggregate_verify(const CkbCryptoBlsFastAggregateVerifyRequest* request,
+                                               CkbCryptoBlsFastAggregateVerifyResponse* response, CSIScratch* scratch);
+
+static inline int ckb_crypto_dispatch(const CSIRequestPacket* request, CSIResponsePacket* response,
+                                      CSIScratch* scratch) {
+    int err = 0;
+    CSIJsonReader reader;
+    CSIJsonWriter writer;
+    const char* method = NULL;
+    size_t method_len = 0;
+    scratch->pos = 0;
+    csi_json_reader_init(&reader, request->payload, request->payload_len);
+    err = csi_json_read_begin_envelope(&reader, &method, &method_len);
+    if (err == 0) err = csi_json_read_begin_object(&reader);
+    if (err) return err;
+    if (csi_json_key_equals(method, method_len, "HasherNew")) {
+        CkbCryptoHasherNewRequest req = {0};
+        CkbCryptoHasherNewResponse resp = {0};
+        while (err == 0) {
+            const char* key = NULL;
+            size_t key_len = 0;
+            bool end = false;
+            err = csi_json_read_key(&reader, &key, &key_len, &end);
+            if (err || end) break;
+            if (csi_json_key_equals(key, key_len, "hash_type")) {
+                if (err == 0) err = csi_json_read_raw_in(&reader, scratch, &req.hash_type);
+            } else {
+                err = csi_json_skip_value(&reader);
+            }
+        }
+        if (err == 0) err = csi_json_read_end_envelope(&reader);
+        if (err == 0) err = ckb_crypto_serve_hasher_new(&req, &resp, scratch);
+        if (err == 0) {
+            csi_json_writer_init(&writer, scratch->buf + scratch->pos, scratch->len - scratch->pos);
+            csi_json_begin_response(&writer, "HasherNew");
+            csi_json_begin_object(&writer);
+            if (resp.is_ok) {
+                csi_json_write_key(&writer, "Ok");
+                csi_json_write_raw(&writer, resp.ok.ptr, resp.ok.len);
+            } else {
+                csi_json_write_key(&writer, "Err");
+                csi_json_write_raw(&writer, resp.err.ptr, resp.err.len);
+            }
+            csi_json_end_object(&writer);
+            csi_json_end_response(&writer);
+            err = csi_json_set_response_payload(&writer, response);
+        }
+        return err;
+    }
+    if (csi_json_key_equals(method, method_len, "HasherUpdate")) {
+        CkbCryptoHasherUpdateRequest req = {0};
+        CkbCryptoHasherUpdateResponse resp = {0};
+        while (err == 0) {
+            const char* key = NULL;
+            size_t key_len = 0;
+            bool end = false;
+            err = csi_json_read_key(&reader, &key, &key_len, &end);
+            if (err || end) break;
+            if (csi_json_key_equals(key, key_len, "ctx")) {
+                if (err == 0) err = csi_json_read_raw_in(&reader, scratch, &req.ctx);
+            } else if (csi_json_key_equals(key, key_len, "data")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.data);
+            } else {
+                err = csi_json_skip_value(&reader);
+            }
+        }
+        if (err == 0) err = csi_json_read_end_envelope(&reader);
+        if (err == 0) err = ckb_crypto_serve_hasher_update(&req, &resp, scratch);
+        if (err == 0) {
+            csi_json_writer_init(&writer, scratch->buf + scratch->pos, scratch->len - scratch->pos);
+            csi_json_begin_response(&writer, "HasherUpdate");
+            csi_json_begin_object(&writer);
+            if (resp.is_ok) {
+                csi_json_write_key(&writer, "Ok");
+                csi_json_write_null(&writer);
+            } else {
+                csi_json_write_key(&writer, "Err");
+                csi_json_write_raw(&writer, resp.err.ptr, resp.err.len);
+            }
+            csi_json_end_object(&writer);
+            csi_json_end_response(&writer);
+            err = csi_json_set_response_payload(&writer, response);
+        }
+        return err;
+    }
+    if (csi_json_key_equals(method, method_len, "HasherFinalize")) {
+        CkbCryptoHasherFinalizeRequest req = {0};
+        CkbCryptoHasherFinalizeResponse resp = {0};
+        while (err == 0) {
+            const char* key = NULL;
+            size_t key_len = 0;
+            bool end = false;
+            err = csi_json_read_key(&reader, &key, &key_len, &end);
+            if (err || end) break;
+            if (csi_json_key_equals(key, key_len, "ctx")) {
+                if (err == 0) err = csi_json_read_raw_in(&reader, scratch, &req.ctx);
+            } else {
+                err = csi_json_skip_value(&reader);
+            }
+        }
+        if (err == 0) err = csi_json_read_end_envelope(&reader);
+        if (err == 0) err = ckb_crypto_serve_hasher_finalize(&req, &resp, scratch);
+        if (err == 0) {
+            csi_json_writer_init(&writer, scratch->buf + scratch->pos, scratch->len - scratch->pos);
+            csi_json_begin_response(&writer, "HasherFinalize");
+            csi_json_begin_object(&writer);
+            if (resp.is_ok) {
+                csi_json_write_key(&writer, "Ok");
+                csi_json_write_bytes(&writer, resp.ok.ptr, resp.ok.len);
+            } else {
+                csi_json_write_key(&writer, "Err");
+                csi_json_write_raw(&writer, resp.err.ptr, resp.err.len);
+            }
+            csi_json_end_object(&writer);
+            csi_json_end_response(&writer);
+            err = csi_json_set_response_payload(&writer, response);
+        }
+        return err;
+    }
+    if (csi_json_key_equals(method, method_len, "Secp256k1Recovery")) {
+        CkbCryptoSecp256k1RecoveryRequest req = {0};
+        CkbCryptoSecp256k1RecoveryResponse resp = {0};
+        while (err == 0) {
+            const char* key = NULL;
+            size_t key_len = 0;
+            bool end = false;
+            err = csi_json_read_key(&reader, &key, &key_len, &end);
+            if (err || end) break;
+            if (csi_json_key_equals(key, key_len, "prehash")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.prehash);
+            } else if (csi_json_key_equals(key, key_len, "signature")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.signature);
+            } else if (csi_json_key_equals(key, key_len, "recovery_id")) {
+                if (err == 0) {
+                    uint64_t value = 0;
+                    err = csi_json_read_uint_max(&reader, UINT8_MAX, &value);
+                    req.recovery_id = (uint8_t)value;
+                }
+            } else {
+                err = csi_json_skip_value(&reader);
+            }
+        }
+        if (err == 0) err = csi_json_read_end_envelope(&reader);
+        if (err == 0) err = ckb_crypto_serve_secp256k1_recovery(&req, &resp, scratch);
+        if (err == 0) {
+            csi_json_writer_init(&writer, scratch->buf + scratch->pos, scratch->len - scratch->pos);
+            csi_json_begin_response(&writer, "Secp256k1Recovery");
+            csi_json_begin_object(&writer);
+            if (resp.is_ok) {
+                csi_json_write_key(&writer, "Ok");
+                csi_json_write_bytes(&writer, resp.ok.ptr, resp.ok.len);
+            } else {
+                csi_json_write_key(&writer, "Err");
+                csi_json_write_raw(&writer, resp.err.ptr, resp.err.len);
+            }
+            csi_json_end_object(&writer);
+            csi_json_end_response(&writer);
+            err = csi_json_set_response_payload(&writer, response);
+        }
+        return err;
+    }
+    if (csi_json_key_equals(method, method_len, "Secp256k1Verify")) {
+        CkbCryptoSecp256k1VerifyRequest req = {0};
+        CkbCryptoSecp256k1VerifyResponse resp = {0};
+        while (err == 0) {
+            const char* key = NULL;
+            size_t key_len = 0;
+            bool end = false;
+            err = csi_json_read_key(&reader, &key, &key_len, &end);
+            if (err || end) break;
+            if (csi_json_key_equals(key, key_len, "public_key")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.public_key);
+            } else if (csi_json_key_equals(key, key_len, "prehash")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.prehash);
+            } else if (csi_json_key_equals(key, key_len, "signature")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.signature);
+            } else {
+                err = csi_json_skip_value(&reader);
+            }
+        }
+        if (err == 0) err = csi_json_read_end_envelope(&reader);
+        if (err == 0) err = ckb_crypto_serve_secp256k1_verify(&req, &resp, scratch);
+        if (err == 0) {
+            csi_json_writer_init(&writer, scratch->buf + scratch->pos, scratch->len - scratch->pos);
+            csi_json_begin_response(&writer, "Secp256k1Verify");
+            csi_json_begin_object(&writer);
+            if (resp.is_ok) {
+                csi_json_write_key(&writer, "Ok");
+                csi_json_write_null(&writer);
+            } else {
+                csi_json_write_key(&writer, "Err");
+                csi_json_write_raw(&writer, resp.err.ptr, resp.err.len);
+            }
+            csi_json_end_object(&writer);
+            csi_json_end_response(&writer);
+            err = csi_json_set_response_payload(&writer, response);
+        }
+        return err;
+    }
+    if (csi_json_key_equals(method, method_len, "SchnorrVerify")) {
+        CkbCryptoSchnorrVerifyRequest req = {0};
+        CkbCryptoSchnorrVerifyResponse resp = {0};
+        while (err == 0) {
+            const char* key = NULL;
+            size_t key_len = 0;
+            bool end = false;
+            err = csi_json_read_key(&reader, &key, &key_len, &end);
+            if (err || end) break;
+            if (csi_json_key_equals(key, key_len, "public_key")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.public_key);
+            } else if (csi_json_key_equals(key, key_len, "prehash")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.prehash);
+            } else if (csi_json_key_equals(key, key_len, "signature")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.signature);
+            } else {
+                err = csi_json_skip_value(&reader);
+            }
+        }
+        if (err == 0) err = csi_json_read_end_envelope(&reader);
+        if (err == 0) err = ckb_crypto_serve_schnorr_verify(&req, &resp, scratch);
+        if (err == 0) {
+            csi_json_writer_init(&writer, scratch->buf + scratch->pos, scratch->len - scratch->pos);
+            csi_json_begin_response(&writer, "SchnorrVerify");
+            csi_json_begin_object(&writer);
+            if (resp.is_ok) {
+                csi_json_write_key(&writer, "Ok");
+                csi_json_write_null(&writer);
+            } else {
+                csi_json_write_key(&writer, "Err");
+                csi_json_write_raw(&writer, resp.err.ptr, resp.err.len);
+            }
+            csi_json_end_object(&writer);
+            csi_json_end_response(&writer);
+            err = csi_json_set_response_payload(&writer, response);
+        }
+        return err;
+    }
+    if (csi_json_key_equals(method, method_len, "Ed25519Verify")) {
+        CkbCryptoEd25519VerifyRequest req = {0};
+        CkbCryptoEd25519VerifyResponse resp = {0};
+        while (err == 0) {
+            const char* key = NULL;
+            size_t key_len = 0;
+            bool end = false;
+            err = csi_json_read_key(&reader, &key, &key_len, &end);
+            if (err || end) break;
+            if (csi_json_key_equals(key, key_len, "public_key")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.public_key);
+            } else if (csi_json_key_equals(key, key_len, "prehash")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.prehash);
+            } else if (csi_json_key_equals(key, key_len, "signature")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.signature);
+            } else {
+                err = csi_json_skip_value(&reader);
+            }
+        }
+        if (err == 0) err = csi_json_read_end_envelope(&reader);
+        if (err == 0) err = ckb_crypto_serve_ed25519_verify(&req, &resp, scratch);
+        if (err == 0) {
+            csi_json_writer_init(&writer, scratch->buf + scratch->pos, scratch->len - scratch->pos);
+            csi_json_begin_response(&writer, "Ed25519Verify");
+            csi_json_begin_object(&writer);
+            if (resp.is_ok) {
+                csi_json_write_key(&writer, "Ok");
+                csi_json_write_null(&writer);
+            } else {
+                csi_json_write_key(&writer, "Err");
+                csi_json_write_raw(&writer, resp.err.ptr, resp.err.len);
+            }
+            csi_json_end_object(&writer);
+            csi_json_end_response(&writer);
+            err = csi_json_set_response_payload(&writer, response);
+        }
+        return err;
+    }
+    if (csi_json_key_equals(method, method_len, "HasherDrop")) {
+        CkbCryptoHasherDropRequest req = {0};
+        CkbCryptoHasherDropResponse resp = {0};
+        while (err == 0) {
+            const char* key = NULL;
+            size_t key_len = 0;
+            bool end = false;
+            err = csi_json_read_key(&reader, &key, &key_len, &end);
+            if (err || end) break;
+            if (csi_json_key_equals(key, key_len, "ctx")) {
+                if (err == 0) err = csi_json_read_raw_in(&reader, scratch, &req.ctx);
+            } else {
+                err = csi_json_skip_value(&reader);
+            }
+        }
+        if (err == 0) err = csi_json_read_end_envelope(&reader);
+        if (err == 0) err = ckb_crypto_serve_hasher_drop(&req, &resp, scratch);
+        if (err == 0) {
+            csi_json_writer_init(&writer, scratch->buf + scratch->pos, scratch->len - scratch->pos);
+            csi_json_begin_response(&writer, "HasherDrop");
+            csi_json_begin_object(&writer);
+            if (resp.is_ok) {
+                csi_json_write_key(&writer, "Ok");
+                csi_json_write_null(&writer);
+            } else {
+                csi_json_write_key(&writer, "Err");
+                csi_json_write_raw(&writer, resp.err.ptr, resp.err.len);
+            }
+            csi_json_end_object(&writer);
+            csi_json_end_response(&writer);
+            err = csi_json_set_response_payload(&writer, response);
+        }
+        return err;
+    }
+    if (csi_json_key_equals(method, method_len, "Hash")) {
+        CkbCryptoHashRequest req = {0};
+        CkbCryptoHashResponse resp = {0};
+        while (err == 0) {
+            const char* key = NULL;
+            size_t key_len = 0;
+            bool end = false;
+            err = csi_json_read_key(&reader, &key, &key_len, &end);
+            if (err || end) break;
+            if (csi_json_key_equals(key, key_len, "hash_type")) {
+                if (err == 0) err = csi_json_read_raw_in(&reader, scratch, &req.hash_type);
+            } else if (csi_json_key_equals(key, key_len, "data")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.data);
+            } else {
+                err = csi_json_skip_value(&reader);
+            }
+        }
+        if (err == 0) err = csi_json_read_end_envelope(&reader);
+        if (err == 0) err = ckb_crypto_serve_hash(&req, &resp, scratch);
+        if (err == 0) {
+            csi_json_writer_init(&writer, scratch->buf + scratch->pos, scratch->len - scratch->pos);
+            csi_json_begin_response(&writer, "Hash");
+            csi_json_begin_object(&writer);
+            if (resp.is_ok) {
+                csi_json_write_key(&writer, "Ok");
+                csi_json_write_bytes(&writer, resp.ok.ptr, resp.ok.len);
+            } else {
+                csi_json_write_key(&writer, "Err");
+                csi_json_write_raw(&writer, resp.err.ptr, resp.err.len);
+            }
+            csi_json_end_object(&writer);
+            csi_json_end_response(&writer);
+            err = csi_json_set_response_payload(&writer, response);
+        }
+        return err;
+    }
+    if (csi_json_key_equals(method, method_len, "Secp256r1Verify")) {
+        CkbCryptoSecp256r1VerifyRequest req = {0};
+        CkbCryptoSecp256r1VerifyResponse resp = {0};
+        while (err == 0) {
+            const char* key = NULL;
+            size_t key_len = 0;
+            bool end = false;
+            err = csi_json_read_key(&reader, &key, &key_len, &end);
+            if (err || end) break;
+            if (csi_json_key_equals(key, key_len, "public_key")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.public_key);
+            } else if (csi_json_key_equals(key, key_len, "prehash")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.prehash);
+            } else if (csi_json_key_equals(key, key_len, "signature")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.signature);
+            } else {
+                err = csi_json_skip_value(&reader);
+            }
+        }
+        if (err == 0) err = csi_json_read_end_envelope(&reader);
+        if (err == 0) err = ckb_crypto_serve_secp256r1_verify(&req, &resp, scratch);
+        if (err == 0) {
+            csi_json_writer_init(&writer, scratch->buf + scratch->pos, scratch->len - scratch->pos);
+            csi_json_begin_response(&writer, "Secp256r1Verify");
+            csi_json_begin_object(&writer);
+            if (resp.is_ok) {
+                csi_json_write_key(&writer, "Ok");
+                csi_json_write_null(&writer);
+            } else {
+                csi_json_write_key(&writer, "Err");
+                csi_json_write_raw(&writer, resp.err.ptr, resp.err.len);
+            }
+            csi_json_end_object(&writer);
+            csi_json_end_response(&writer);
+            err = csi_json_set_response_payload(&writer, response);
+        }
+        return err;
+    }
+    if (csi_json_key_equals(method, method_len, "WebauthnVerify")) {
+        CkbCryptoWebauthnVerifyRequest req = {0};
+        CkbCryptoWebauthnVerifyResponse resp = {0};
+        while (err == 0) {
+            const char* key = NULL;
+            size_t key_len = 0;
+            bool end = false;
+            err = csi_json_read_key(&reader, &key, &key_len, &end);
+            if (err || end) break;
+            if (csi_json_key_equals(key, key_len, "public_key")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.public_key);
+            } else if (csi_json_key_equals(key, key_len, "authenticator_data")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.authenticator_data);
+            } else if (csi_json_key_equals(key, key_len, "client_data_json")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.client_data_json);
+            } else if (csi_json_key_equals(key, key_len, "challenge")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.challenge);
+            } else if (csi_json_key_equals(key, key_len, "signature")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.signature);
+            } else {
+                err = csi_json_skip_value(&reader);
+            }
+        }
+        if (err == 0) err = csi_json_read_end_envelope(&reader);
+        if (err == 0) err = ckb_crypto_serve_webauthn_verify(&req, &resp, scratch);
+        if (err == 0) {
+            csi_json_writer_init(&writer, scratch->buf + scratch->pos, scratch->len - scratch->pos);
+            csi_json_begin_response(&writer, "WebauthnVerify");
+            csi_json_begin_object(&writer);
+            if (resp.is_ok) {
+                csi_json_write_key(&writer, "Ok");
+                csi_json_write_null(&writer);
+            } else {
+                csi_json_write_key(&writer, "Err");
+                csi_json_write_raw(&writer, resp.err.ptr, resp.err.len);
+            }
+            csi_json_end_object(&writer);
+            csi_json_end_response(&writer);
+            err = csi_json_set_response_payload(&writer, response);
+        }
+        return err;
+    }
+    if (csi_json_key_equals(method, method_len, "EthPersonalRecover")) {
+        CkbCryptoEthPersonalRecoverRequest req = {0};
+        CkbCryptoEthPersonalRecoverResponse resp = {0};
+        while (err == 0) {
+            const char* key = NULL;
+            size_t key_len = 0;
+            bool end = false;
+            err = csi_json_read_key(&reader, &key, &key_len, &end);
+            if (err || end) break;
+            if (csi_json_key_equals(key, key_len, "message")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.message);
+            } else if (csi_json_key_equals(key, key_len, "signature")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.signature);
+            } else if (csi_json_key_equals(key, key_len, "expected_address")) {
+                if (err == 0) {
+                    bool is_null = false;
+                    err = csi_json_read_null(&reader, &is_null);
+                    req.has_expected_address = !is_null;
+                }
+                if (err == 0 && req.has_expected_address) err = csi_json_read_bytes_in(&reader, scratch, &req.expected_address);
+            } else {
+                err = csi_json_skip_value(&reader);
+            }
+        }
+        if (err == 0) err = csi_json_read_end_envelope(&reader);
+        if (err == 0) err = ckb_crypto_serve_eth_personal_recover(&req, &resp, scratch);
+        if (err == 0) {
+            csi_json_writer_init(&writer, scratch->buf + scratch->pos, scratch->len - scratch->pos);
+            csi_json_begin_response(&writer, "EthPersonalRecover");
+            csi_json_begin_object(&writer);
+            if (resp.is_ok) {
+                csi_json_write_key(&writer, "Ok");
+                csi_json_write_bytes(&writer, resp.ok.ptr, resp.ok.len);
+            } else {
+                csi_json_write_key(&writer, "Err");
+                csi_json_write_raw(&writer, resp.err.ptr, resp.err.len);
+            }
+            csi_json_end_object(&writer);
+            csi_json_end_response(&writer);
+            err = csi_json_set_response_payload(&writer, response);
+        }
+        return err;
+    }
+    if (csi_json_key_equals(method, method_len, "EthTypedDataRecover")) {
+        CkbCryptoEthTypedDataRecoverRequest req = {0};
+        CkbCryptoEthTypedDataRecoverResponse resp = {0};
+        while (err == 0) {
+            const char* key = NULL;
+            size_t key_len = 0;
+            bool end = false;
+            err = csi_json_read_key(&reader, &key, &key_len, &end);
+            if (err || end) break;
+            if (csi_json_key_equals(key, key_len, "domain_separator")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.domain_separator);
+            } else if (csi_json_key_equals(key, key_len, "struct_hash")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.struct_hash);
+            } else if (csi_json_key_equals(key, key_len, "signature")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.signature);
+            } else if (csi_json_key_equals(key, key_len, "expected_address")) {
+                if (err == 0) {
+                    bool is_null = false;
+                    err = csi_json_read_null(&reader, &is_null);
+                    req.has_expected_address = !is_null;
+                }
+                if (err == 0 && req.has_expected_address) err = csi_json_read_bytes_in(&reader, scratch, &req.expected_address);
+            } else {
+                err = csi_json_skip_value(&reader);
+            }
+        }
+        if (err == 0) err = csi_json_read_end_envelope(&reader);
+        if (err == 0) err = ckb_crypto_serve_eth_typed_data_recover(&req, &resp, scratch);
+        if (err == 0) {
+            csi_json_writer_init(&writer, scratch->buf + scratch->pos, scratch->len - scratch->pos);
+            csi_json_begin_response(&writer, "EthTypedDataRecover");
+            csi_json_begin_object(&writer);
+            if (resp.is_ok) {
+                csi_json_write_key(&writer, "Ok");
+                csi_json_write_bytes(&writer, resp.ok.ptr, resp.ok.len);
+            } else {
+                csi_json_write_key(&writer, "Err");
+                csi_json_write_raw(&writer, resp.err.ptr, resp.err.len);
+            }
+            csi_json_end_object(&writer);
+            csi_json_end_response(&writer);
+            err = csi_json_set_response_payload(&writer, response);
+        }
+        return err;
+    }
+    if (csi_json_key_equals(method, method_len, "BitcoinMessageVerify")) {
+        CkbCryptoBitcoinMessageVerifyRequest req = {0};
+        CkbCryptoBitcoinMessageVerifyResponse resp = {0};
+        while (err == 0) {
+            const char* key = NULL;
+            size_t key_len = 0;
+            bool end = false;
+            err = csi_json_read_key(&reader, &key, &key_len, &end);
+            if (err || end) break;
+            if (csi_json_key_equals(key, key_len, "address_type")) {
+                if (err == 0) err = csi_json_read_raw_in(&reader, scratch, &req.address_type);
+            } else if (csi_json_key_equals(key, key_len, "message")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.message);
+            } else if (csi_json_key_equals(key, key_len, "signature")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.signature);
+            } else if (csi_json_key_equals(key, key_len, "pubkey_hash")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.pubkey_hash);
+            } else {
+                err = csi_json_skip_value(&reader);
+            }
+        }
+        if (err == 0) err = csi_json_read_end_envelope(&reader);
+        if (err == 0) err = ckb_crypto_serve_bitcoin_message_verify(&req, &resp, scratch);
+        if (err == 0) {
+            csi_json_writer_init(&writer, scratch->buf + scratch->pos, scratch->len - scratch->pos);
+            csi_json_begin_response(&writer, "BitcoinMessageVerify");
+            csi_json_begin_object(&writer);
+            if (resp.is_ok) {
+                csi_json_write_key(&writer, "Ok");
+                csi_json_write_null(&writer);
+            } else {
+                csi_json_write_key(&writer, "Err");
+                csi_json_write_raw(&writer, resp.err.ptr, resp.err.len);
+            }
+            csi_json_end_object(&writer);
+            csi_json_end_response(&writer);
+            err = csi_json_set_response_payload(&writer, response);
+        }
+        return err;
+    }
+    if (csi_json_key_equals(method, method_len, "Bip322Verify")) {
+        CkbCryptoBip322VerifyRequest req = {0};
+        CkbCryptoBip322VerifyResponse resp = {0};
+        while (err == 0) {
+            const char* key = NULL;
+            size_t key_len = 0;
+            bool end = false;
+            err = csi_json_read_key(&reader, &key, &key_len, &end);
+            if (err || end) break;
+            if (csi_json_key_equals(key, key_len, "address_type")) {
+                if (err == 0) err = csi_json_read_raw_in(&reader, scratch, &req.address_type);
+            } else if (csi_json_key_equals(key, key_len, "message")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.message);
+            } else if (csi_json_key_equals(key, key_len, "signature")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.signature);
+            } else if (csi_json_key_equals(key, key_len, "pubkey_hash")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.pubkey_hash);
+            } else {
+                err = csi_json_skip_value(&reader);
+            }
+        }
+        if (err == 0) err = csi_json_read_end_envelope(&reader);
+        if (err == 0) err = ckb_crypto_serve_bip322_verify(&req, &resp, scratch);
+        if (err == 0) {
+            csi_json_writer_init(&writer, scratch->buf + scratch->pos, scratch->len - scratch->pos);
+            csi_json_begin_response(&writer, "Bip322Verify");
+            csi_json_begin_object(&writer);
+            if (resp.is_ok) {
+                csi_json_write_key(&writer, "Ok");
+                csi_json_write_null(&writer);
+            } else {
+                csi_json_write_key(&writer, "Err");
+                csi_json_write_raw(&writer, resp.err.ptr, resp.err.len);
+            }
+            csi_json_end_object(&writer);
+            csi_json_end_response(&writer);
+            err = csi_json_set_response_payload(&writer, response);
+        }
+        return err;
+    }
+    if (csi_json_key_equals(method, method_len, "BatchVerify")) {
+        CkbCryptoBatchVerifyRequest req = {0};
+        CkbCryptoBatchVerifyResponse resp = {0};
+        while (err == 0) {
+            const char* key = NULL;
+            size_t key_len = 0;
+            bool end = false;
+            err = csi_json_read_key(&reader, &key, &key_len, &end);
+            if (err || end) break;
+            if (csi_json_key_equals(key, key_len, "items")) {
+                if (err == 0) err = csi_json_read_raw_in(&reader, scratch, &req.items);
+            } else {
+                err = csi_json_skip_value(&reader);
+            }
+        }
+        if (err == 0) err = csi_json_read_end_envelope(&reader);
+        if (err == 0) err = ckb_crypto_serve_batch_verify(&req, &resp, scratch);
+        if (err == 0) {
+            csi_json_writer_init(&writer, scratch->buf + scratch->pos, scratch->len - scratch->pos);
+            csi_json_begin_response(&writer, "BatchVerify");
+            csi_json_write_raw(&writer, resp.ret.ptr, resp.ret.len);
+            csi_json_end_response(&writer);
+            err = csi_json_set_response_payload(&writer, response);
+        }
+        return err;
+    }
+    if (csi_json_key_equals(method, method_len, "MultisigVerify")) {
+        CkbCryptoMultisigVerifyRequest req = {0};
+        CkbCryptoMultisigVerifyResponse resp = {0};
+        while (err == 0) {
+            const char* key = NULL;
+            size_t key_len = 0;
+            bool end = false;
+            err = csi_json_read_key(&reader, &key, &key_len, &end);
+            if (err || end) break;
+            if (csi_json_key_equals(key, key_len, "multisig_script")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.multisig_script);
+            } else if (csi_json_key_equals(key, key_len, "message")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.message);
+            } else if (csi_json_key_equals(key, key_len, "signatures")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.signatures);
+            } else {
+                err = csi_json_skip_value(&reader);
+            }
+        }
+        if (err == 0) err = csi_json_read_end_envelope(&reader);
+        if (err == 0) err = ckb_crypto_serve_multisig_verify(&req, &resp, scratch);
+        if (err == 0) {
+            csi_json_writer_init(&writer, scratch->buf + scratch->pos, scratch->len - scratch->pos);
+            csi_json_begin_response(&writer, "MultisigVerify");
+            csi_json_begin_object(&writer);
+            if (resp.is_ok) {
+                csi_json_write_key(&writer, "Ok");
+                csi_json_write_bytes(&writer, resp.ok.ptr, resp.ok.len);
+            } else {
+                csi_json_write_key(&writer, "Err");
+                csi_json_write_raw(&writer, resp.err.ptr, resp.err.len);
+            }
+            csi_json_end_object(&writer);
+            csi_json_end_response(&writer);
+            err = csi_json_set_response_payload(&writer, response);
+        }
+        return err;
+    }
+    if (csi_json_key_equals(method, method_len, "BlsVerify")) {
+        CkbCryptoBlsVerifyRequest req = {0};
+        CkbCryptoBlsVerifyResponse resp = {0};
+        while (err == 0) {
+            const char* key = NULL;
+            size_t key_len = 0;
+            bool end = false;
+            err = csi_json_read_key(&reader, &key, &key_len, &end);
+            if (err || end) break;
+            if (csi_json_key_equals(key, key_len, "public_key")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.public_key);
+            } else if (csi_json_key_equals(key, key_len, "message")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.message);
+            } else if (csi_json_key_equals(key, key_len, "signature")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.signature);
+            } else {
+                err = csi_json_skip_value(&reader);
+            }
+        }
+        if (err == 0) err = csi_json_read_end_envelope(&reader);
+        if (err == 0) err = ckb_crypto_serve_bls_verify(&req, &resp, scratch);
+        if (err == 0) {
+            csi_json_writer_init(&writer, scratch->buf + scratch->pos, scratch->len - scratch->pos);
+            csi_json_begin_response(&writer, "BlsVerify");
+            csi_json_begin_object(&writer);
+            if (resp.is_ok) {
+                csi_json_write_key(&writer, "Ok");
+                csi_json_write_null(&writer);
+            } else {
+                csi_json_write_key(&writer, "Err");
+                csi_json_write_raw(&writer, resp.err.ptr, resp.err.len);
+            }
+            csi_json_end_object(&writer);
+            csi_json_end_response(&writer);
+            err = csi_json_set_response_payload(&writer, response);
+        }
+        return err;
+    }
+    if (csi_json_key_equals(method, method_len, "BlsAggregateVerify")) {
+        CkbCryptoBlsAggregateVerifyRequest req = {0};
+        CkbCryptoBlsAggregateVerifyResponse resp = {0};
+        while (err == 0) {
+            const char* key = NULL;
+            size_t key_len = 0;
+            bool end = false;
+            err = csi_json_read_key(&reader, &key, &key_len, &end);
+            if (err || end) break;
+            if (csi_json_key_equals(key, key_len, "public_keys")) {
+                if (err == 0) err = csi_json_read_raw_in(&reader, scratch, &req.public_keys);
+            } else if (csi_json_key_equals(key, key_len, "messages")) {
+                if (err == 0) err = csi_json_read_raw_in(&reader, scratch, &req.messages);
+            } else if (csi_json_key_equals(key, key_len, "signature")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.signature);
+            } else {
+                err = csi_json_skip_value(&reader);
+            }
+        }
+        if (err == 0) err = csi_json_read_end_envelope(&reader);
+        if (err == 0) err = ckb_crypto_serve_bls_aggregate_verify(&req, &resp, scratch);
+        if (err == 0) {
+            csi_json_writer_init(&writer, scratch->buf + scratch->pos, scratch->len - scratch->pos);
+            csi_json_begin_response(&writer, "BlsAggregateVerify");
+            csi_json_begin_object(&writer);
+            if (resp.is_ok) {
+                csi_json_write_key(&writer, "Ok");
+                csi_json_write_null(&writer);
+            } else {
+                csi_json_write_key(&writer, "Err");
+                csi_json_write_raw(&writer, resp.err.ptr, resp.err.len);
+            }
+            csi_json_end_object(&writer);
+            csi_json_end_response(&writer);
+            err = csi_json_set_response_payload(&writer, response);
+        }
+        return err;
+    }
+    if (csi_json_key_equals(method, method_len, "BlsFastAggregateVerify")) {
+        CkbCryptoBlsFastAggregateVerifyRequest req = {0};
+        CkbCryptoBlsFastAggregateVerifyResponse resp = {0};
+        while (err == 0) {
+            const char* key = NULL;
+            size_t key_len = 0;
+            bool end = false;
+            err = csi_json_read_key(&reader, &key, &key_len, &end);
+            if (err || end) break;
+            if (csi_json_key_equals(key, key_len, "public_keys")) {
+                if (err == 0) err = csi_json_read_raw_in(&reader, scratch, &req.public_keys);
+            } else if (csi_json_key_equals(key, key_len, "message")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.message);
+            } else if (csi_json_key_equals(key, key_len, "signature")) {
+                if (err == 0) err = csi_json_read_bytes_in(&reader, scratch, &req.signature);
+            } else {
+                err = csi_json_skip_value(&reader);
+            }
+        }
+        if (err == 0) err = csi_json_read_end_envelope(&reader);
+        if (err == 0) err = ckb_crypto_serve_bls_fast_aggregate_verify(&req, &resp, scratch);
+        if (err == 0) {
+            csi_json_writer_init(&writer, scratch->buf + scratch->pos, scratch->len - scratch->pos);
+            csi_json_begin_response(&writer, "BlsFastAggregateVerify");
+            csi_json_begin_object(&writer);
+            if (resp.is_ok) {
+                csi_json_write_key(&writer, "Ok");
+                csi_json_write_null(&writer);
+            } else {
+                csi_json_write_key(&writer, "Err");
+                csi_json_write_raw(&writer, resp.err.ptr, resp.err.len);
+            }
+            csi_json_end_object(&writer);
+            csi_json_end_response(&writer);
+            err = csi_json_set_response_payload(&writer, response);
+        }
+        return err;
+    }
+    response->error_code = CSI_UNKNOWN_METHOD;
+    return 0;
+}
+
+#endif
@@ -0,0 +1,14 @@
+// The subset of ckb-c-stdlib constants used by ckb_script_ipc.c, for building it natively.
+
+#ifndef __CKB_CONSTS_NATIVE_H__
+#define __CKB_CONSTS_NATIVE_H__
+
+#define CKB_SUCCESS 0
+#define CKB_INDEX_OUT_OF_BOUND 1
+#define CKB_ITEM_MISSING 2
+#define CKB_INVALID_FD 6
+#define CKB_OTHER_END_CLOSED 7
+
+#define CKB_SOURCE_CELL_DEP 3
+
+#endif
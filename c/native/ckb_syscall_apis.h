@@ -0,0 +1,27 @@
+// Declarations of the ckb-c-stdlib syscalls used by ckb_script_ipc.c, for building it
+// natively (e.g. for fuzzing). The syscalls themselves are stubbed in ckb_syscalls_native.c.
+
+#ifndef __CKB_SYSCALL_APIS_NATIVE_H__
+#define __CKB_SYSCALL_APIS_NATIVE_H__
+#include <stddef.h>
+#include <stdint.h>
+
+typedef struct spawn_args_t {
+    size_t argc;
+    const char** argv;
+    uint64_t* process_id;
+    const uint64_t* inherited_fds;
+} spawn_args_t;
+
+void ckb_exit(int8_t code);
+int ckb_spawn(size_t index, size_t source, size_t place, size_t bounds, spawn_args_t* spawn_args);
+int ckb_wait(uint64_t pid, int8_t* exit_code);
+uint64_t ckb_process_id(void);
+int ckb_pipe(uint64_t fds[2]);
+int ckb_read(uint64_t fd, void* buffer, size_t* length);
+int ckb_write(uint64_t fd, const void* buffer, size_t* length);
+int ckb_inherited_fds(uint64_t* fds, size_t* length);
+int ckb_close(uint64_t fd);
+int ckb_look_for_dep_with_hash2(const uint8_t* code_hash, uint8_t hash_type, size_t* index);
+
+#endif
@@ -0,0 +1,32 @@
+// Stubs of the ckb-c-stdlib syscalls, so ckb_script_ipc.c can be linked natively. There are
+// no processes or pipes off-chain: code built this way must use its own CSIReader/CSIWriter.
+#include <stdlib.h>
+#include "ckb_syscall_apis.h"
+#include "ckb_consts.h"
+
+void ckb_exit(int8_t code) { exit(code); }
+
+int ckb_spawn(size_t index, size_t source, size_t place, size_t bounds, spawn_args_t* spawn_args) {
+    return CKB_INDEX_OUT_OF_BOUND;
+}
+
+int ckb_wait(uint64_t pid, int8_t* exit_code) { return CKB_INDEX_OUT_OF_BOUND; }
+
+uint64_t ckb_process_id(void) { return 0; }
+
+int ckb_pipe(uint64_t fds[2]) { return CKB_INVALID_FD; }
+
+int ckb_read(uint64_t fd, void* buffer, size_t* length) { return CKB_INVALID_FD; }
+
+int ckb_write(uint64_t fd, const void* buffer, size_t* length) { return CKB_INVALID_FD; }
+
+int ckb_inherited_fds(uint64_t* fds, size_t* length) {
+    *length = 0;
+    return CKB_SUCCESS;
+}
+
+int ckb_close(uint64_t fd) { return CKB_INVALID_FD; }
+
+int ckb_look_for_dep_with_hash2(const uint8_t* code_hash, uint8_t hash_type, size_t* index) {
+    return CKB_ITEM_MISSING;
+}
@@ -1,5 +1,6 @@
 #include <stdlib.h>
 #include <stdio.h>
+#include <string.h>
 #include "ckb_syscalls.h"
 #include "ckb_consts.h"
 #include "ckb_script_ipc.h"
@@ -13,8 +14,8 @@ int main() {
     csi_init_iobuf(g_io_buf, sizeof(g_io_buf), 2);
 
     int err = 0;
-    CSIChannel channel;
-    err = csi_spawn_server(0, CKB_SOURCE_CELL_DEP, 0, 0, NULL, 0, &channel);
+    CSIServerHandle server;
+    err = csi_spawn_server_handle(0, CKB_SOURCE_CELL_DEP, 0, 0, NULL, 0, &server);
     if (err) {
         printf("failed to spawn server: %d\n", err);
         return err;
@@ -36,7 +37,7 @@ int main() {
         request.payload = payload;
 
         CSIResponsePacket response;
-        err = csi_call(&channel, &request, &response);
+        err = csi_call(&server.channel, &request, &response);
         if (err) {
             printf("failed to call server: %d\n", err);
             return err;
@@ -49,4 +50,41 @@ int main() {
         }
         csi_client_free_response_payload(&response);
     }
+
+    // unknown methods are answered with an error code, and the server keeps running
+    CSIRequestPacket request = {.version = 0, .method_id = 42, .payload_len = 0, .payload = NULL};
+    CSIResponsePacket response;
+    err = csi_call(&server.channel, &request, &response);
+    if (err != CSI_UNKNOWN_METHOD) {
+        printf("unexpected result of unknown method: %d\n", err);
+        return -43;
+    }
+    csi_client_free_response_payload(&response);
+
+    request.method_id = 2;
+    err = csi_call(&server.channel, &request, &response);
+    if (err) {
+        printf("failed to call server: %d\n", err);
+        return err;
+    }
+    uint64_t calls = 0;
+    memcpy(&calls, response.payload, 8);
+    if (calls != loop_count) {
+        printf("The calls are wrong: %lu vs %lu", calls, (uint64_t)loop_count);
+        return -44;
+    }
+    csi_client_free_response_payload(&response);
+
+    // the server returns once the channel is closed
+    int8_t exit_code = -1;
+    err = csi_wait_server_handle(&server, &exit_code);
+    if (err) {
+        printf("failed to wait server: %d\n", err);
+        return err;
+    }
+    if (exit_code != 0) {
+        printf("unexpected exit code of server: %d\n", exit_code);
+        return -45;
+    }
+    return 0;
 }
@@ -0,0 +1,23 @@
+// Compiles the checked-in header generated from the `CkbCrypto` service, so changes to the generator
+// which break C code are caught by `make tests`. Only built into an object, as the handlers are missing.
+#include "ckb_crypto.h"
+
+// Method ids are part of the wire format, and must not change when methods are added.
+_Static_assert(CKB_CRYPTO_HASHER_NEW == 0, "method id changed");
+_Static_assert(CKB_CRYPTO_ED25519_VERIFY == 6, "method id changed");
+_Static_assert(CKB_CRYPTO_HASHER_DROP == 7, "method id changed");
+_Static_assert(CKB_CRYPTO_BLS_FAST_AGGREGATE_VERIFY == 19, "method id changed");
+
+int serve(const CSIRequestPacket* request, CSIResponsePacket* response) {
+    static uint8_t buf[4096];
+    CSIScratch scratch = {buf, sizeof(buf), 0};
+    return ckb_crypto_dispatch(request, response, &scratch);
+}
+
+int hasher_new(CSIChannel* channel) {
+    uint8_t buf[1024];
+    CSIScratch scratch = {buf, sizeof(buf), 0};
+    CkbCryptoHasherNewRequest request = {.hash_type = {"\"Sha256\"", 8}};
+    CkbCryptoHasherNewResponse response;
+    return ckb_crypto_hasher_new(channel, &request, &response, &scratch);
+}
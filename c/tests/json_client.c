@@ -0,0 +1,219 @@
+// Calls the `UnitTests` service of the Rust `unit-tests` script with JSON payloads.
+#include <stdlib.h>
+#include <stdio.h>
+#include "ckb_syscalls.h"
+#include "ckb_consts.h"
+#include "ckb_script_ipc.h"
+
+static uint8_t g_payload_buf[4096];
+static uint8_t g_io_buf[1024];
+
+#define CHECK(e)                                                       \
+    do {                                                               \
+        int _err = (e);                                                \
+        if (_err) {                                                    \
+            printf("failed at %s:%d, error: %d", __FILE__, __LINE__, _err); \
+            return _err;                                               \
+        }                                                              \
+    } while (0)
+
+#define ASSERT(cond)                                         \
+    do {                                                     \
+        if (!(cond)) {                                       \
+            printf("assertion failed at %s:%d", __FILE__, __LINE__); \
+            return -1;                                       \
+        }                                                    \
+    } while (0)
+
+// Sends the request in `writer` and begins reading the response of `method`.
+static int call(CSIChannel* channel, CSIJsonWriter* writer, const char* method, CSIResponsePacket* response,
+                CSIJsonReader* reader) {
+    size_t len = 0;
+    CHECK(csi_json_writer_finish(writer, &len));
+    CSIRequestPacket request = {.version = 0, .method_id = 0, .payload_len = len, .payload = writer->buf};
+    CHECK(csi_call(channel, &request, response));
+    csi_json_reader_init(reader, response->payload, response->payload_len);
+    const char* name = NULL;
+    size_t name_len = 0;
+    CHECK(csi_json_read_begin_envelope(reader, &name, &name_len));
+    ASSERT(csi_json_key_equals(name, name_len, method));
+    return 0;
+}
+
+// Calls a method returning `()`.
+static int call_unit(CSIChannel* channel, CSIJsonWriter* writer, const char* method) {
+    CSIResponsePacket response = {0};
+    CSIJsonReader reader;
+    CHECK(call(channel, writer, method, &response, &reader));
+    bool is_null = false;
+    CHECK(csi_json_read_null(&reader, &is_null));
+    ASSERT(is_null);
+    CHECK(csi_json_read_end_envelope(&reader));
+    csi_client_free_response_payload(&response);
+    return 0;
+}
+
+static int test_primitive_types(CSIChannel* channel) {
+    uint8_t buf[256];
+    CSIJsonWriter writer;
+    csi_json_writer_init(&writer, buf, sizeof(buf));
+    csi_json_begin_request(&writer, "TestPrimitiveTypes");
+    const char* keys[] = {"arg1", "arg2", "arg3", "arg4", "arg5", "arg6", "arg7", "arg8", "arg9", "arg10"};
+    for (size_t i = 0; i < 10; i++) {
+        csi_json_write_key(&writer, keys[i]);
+        csi_json_write_int(&writer, (int64_t)i + 1);
+    }
+    csi_json_write_key(&writer, "arg11");
+    csi_json_write_bool(&writer, true);
+    csi_json_end_request(&writer);
+    return call_unit(channel, &writer, "TestPrimitiveTypes");
+}
+
+static int test_vec(CSIChannel* channel) {
+    uint8_t buf[64];
+    CSIJsonWriter writer;
+    csi_json_writer_init(&writer, buf, sizeof(buf));
+    csi_json_begin_request(&writer, "TestVec");
+    csi_json_write_key(&writer, "vec");
+    csi_json_begin_array(&writer);
+    for (int64_t i = 1; i <= 5; i++) {
+        csi_json_write_int(&writer, i);
+    }
+    csi_json_end_array(&writer);
+    csi_json_end_request(&writer);
+    return call_unit(channel, &writer, "TestVec");
+}
+
+static int test_btree_map(CSIChannel* channel) {
+    uint8_t buf[64];
+    CSIJsonWriter writer;
+    csi_json_writer_init(&writer, buf, sizeof(buf));
+    csi_json_begin_request(&writer, "TestBtreeMap");
+    csi_json_write_key(&writer, "map");
+    csi_json_begin_object(&writer);
+    csi_json_write_key(&writer, "one");
+    csi_json_write_int(&writer, 1);
+    csi_json_write_key(&writer, "three");
+    csi_json_write_int(&writer, 3);
+    csi_json_write_key(&writer, "two");
+    csi_json_write_int(&writer, 2);
+    csi_json_end_object(&writer);
+    csi_json_end_request(&writer);
+    return call_unit(channel, &writer, "TestBtreeMap");
+}
+
+static int test_complex_types(CSIChannel* channel) {
+    uint8_t buf[256];
+    CSIJsonWriter writer;
+    csi_json_writer_init(&writer, buf, sizeof(buf));
+    csi_json_begin_request(&writer, "TestComplexTypes");
+    csi_json_write_key(&writer, "arg1");
+    csi_json_begin_object(&writer);
+    csi_json_write_key(&writer, "f1");
+    csi_json_write_uint(&writer, 1);
+    csi_json_write_key(&writer, "f2");
+    csi_json_write_uint(&writer, 2);
+    csi_json_write_key(&writer, "f3");
+    csi_json_write_bytes(&writer, "\x03\x03\x03", 3);
+    csi_json_write_key(&writer, "f4");
+    csi_json_begin_array(&writer);
+    csi_json_write_bytes(&writer, "\x04\x04\x04\x04\x04", 5);
+    csi_json_write_bytes(&writer, "\x05\x05\x05\x05\x05", 5);
+    csi_json_end_array(&writer);
+    csi_json_write_key(&writer, "f5");
+    csi_json_write_hex(&writer, "\x06\x07\x08", 3);
+    csi_json_write_key(&writer, "f6");
+    csi_json_write_string(&writer, "test", 4);
+    csi_json_write_key(&writer, "f7");
+    csi_json_write_uint(&writer, 9);
+    csi_json_write_key(&writer, "f8");
+    csi_json_begin_array(&writer);
+    csi_json_write_bytes(&writer, "\x0a\x0b", 2);
+    csi_json_write_bytes(&writer, "\x0c\x0d", 2);
+    csi_json_end_array(&writer);
+    csi_json_write_key(&writer, "f9");
+    csi_json_begin_object(&writer);
+    csi_json_write_key(&writer, "f0");
+    csi_json_write_uint(&writer, 14);
+    csi_json_write_key(&writer, "f1");
+    csi_json_write_uint(&writer, 15);
+    csi_json_write_key(&writer, "f2");
+    csi_json_write_bytes(&writer, "\x10\x11\x12", 3);
+    csi_json_end_object(&writer);
+    csi_json_end_object(&writer);
+    csi_json_end_request(&writer);
+    return call_unit(channel, &writer, "TestComplexTypes");
+}
+
+static int test_return_types(CSIChannel* channel) {
+    uint8_t buf[64];
+    CSIJsonWriter writer;
+    csi_json_writer_init(&writer, buf, sizeof(buf));
+    csi_json_begin_request(&writer, "TestReturnTypes");
+    csi_json_end_request(&writer);
+
+    CSIResponsePacket response = {0};
+    CSIJsonReader reader;
+    CHECK(call(channel, &writer, "TestReturnTypes", &response, &reader));
+    // {"Ok":42}
+    const char* key = NULL;
+    size_t key_len = 0;
+    bool end = false;
+    CHECK(csi_json_read_begin_object(&reader));
+    CHECK(csi_json_read_key(&reader, &key, &key_len, &end));
+    ASSERT(!end && csi_json_key_equals(key, key_len, "Ok"));
+    uint64_t value = 0;
+    CHECK(csi_json_read_uint(&reader, &value));
+    ASSERT(value == 42);
+    CHECK(csi_json_read_key(&reader, &key, &key_len, &end));
+    ASSERT(end);
+    CHECK(csi_json_read_end_envelope(&reader));
+    csi_client_free_response_payload(&response);
+    return 0;
+}
+
+static int test_large_input_output(CSIChannel* channel) {
+    uint8_t input[200];
+    for (size_t i = 0; i < sizeof(input); i++) {
+        input[i] = (uint8_t)i;
+    }
+    uint8_t buf[1024];
+    CSIJsonWriter writer;
+    csi_json_writer_init(&writer, buf, sizeof(buf));
+    csi_json_begin_request(&writer, "TestLargeInputOutput");
+    csi_json_write_key(&writer, "input");
+    csi_json_write_bytes(&writer, input, sizeof(input));
+    csi_json_end_request(&writer);
+
+    CSIResponsePacket response = {0};
+    CSIJsonReader reader;
+    CHECK(call(channel, &writer, "TestLargeInputOutput", &response, &reader));
+    uint8_t output[sizeof(input)];
+    size_t output_len = 0;
+    CHECK(csi_json_read_bytes(&reader, output, sizeof(output), &output_len));
+    ASSERT(output_len == sizeof(input));
+    for (size_t i = 0; i < sizeof(input); i++) {
+        ASSERT(output[i] == (uint8_t)(input[i] + 1));
+    }
+    CHECK(csi_json_read_end_envelope(&reader));
+    csi_client_free_response_payload(&response);
+    return 0;
+}
+
+int main() {
+    csi_init_payload(g_payload_buf, sizeof(g_payload_buf), 2);
+    csi_init_iobuf(g_io_buf, sizeof(g_io_buf), 2);
+
+    CSIChannel channel;
+    // any argument starts `unit-tests` as a server
+    const char* argv[] = {"server"};
+    CHECK(csi_spawn_server(0, CKB_SOURCE_CELL_DEP, 0, 0, argv, 1, &channel));
+
+    CHECK(test_primitive_types(&channel));
+    CHECK(test_vec(&channel));
+    CHECK(test_btree_map(&channel));
+    CHECK(test_complex_types(&channel));
+    CHECK(test_return_types(&channel));
+    CHECK(test_large_input_output(&channel));
+    return 0;
+}
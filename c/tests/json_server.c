@@ -0,0 +1,209 @@
+// Serves the `UnitTests` service of the Rust `unit-tests` script with JSON payloads.
+#include <stdint.h>
+#include <stdio.h>
+#include <string.h>
+#include "ckb_syscalls.h"
+#include "ckb_script_ipc.h"
+
+static uint8_t g_payload_buf[4096];
+static uint8_t g_io_buf[1024];
+
+#define CHECK(e)                                                       \
+    do {                                                               \
+        int _err = (e);                                                \
+        if (_err) {                                                    \
+            printf("failed at %s:%d, error: %d", __FILE__, __LINE__, _err); \
+            return _err;                                               \
+        }                                                              \
+    } while (0)
+
+#define ASSERT(cond)                                         \
+    do {                                                     \
+        if (!(cond)) {                                       \
+            printf("assertion failed at %s:%d", __FILE__, __LINE__); \
+            return -1;                                       \
+        }                                                    \
+    } while (0)
+
+// Reads the next field of an object, which must be `expected`.
+static int read_key(CSIJsonReader* reader, const char* expected) {
+    const char* key = NULL;
+    size_t key_len = 0;
+    bool end = false;
+    CHECK(csi_json_read_key(reader, &key, &key_len, &end));
+    ASSERT(!end && csi_json_key_equals(key, key_len, expected));
+    return 0;
+}
+
+static int read_end(CSIJsonReader* reader) {
+    const char* key = NULL;
+    size_t key_len = 0;
+    bool end = false;
+    CHECK(csi_json_read_key(reader, &key, &key_len, &end));
+    ASSERT(end);
+    return 0;
+}
+
+static int expect_bytes(CSIJsonReader* reader, const void* expected, size_t expected_len) {
+    uint8_t buf[16];
+    size_t len = 0;
+    CHECK(csi_json_read_bytes(reader, buf, sizeof(buf), &len));
+    ASSERT(len == expected_len && memcmp(buf, expected, len) == 0);
+    return 0;
+}
+
+static int expect_uint(CSIJsonReader* reader, uint64_t expected) {
+    uint64_t value = 0;
+    CHECK(csi_json_read_uint(reader, &value));
+    ASSERT(value == expected);
+    return 0;
+}
+
+static int test_primitive_types(CSIJsonReader* reader) {
+    const char* keys[] = {"arg1", "arg2", "arg3", "arg4", "arg5", "arg6", "arg7", "arg8", "arg9", "arg10"};
+    for (size_t i = 0; i < 10; i++) {
+        CHECK(read_key(reader, keys[i]));
+        int64_t value = 0;
+        CHECK(csi_json_read_int(reader, &value));
+        ASSERT(value == (int64_t)i + 1);
+    }
+    CHECK(read_key(reader, "arg11"));
+    bool value = false;
+    CHECK(csi_json_read_bool(reader, &value));
+    ASSERT(value);
+    return 0;
+}
+
+static int test_vec(CSIJsonReader* reader) {
+    CHECK(read_key(reader, "vec"));
+    CHECK(csi_json_read_begin_array(reader));
+    for (int64_t i = 1;; i++) {
+        bool end = false;
+        CHECK(csi_json_read_next(reader, &end));
+        if (end) {
+            ASSERT(i == 6);
+            break;
+        }
+        int64_t value = 0;
+        CHECK(csi_json_read_int(reader, &value));
+        ASSERT(value == i);
+    }
+    return 0;
+}
+
+static int test_btree_map(CSIJsonReader* reader) {
+    CHECK(read_key(reader, "map"));
+    CHECK(csi_json_read_begin_object(reader));
+    // keys are sorted
+    const char* keys[] = {"one", "three", "two"};
+    const int64_t values[] = {1, 3, 2};
+    for (size_t i = 0; i < 3; i++) {
+        CHECK(read_key(reader, keys[i]));
+        int64_t value = 0;
+        CHECK(csi_json_read_int(reader, &value));
+        ASSERT(value == values[i]);
+    }
+    return read_end(reader);
+}
+
+static int test_complex_types(CSIJsonReader* reader) {
+    CHECK(read_key(reader, "arg1"));
+    CHECK(csi_json_read_begin_object(reader));
+    CHECK(read_key(reader, "f1"));
+    CHECK(expect_uint(reader, 1));
+    CHECK(read_key(reader, "f2"));
+    CHECK(expect_uint(reader, 2));
+    CHECK(read_key(reader, "f3"));
+    CHECK(expect_bytes(reader, "\x03\x03\x03", 3));
+    CHECK(read_key(reader, "f4"));
+    CHECK(csi_json_skip_value(reader));
+    CHECK(read_key(reader, "f5"));
+    uint8_t hex[8];
+    size_t hex_len = 0;
+    CHECK(csi_json_read_hex(reader, hex, sizeof(hex), &hex_len));
+    ASSERT(hex_len == 3 && memcmp(hex, "\x06\x07\x08", 3) == 0);
+    CHECK(read_key(reader, "f6"));
+    char str[8];
+    size_t str_len = 0;
+    CHECK(csi_json_read_string(reader, str, sizeof(str), &str_len));
+    ASSERT(str_len == 4 && memcmp(str, "test", 4) == 0);
+    CHECK(read_key(reader, "f7"));
+    bool is_null = false;
+    CHECK(csi_json_read_null(reader, &is_null));
+    ASSERT(!is_null);
+    CHECK(expect_uint(reader, 9));
+    CHECK(read_key(reader, "f8"));
+    CHECK(csi_json_read_begin_array(reader));
+    bool end = false;
+    CHECK(csi_json_read_next(reader, &end));
+    CHECK(expect_bytes(reader, "\x0a\x0b", 2));
+    CHECK(csi_json_read_next(reader, &end));
+    CHECK(expect_bytes(reader, "\x0c\x0d", 2));
+    CHECK(csi_json_read_next(reader, &end));
+    ASSERT(end);
+    CHECK(read_key(reader, "f9"));
+    CHECK(csi_json_read_begin_object(reader));
+    CHECK(read_key(reader, "f0"));
+    CHECK(expect_uint(reader, 14));
+    CHECK(read_key(reader, "f1"));
+    CHECK(expect_uint(reader, 15));
+    CHECK(read_key(reader, "f2"));
+    CHECK(expect_bytes(reader, "\x10\x11\x12", 3));
+    CHECK(read_end(reader));
+    return read_end(reader);
+}
+
+static int serve(const CSIRequestPacket* request, CSIResponsePacket* response) {
+    CSIJsonReader reader;
+    csi_json_reader_init(&reader, request->payload, request->payload_len);
+    const char* method = NULL;
+    size_t method_len = 0;
+    CHECK(csi_json_read_begin_envelope(&reader, &method, &method_len));
+    CHECK(csi_json_read_begin_object(&reader));
+
+    uint8_t buf[64];
+    CSIJsonWriter writer;
+    csi_json_writer_init(&writer, buf, sizeof(buf));
+    if (csi_json_key_equals(method, method_len, "TestPrimitiveTypes")) {
+        CHECK(test_primitive_types(&reader));
+        csi_json_begin_response(&writer, "TestPrimitiveTypes");
+        csi_json_write_null(&writer);
+    } else if (csi_json_key_equals(method, method_len, "TestVec")) {
+        CHECK(test_vec(&reader));
+        csi_json_begin_response(&writer, "TestVec");
+        csi_json_write_null(&writer);
+    } else if (csi_json_key_equals(method, method_len, "TestBtreeMap")) {
+        CHECK(test_btree_map(&reader));
+        csi_json_begin_response(&writer, "TestBtreeMap");
+        csi_json_write_null(&writer);
+    } else if (csi_json_key_equals(method, method_len, "TestComplexTypes")) {
+        CHECK(test_complex_types(&reader));
+        csi_json_begin_response(&writer, "TestComplexTypes");
+        csi_json_write_null(&writer);
+    } else if (csi_json_key_equals(method, method_len, "TestReturnTypes")) {
+        // Ok(42)
+        csi_json_begin_response(&writer, "TestReturnTypes");
+        csi_json_begin_object(&writer);
+        csi_json_write_key(&writer, "Ok");
+        csi_json_write_uint(&writer, 42);
+        csi_json_end_object(&writer);
+    } else {
+        return CSI_ERROR_INVALID_REQUEST;
+    }
+    csi_json_end_response(&writer);
+    CHECK(read_end(&reader));
+    CHECK(csi_json_read_end_envelope(&reader));
+
+    size_t len = 0;
+    CHECK(csi_json_writer_finish(&writer, &len));
+    response->payload_len = len;
+    csi_server_malloc_response_payload(response);
+    memcpy(response->payload, buf, len);
+    return 0;
+}
+
+int main() {
+    csi_init_payload(g_payload_buf, sizeof(g_payload_buf), 2);
+    csi_init_iobuf(g_io_buf, sizeof(g_io_buf), 2);
+    return csi_run_server(serve);
+}
@@ -1,18 +1,19 @@
 #include <stdint.h>
+#include <string.h>
 #include "ckb_syscalls.h"
 #include "ckb_script_ipc.h"
 
 static uint8_t g_payload_buf[4096];
 static uint8_t g_io_buf[1024];
 
-static int serve_call_back(const CSIRequestPacket* request, CSIResponsePacket* response) {
-    printf("serve callback");
-    int err = 0;
-    // Only accept requests with method_id == 1
-    if (request->method_id != 1) {
-        printf("Ignoring request with method_id %lu (expected: 1)\n", request->method_id);
-        return 0;
-    }
+typedef struct Stats {
+    uint64_t calls;
+} Stats;
+
+// method 1: sum of the payload bytes
+static int serve_sum(void* ctx, const CSIRequestPacket* request, CSIResponsePacket* response) {
+    Stats* stats = ctx;
+    stats->calls++;
     uint64_t sum = 0;
 
     for (size_t i = 0; i < request->payload_len; i++) {
@@ -21,13 +22,31 @@ static int serve_call_back(const CSIRequestPacket* request, CSIResponsePacket* r
 
     response->payload_len = 8;
     csi_server_malloc_response_payload(response);
-    *((uint64_t*)response->payload) = sum;
+    memcpy(response->payload, &sum, 8);
+    return 0;
+}
+
+// method 2: number of calls to method 1
+static int serve_calls(void* ctx, const CSIRequestPacket* request, CSIResponsePacket* response) {
+    Stats* stats = ctx;
+    response->payload_len = 8;
+    csi_server_malloc_response_payload(response);
+    memcpy(response->payload, &stats->calls, 8);
     return 0;
 }
 
 int main() {
+    static Stats stats = {0};
     // initialize the fixed memory allocator
     csi_init_payload(g_payload_buf, sizeof(g_payload_buf), 2);
     csi_init_iobuf(g_io_buf, sizeof(g_io_buf), 2);
-    return csi_run_server(serve_call_back);
+    int err = csi_register_method(1, serve_sum, &stats);
+    if (err) {
+        return err;
+    }
+    err = csi_register_method(2, serve_calls, &stats);
+    if (err) {
+        return err;
+    }
+    return csi_run_server(csi_dispatch);
 }
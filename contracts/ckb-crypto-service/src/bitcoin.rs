@@ -0,0 +1,263 @@
+//! Bitcoin signed messages: the legacy format, with the BIP-137 headers of
+//! segwit addresses, and the simple signatures of BIP-322.
+
+use alloc::vec::Vec;
+use ckb_crypto_interface::{BitcoinAddressType, CryptoError};
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256};
+
+const SIGHASH_DEFAULT: u8 = 0x00;
+const SIGHASH_ALL: u8 = 0x01;
+
+fn sha256d(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(Sha256::digest(data)).into()
+}
+
+fn hash160(data: &[u8]) -> [u8; 20] {
+    Ripemd160::digest(Sha256::digest(data)).into()
+}
+
+fn tagged_hash(tag: &[u8], data: &[u8]) -> [u8; 32] {
+    let tag = Sha256::digest(tag);
+    let mut hasher = Sha256::new();
+    hasher.update(tag);
+    hasher.update(tag);
+    hasher.update(data);
+    hasher.finalize().into()
+}
+
+fn write_compact_size(buf: &mut Vec<u8>, n: usize) {
+    match n {
+        0..=0xfc => buf.push(n as u8),
+        0xfd..=0xffff => {
+            buf.push(0xfd);
+            buf.extend_from_slice(&(n as u16).to_le_bytes());
+        }
+        _ => {
+            buf.push(0xfe);
+            buf.extend_from_slice(&(n as u32).to_le_bytes());
+        }
+    }
+}
+
+fn write_bytes(buf: &mut Vec<u8>, bytes: &[u8]) {
+    write_compact_size(buf, bytes.len());
+    buf.extend_from_slice(bytes);
+}
+
+fn read_compact_size(buf: &mut &[u8]) -> Result<usize, CryptoError> {
+    let (&first, rest) = buf.split_first().ok_or(CryptoError::InvalidWitness)?;
+    let len = match first {
+        0xfd => 2,
+        0xfe => 4,
+        0xff => 8,
+        n => {
+            *buf = rest;
+            return Ok(n as usize);
+        }
+    };
+    if rest.len() < len {
+        return Err(CryptoError::InvalidWitness);
+    }
+    let mut bytes = [0u8; 8];
+    bytes[..len].copy_from_slice(&rest[..len]);
+    *buf = &rest[len..];
+    usize::try_from(u64::from_le_bytes(bytes)).map_err(|_| CryptoError::InvalidWitness)
+}
+
+/// Parses a serialized witness stack.
+fn parse_witness(mut buf: &[u8]) -> Result<Vec<&[u8]>, CryptoError> {
+    let count = read_compact_size(&mut buf)?;
+    let mut items = Vec::new();
+    for _ in 0..count {
+        let len = read_compact_size(&mut buf)?;
+        if buf.len() < len {
+            return Err(CryptoError::InvalidWitness);
+        }
+        let (item, rest) = buf.split_at(len);
+        items.push(item);
+        buf = rest;
+    }
+    if !buf.is_empty() {
+        return Err(CryptoError::InvalidWitness);
+    }
+    Ok(items)
+}
+
+fn check_pubkey_hash(expected: &[u8], actual: &[u8]) -> Result<(), CryptoError> {
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(CryptoError::AddressMismatch)
+    }
+}
+
+pub fn message_verify(
+    address_type: BitcoinAddressType,
+    message: &[u8],
+    signature: &[u8],
+    pubkey_hash: &[u8],
+) -> Result<(), CryptoError> {
+    if pubkey_hash.len() != 20 {
+        return Err(CryptoError::InvalidAddress);
+    }
+    if signature.len() != 65 {
+        return Err(CryptoError::InvalidSig);
+    }
+    // 27 to 30 for uncompressed keys, 31 to 34 for compressed ones, and
+    // 35 to 38 and 39 to 42 for P2SH-P2WPKH and P2WPKH
+    let header = signature[0];
+    if !(27..=42).contains(&header) {
+        return Err(CryptoError::InvalidRecoveryId);
+    }
+    let compressed = header >= 31;
+    if !compressed && address_type != BitcoinAddressType::P2pkh {
+        return Err(CryptoError::InvalidAddressType);
+    }
+
+    let mut data = Vec::new();
+    write_bytes(&mut data, b"Bitcoin Signed Message:\n");
+    write_bytes(&mut data, message);
+    let digest = sha256d(&data);
+
+    let vk = crate::recover_secp256k1(&digest, &signature[1..], (header - 27) & 3)?;
+    let public_key = vk.to_encoded_point(compressed);
+    let key_hash = hash160(public_key.as_bytes());
+    match address_type {
+        BitcoinAddressType::P2pkh | BitcoinAddressType::P2wpkh => {
+            check_pubkey_hash(pubkey_hash, &key_hash)
+        }
+        BitcoinAddressType::P2shP2wpkh => {
+            let redeem_script = [&[0x00, 0x14], &key_hash[..]].concat();
+            check_pubkey_hash(pubkey_hash, &hash160(&redeem_script))
+        }
+        BitcoinAddressType::P2tr => Err(CryptoError::InvalidAddressType),
+    }
+}
+
+pub fn bip322_verify(
+    address_type: BitcoinAddressType,
+    message: &[u8],
+    signature: &[u8],
+    pubkey_hash: &[u8],
+) -> Result<(), CryptoError> {
+    let script_pubkey = match (address_type, pubkey_hash.len()) {
+        (BitcoinAddressType::P2wpkh, 20) => [&[0x00, 0x14], pubkey_hash].concat(),
+        (BitcoinAddressType::P2tr, 32) => [&[0x51, 0x20], pubkey_hash].concat(),
+        (BitcoinAddressType::P2wpkh | BitcoinAddressType::P2tr, _) => {
+            return Err(CryptoError::InvalidAddress)
+        }
+        _ => return Err(CryptoError::InvalidAddressType),
+    };
+    let witness = parse_witness(signature)?;
+
+    // the virtual transaction that pays to the address, and is spent by the
+    // signed one
+    let message_hash = tagged_hash(b"BIP0322-signed-message", message);
+    let mut to_spend = Vec::new();
+    to_spend.extend_from_slice(&0u32.to_le_bytes());
+    to_spend.push(1);
+    to_spend.extend_from_slice(&[0u8; 32]);
+    to_spend.extend_from_slice(&u32::MAX.to_le_bytes());
+    write_bytes(&mut to_spend, &[&[0x00, 0x20], &message_hash[..]].concat());
+    to_spend.extend_from_slice(&0u32.to_le_bytes());
+    to_spend.push(1);
+    to_spend.extend_from_slice(&0u64.to_le_bytes());
+    write_bytes(&mut to_spend, &script_pubkey);
+    to_spend.extend_from_slice(&0u32.to_le_bytes());
+    let prevout = [&sha256d(&to_spend)[..], &0u32.to_le_bytes()].concat();
+
+    // the only output of the signed transaction: 0 to OP_RETURN
+    let mut output = Vec::new();
+    output.extend_from_slice(&0u64.to_le_bytes());
+    write_bytes(&mut output, &[0x6a]);
+
+    match address_type {
+        BitcoinAddressType::P2wpkh => verify_p2wpkh(&witness, &prevout, &output, pubkey_hash),
+        _ => verify_p2tr(&witness, &prevout, &output, &script_pubkey, pubkey_hash),
+    }
+}
+
+fn verify_p2wpkh(
+    witness: &[&[u8]],
+    prevout: &[u8],
+    output: &[u8],
+    pubkey_hash: &[u8],
+) -> Result<(), CryptoError> {
+    use k256::ecdsa::signature::hazmat::PrehashVerifier;
+    use k256::ecdsa::{Signature, VerifyingKey};
+
+    let [signature, public_key] = witness else {
+        return Err(CryptoError::InvalidWitness);
+    };
+    let (&sighash_type, signature) = signature.split_last().ok_or(CryptoError::InvalidSig)?;
+    if sighash_type != SIGHASH_ALL {
+        return Err(CryptoError::InvalidSighashType);
+    }
+    let signature = Signature::from_der(signature).map_err(|_| CryptoError::InvalidSig)?;
+    if public_key.len() != 33 {
+        return Err(CryptoError::InvalidPubkey);
+    }
+    check_pubkey_hash(pubkey_hash, &hash160(public_key))?;
+    let vk = VerifyingKey::from_sec1_bytes(public_key).map_err(|_| CryptoError::InvalidPubkey)?;
+
+    // BIP-143 signature hash, with a P2PKH script code
+    let mut preimage = Vec::new();
+    preimage.extend_from_slice(&0u32.to_le_bytes());
+    preimage.extend_from_slice(&sha256d(prevout));
+    preimage.extend_from_slice(&sha256d(&0u32.to_le_bytes()));
+    preimage.extend_from_slice(prevout);
+    write_bytes(
+        &mut preimage,
+        &[&[0x76, 0xa9, 0x14], pubkey_hash, &[0x88, 0xac]].concat(),
+    );
+    preimage.extend_from_slice(&0u64.to_le_bytes());
+    preimage.extend_from_slice(&0u32.to_le_bytes());
+    preimage.extend_from_slice(&sha256d(output));
+    preimage.extend_from_slice(&0u32.to_le_bytes());
+    preimage.extend_from_slice(&(sighash_type as u32).to_le_bytes());
+
+    vk.verify_prehash(&sha256d(&preimage), &signature)
+        .map_err(|_| CryptoError::VerifyFailed)
+}
+
+fn verify_p2tr(
+    witness: &[&[u8]],
+    prevout: &[u8],
+    output: &[u8],
+    script_pubkey: &[u8],
+    output_key: &[u8],
+) -> Result<(), CryptoError> {
+    use k256::schnorr::{Signature, VerifyingKey};
+
+    let [signature] = witness else {
+        return Err(CryptoError::InvalidWitness);
+    };
+    let (signature, sighash_type) = match signature.len() {
+        64 => (*signature, SIGHASH_DEFAULT),
+        65 if signature[64] == SIGHASH_ALL => (&signature[..64], SIGHASH_ALL),
+        65 => return Err(CryptoError::InvalidSighashType),
+        _ => return Err(CryptoError::InvalidSig),
+    };
+    let signature = Signature::try_from(signature).map_err(|_| CryptoError::InvalidSig)?;
+    let vk = VerifyingKey::from_bytes(output_key).map_err(|_| CryptoError::InvalidPubkey)?;
+
+    // BIP-341 signature hash of a key path spending
+    let mut spent_script = Vec::new();
+    write_bytes(&mut spent_script, script_pubkey);
+    let mut message = Vec::new();
+    message.push(0);
+    message.push(sighash_type);
+    message.extend_from_slice(&0u32.to_le_bytes());
+    message.extend_from_slice(&0u32.to_le_bytes());
+    message.extend_from_slice(&Sha256::digest(prevout));
+    message.extend_from_slice(&Sha256::digest(0u64.to_le_bytes()));
+    message.extend_from_slice(&Sha256::digest(&spent_script));
+    message.extend_from_slice(&Sha256::digest(0u32.to_le_bytes()));
+    message.extend_from_slice(&Sha256::digest(output));
+    message.push(0);
+    message.extend_from_slice(&0u32.to_le_bytes());
+
+    vk.verify_raw(&tagged_hash(b"TapSighash", &message), &signature)
+        .map_err(|_| CryptoError::VerifyFailed)
+}
@@ -0,0 +1,86 @@
+//! BLS signatures over BLS12-381 as used by Ethereum's beacon chain: public
+//! keys in G1, signatures in G2 and the proof of possession ciphersuite.
+
+use alloc::vec::Vec;
+use bls12_381::hash_to_curve::{ExpandMsgXmd, HashToCurve};
+use bls12_381::{
+    multi_miller_loop, G1Affine, G1Projective, G2Affine, G2Prepared, G2Projective, Gt,
+};
+use ckb_crypto_interface::CryptoError;
+
+const DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+
+fn parse_public_key(bytes: &[u8]) -> Result<G1Affine, CryptoError> {
+    let bytes = bytes.try_into().map_err(|_| CryptoError::InvalidPubkey)?;
+    let public_key = Option::<G1Affine>::from(G1Affine::from_compressed(bytes))
+        .ok_or(CryptoError::InvalidPubkey)?;
+    // checked to be in G1 by `from_compressed`, and must not be the identity
+    if bool::from(public_key.is_identity()) {
+        return Err(CryptoError::InvalidPubkey);
+    }
+    Ok(public_key)
+}
+
+fn parse_signature(bytes: &[u8]) -> Result<G2Affine, CryptoError> {
+    let bytes = bytes.try_into().map_err(|_| CryptoError::InvalidSig)?;
+    Option::from(G2Affine::from_compressed(bytes)).ok_or(CryptoError::InvalidSig)
+}
+
+fn hash_to_g2(message: &[u8]) -> G2Affine {
+    <G2Projective as HashToCurve<ExpandMsgXmd<sha2_09::Sha256>>>::hash_to_curve(message, DST).into()
+}
+
+/// Checks that the product of the pairings of `pairs` is the pairing of the
+/// generator of G1 and `signature`.
+fn verify_pairings(pairs: &[(G1Affine, G2Affine)], signature: G2Affine) -> Result<(), CryptoError> {
+    let prepared: Vec<(G1Affine, G2Prepared)> = pairs
+        .iter()
+        .map(|&(p, q)| (p, G2Prepared::from(q)))
+        .chain([(-G1Affine::generator(), G2Prepared::from(signature))])
+        .collect();
+    let terms: Vec<(&G1Affine, &G2Prepared)> = prepared.iter().map(|(p, q)| (p, q)).collect();
+    if multi_miller_loop(&terms).final_exponentiation() == Gt::identity() {
+        Ok(())
+    } else {
+        Err(CryptoError::VerifyFailed)
+    }
+}
+
+pub fn verify(public_key: &[u8], message: &[u8], signature: &[u8]) -> Result<(), CryptoError> {
+    let public_key = parse_public_key(public_key)?;
+    let signature = parse_signature(signature)?;
+    verify_pairings(&[(public_key, hash_to_g2(message))], signature)
+}
+
+pub fn aggregate_verify(
+    public_keys: &[Vec<u8>],
+    messages: &[Vec<u8>],
+    signature: &[u8],
+) -> Result<(), CryptoError> {
+    if public_keys.is_empty() || public_keys.len() != messages.len() {
+        return Err(CryptoError::InvalidAggregate);
+    }
+    let signature = parse_signature(signature)?;
+    let pairs = public_keys
+        .iter()
+        .zip(messages)
+        .map(|(public_key, message)| Ok((parse_public_key(public_key)?, hash_to_g2(message))))
+        .collect::<Result<Vec<_>, CryptoError>>()?;
+    verify_pairings(&pairs, signature)
+}
+
+pub fn fast_aggregate_verify(
+    public_keys: &[Vec<u8>],
+    message: &[u8],
+    signature: &[u8],
+) -> Result<(), CryptoError> {
+    if public_keys.is_empty() {
+        return Err(CryptoError::InvalidAggregate);
+    }
+    let signature = parse_signature(signature)?;
+    let mut aggregate = G1Projective::identity();
+    for public_key in public_keys {
+        aggregate += parse_public_key(public_key)?;
+    }
+    verify_pairings(&[(aggregate.into(), hash_to_g2(message))], signature)
+}
@@ -11,8 +11,13 @@ ckb_std::entry!(program_entry);
 #[cfg(not(test))]
 default_alloc!();
 
-use alloc::{boxed::Box, collections::BTreeMap, vec::Vec};
-use ckb_crypto_interface::{CkbCrypto, CryptoError, HasherCtx, HasherType};
+mod bitcoin;
+mod bls;
+
+use alloc::{boxed::Box, collections::BTreeMap, vec, vec::Vec};
+use ckb_crypto_interface::{
+    BitcoinAddressType, CkbCrypto, CryptoError, HasherCtx, HasherType, VerifyItem,
+};
 use ckb_script_ipc_common::spawn::run_server;
 use ckb_std::log::error;
 
@@ -23,6 +28,19 @@ trait Hasher {
 
 struct Blake2b {
     ctx: Option<blake2b_ref::Blake2b>,
+    out_len: usize,
+}
+impl Blake2b {
+    fn new(out_len: usize, personal: &[u8]) -> Self {
+        Self {
+            ctx: Some(
+                blake2b_ref::Blake2bBuilder::new(out_len)
+                    .personal(personal)
+                    .build(),
+            ),
+            out_len,
+        }
+    }
 }
 impl Hasher for Blake2b {
     fn update(&mut self, data: &[u8]) {
@@ -30,9 +48,35 @@ impl Hasher for Blake2b {
     }
     fn finalize(&mut self) -> Vec<u8> {
         let ctx = self.ctx.take().unwrap();
-        let mut buf = [0u8; 32];
+        let mut buf = vec![0u8; self.out_len];
         ctx.finalize(&mut buf);
-        buf.to_vec()
+        buf
+    }
+}
+
+/// Any hasher of the `digest` crates, e.g. SHA-512 or Keccak-256.
+struct DigestHasher<D> {
+    ctx: Option<D>,
+}
+impl<D: sha2::Digest> Hasher for DigestHasher<D> {
+    fn update(&mut self, data: &[u8]) {
+        self.ctx.as_mut().unwrap().update(data);
+    }
+    fn finalize(&mut self) -> Vec<u8> {
+        let ctx = self.ctx.take().unwrap();
+        ctx.finalize().to_vec()
+    }
+}
+
+struct Blake3Hasher {
+    ctx: blake3::Hasher,
+}
+impl Hasher for Blake3Hasher {
+    fn update(&mut self, data: &[u8]) {
+        self.ctx.update(data);
+    }
+    fn finalize(&mut self) -> Vec<u8> {
+        self.ctx.finalize().as_bytes().to_vec()
     }
 }
 
@@ -66,6 +110,9 @@ impl Hasher for Ripemd160Hasher {
     }
 }
 
+/// Maximum number of live hasher contexts of a session.
+const MAX_HASHERS: usize = 32;
+
 struct CryptoServer {
     hashers: BTreeMap<u64, Box<dyn Hasher>>,
     hasher_count: u64,
@@ -80,39 +127,206 @@ impl CryptoServer {
     }
 }
 
-impl CkbCrypto for CryptoServer {
-    fn hasher_new(&mut self, hash_type: HasherType) -> HasherCtx {
-        const CKB_HASH_PERSONALIZATION: &[u8] = b"ckb-default-hash";
-
-        let hash: Box<dyn Hasher> = match hash_type {
-            HasherType::CkbBlake2b => Box::new(Blake2b {
-                ctx: Some(
-                    blake2b_ref::Blake2bBuilder::new(32)
-                        .personal(CKB_HASH_PERSONALIZATION)
-                        .build(),
-                ),
-            }),
-            HasherType::Blake2b => Box::new(Blake2b {
-                ctx: Some(blake2b_ref::Blake2bBuilder::new(32).build()),
-            }),
-            HasherType::Sha256 => {
-                use sha2::{Digest, Sha256};
-                Box::new(Sha256Hasher {
-                    ctx: Some(Sha256::new()),
-                })
+const CKB_HASH_PERSONALIZATION: &[u8] = b"ckb-default-hash";
+
+fn new_hasher(hash_type: HasherType) -> Result<Box<dyn Hasher>, CryptoError> {
+    let hasher: Box<dyn Hasher> = match hash_type {
+        HasherType::CkbBlake2b => Box::new(Blake2b::new(32, CKB_HASH_PERSONALIZATION)),
+        HasherType::Blake2b => Box::new(Blake2b::new(32, &[])),
+        HasherType::Sha256 => {
+            use sha2::{Digest, Sha256};
+            Box::new(Sha256Hasher {
+                ctx: Some(Sha256::new()),
+            })
+        }
+        HasherType::Ripemd160 => {
+            use ripemd::{Digest, Ripemd160};
+            Box::new(Ripemd160Hasher {
+                ctx: Some(Ripemd160::new()),
+            })
+        }
+        HasherType::Keccak256 => {
+            use sha3::{Digest, Keccak256};
+            Box::new(DigestHasher {
+                ctx: Some(Keccak256::new()),
+            })
+        }
+        HasherType::Sha512 => {
+            use sha2::{Digest, Sha512};
+            Box::new(DigestHasher {
+                ctx: Some(Sha512::new()),
+            })
+        }
+        HasherType::Sha3_256 => {
+            use sha3::{Digest, Sha3_256};
+            Box::new(DigestHasher {
+                ctx: Some(Sha3_256::new()),
+            })
+        }
+        HasherType::Blake3 => Box::new(Blake3Hasher {
+            ctx: blake3::Hasher::new(),
+        }),
+        HasherType::Blake2bCustom { out_len, personal } => {
+            // blake2b-ref panics on parameters out of these bounds
+            if !(1..=64).contains(&out_len) || personal.len() > 16 {
+                return Err(CryptoError::InvalidHasherParams);
             }
-            HasherType::Ripemd160 => {
-                use ripemd::{Digest, Ripemd160};
-                Box::new(Ripemd160Hasher {
-                    ctx: Some(Ripemd160::new()),
-                })
+            Box::new(Blake2b::new(out_len as usize, &personal))
+        }
+    };
+    Ok(hasher)
+}
+
+fn recover_secp256k1(
+    prehash: &[u8],
+    signature: &[u8],
+    recovery_id: u8,
+) -> Result<k256::ecdsa::VerifyingKey, CryptoError> {
+    use k256::ecdsa::hazmat::bits2field;
+    // use k256::ecdsa::signature::Result;
+    use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+    use k256::elliptic_curve::bigint::CheckedAdd;
+    use k256::elliptic_curve::ops::{Invert, LinearCombination, Reduce};
+    use k256::elliptic_curve::point::DecompressPoint;
+    use k256::elliptic_curve::{
+        AffinePoint, Curve, FieldBytesEncoding, PrimeField, ProjectivePoint,
+    };
+    use k256::{Scalar, Secp256k1};
+
+    let signature = Signature::from_slice(signature).map_err(|_| CryptoError::InvalidSig)?;
+    let (r, s) = signature.split_scalars();
+    let z = <Scalar as Reduce<<Secp256k1 as k256::elliptic_curve::Curve>::Uint>>::reduce_bytes(
+        &bits2field::<Secp256k1>(prehash).map_err(|_| CryptoError::InvalidPrehash)?,
+    );
+
+    let recovery_id = RecoveryId::from_byte(recovery_id);
+    if recovery_id.is_none() {
+        return Err(CryptoError::InvalidRecoveryId);
+    }
+    let recovery_id = recovery_id.unwrap();
+
+    let mut r_bytes = r.to_repr();
+    if recovery_id.is_x_reduced() {
+        match Option::<<Secp256k1 as k256::elliptic_curve::Curve>::Uint>::from(
+            <<Secp256k1 as Curve>::Uint as FieldBytesEncoding<Secp256k1>>::decode_field_bytes(
+                &r_bytes,
+            )
+            .checked_add(&Secp256k1::ORDER),
+        ) {
+            Some(restored) => {
+                r_bytes = FieldBytesEncoding::<Secp256k1>::encode_field_bytes(&restored)
             }
+            // No reduction should happen here if r was reduced
+            None => return Err(CryptoError::InvalidRecoveryId),
         };
+    }
+    #[allow(non_snake_case)]
+    let R = AffinePoint::<Secp256k1>::decompress(&r_bytes, u8::from(recovery_id.is_y_odd()).into());
+
+    if R.is_none().into() {
+        return Err(CryptoError::RecoveryFailed);
+    }
+
+    #[allow(non_snake_case)]
+    let R = ProjectivePoint::<Secp256k1>::from(R.unwrap());
+    let r_inv = *r.invert();
+    let u1 = -(r_inv * z);
+    let u2 = r_inv * *s;
+    let pk = ProjectivePoint::<Secp256k1>::lincomb(
+        &ProjectivePoint::<Secp256k1>::GENERATOR,
+        &u1,
+        &R,
+        &u2,
+    );
+    VerifyingKey::from_affine(pk.into()).map_err(|_| CryptoError::RecoveryFailed)
+}
+
+/// Recovers the Ethereum address that signed `digest`, and checks it against
+/// `expected_address` if given.
+fn recover_eth_address(
+    digest: &[u8],
+    signature: &[u8],
+    expected_address: Option<Vec<u8>>,
+) -> Result<Vec<u8>, CryptoError> {
+    use sha3::{Digest, Keccak256};
+
+    if signature.len() != 65 {
+        return Err(CryptoError::InvalidSig);
+    }
+    let recovery_id = match signature[64] {
+        v @ (0 | 1) => v,
+        v @ (27 | 28) => v - 27,
+        _ => return Err(CryptoError::InvalidRecoveryId),
+    };
+    let vk = recover_secp256k1(digest, &signature[..64], recovery_id)?;
+    let public_key = vk.to_encoded_point(false);
+    let address = Keccak256::digest(&public_key.as_bytes()[1..])[12..].to_vec();
+
+    if let Some(expected_address) = expected_address {
+        if expected_address.len() != 20 {
+            return Err(CryptoError::InvalidAddress);
+        }
+        if expected_address != address {
+            return Err(CryptoError::AddressMismatch);
+        }
+    }
+    Ok(address)
+}
+
+fn parse_ed25519(
+    public_key: &[u8],
+    signature: &[u8],
+) -> Result<(ed25519_dalek::VerifyingKey, ed25519_dalek::Signature), CryptoError> {
+    use ed25519_dalek::{Signature, VerifyingKey};
+
+    let signature = Signature::from_slice(signature).map_err(|_| CryptoError::InvalidSig)?;
+    let public_key = public_key
+        .try_into()
+        .map_err(|_| CryptoError::InvalidPubkey)?;
+    let public_key =
+        VerifyingKey::from_bytes(&public_key).map_err(|_| CryptoError::InvalidPubkey)?;
+    Ok((public_key, signature))
+}
+
+// Whether an Ed25519 signature can be verified in a batch. The batch equation
+// is multiplied by random coefficients, which can cancel the torsion component
+// of a key or R and accept a signature `Verifier::verify` rejects, and
+// decompresses R without rejecting non-canonical encodings like it does. So
+// keys and R points which are not torsion free, including small order ones,
+// are verified one by one.
+fn is_batchable_ed25519(
+    public_key: &ed25519_dalek::VerifyingKey,
+    signature: &ed25519_dalek::Signature,
+) -> bool {
+    use curve25519_dalek::edwards::CompressedEdwardsY;
 
+    let a = CompressedEdwardsY(public_key.to_bytes()).decompress();
+    let r = CompressedEdwardsY(*signature.r_bytes());
+    match (a, r.decompress()) {
+        (Some(a_point), Some(r_point)) => {
+            !public_key.is_weak()
+                && a_point.is_torsion_free()
+                && !r_point.is_small_order()
+                && r_point.is_torsion_free()
+                && r_point.compress() == r
+        }
+        _ => false,
+    }
+}
+
+impl CkbCrypto for CryptoServer {
+    fn hasher_new(&mut self, hash_type: HasherType) -> Result<HasherCtx, CryptoError> {
+        if self.hashers.len() >= MAX_HASHERS {
+            return Err(CryptoError::TooManyContexts);
+        }
+        let hasher = new_hasher(hash_type)?;
+
+        // IDs are never reused, so a finalized or dropped context can't reach
+        // a newer hasher
         let id = self.hasher_count;
         self.hasher_count += 1;
-        self.hashers.insert(id, hash);
-        HasherCtx(id)
+        self.hashers.insert(id, hasher);
+        Ok(HasherCtx(id))
     }
     fn hasher_update(&mut self, ctx: HasherCtx, data: Vec<u8>) -> Result<(), CryptoError> {
         if let Some(hasher) = self.hashers.get_mut(&ctx.0) {
@@ -129,6 +343,18 @@ impl CkbCrypto for CryptoServer {
             Err(CryptoError::InvalidContext)
         }
     }
+    fn hasher_drop(&mut self, ctx: HasherCtx) -> Result<(), CryptoError> {
+        if self.hashers.remove(&ctx.0).is_some() {
+            Ok(())
+        } else {
+            Err(CryptoError::InvalidContext)
+        }
+    }
+    fn hash(&mut self, hash_type: HasherType, data: Vec<u8>) -> Result<Vec<u8>, CryptoError> {
+        let mut hasher = new_hasher(hash_type)?;
+        hasher.update(&data);
+        Ok(hasher.finalize())
+    }
 
     fn secp256k1_recovery(
         &mut self,
@@ -136,61 +362,193 @@ impl CkbCrypto for CryptoServer {
         signature: Vec<u8>,
         recovery_id: u8,
     ) -> Result<Vec<u8>, CryptoError> {
-        use k256::ecdsa::hazmat::bits2field;
-        // use k256::ecdsa::signature::Result;
-        use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
-        use k256::elliptic_curve::bigint::CheckedAdd;
-        use k256::elliptic_curve::ops::{Invert, LinearCombination, Reduce};
-        use k256::elliptic_curve::point::DecompressPoint;
-        use k256::elliptic_curve::{
-            AffinePoint, Curve, FieldBytesEncoding, PrimeField, ProjectivePoint,
-        };
-        use k256::{Scalar, Secp256k1};
+        let vk = recover_secp256k1(&prehash, &signature, recovery_id)?;
+        Ok(vk.to_sec1_bytes().to_vec())
+    }
 
-        let signature = Signature::from_slice(&signature).map_err(|_| CryptoError::InvalidSig)?;
-        let (r, s) = signature.split_scalars();
-        let z = <Scalar as Reduce<<Secp256k1 as k256::elliptic_curve::Curve>::Uint>>::reduce_bytes(
-            &bits2field::<Secp256k1>(&prehash).map_err(|_| CryptoError::InvalidPrehash)?,
-        );
-
-        let recovery_id = RecoveryId::from_byte(recovery_id);
-        if recovery_id.is_none() {
-            return Err(CryptoError::InvalidRecoveryId);
-        }
-        let recovery_id = recovery_id.unwrap();
-
-        let mut r_bytes = r.to_repr();
-        if recovery_id.is_x_reduced() {
-            match Option::<<Secp256k1 as k256::elliptic_curve::Curve>::Uint>::from(
-                <Secp256k1 as k256::elliptic_curve::Curve>::Uint::decode_field_bytes(&r_bytes)
-                    .checked_add(&Secp256k1::ORDER),
-            ) {
-                Some(restored) => r_bytes = restored.encode_field_bytes(),
-                // No reduction should happen here if r was reduced
-                None => return Err(CryptoError::InvalidRecoveryId),
+    fn batch_verify(&mut self, items: Vec<VerifyItem>) -> Vec<Result<(), CryptoError>> {
+        use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+        let mut results = Vec::with_capacity(items.len());
+        // parsed Ed25519 items, with their indexes, to verify in one batch
+        let mut ed25519_items = Vec::new();
+        for (index, item) in items.into_iter().enumerate() {
+            let result = match item {
+                VerifyItem::Secp256k1 {
+                    public_key,
+                    prehash,
+                    signature,
+                } => self.secp256k1_verify(public_key, prehash, signature),
+                VerifyItem::Schnorr {
+                    public_key,
+                    prehash,
+                    signature,
+                } => self.schnorr_verify(public_key, prehash, signature),
+                VerifyItem::Secp256r1 {
+                    public_key,
+                    prehash,
+                    signature,
+                } => self.secp256r1_verify(public_key, prehash, signature),
+                VerifyItem::Ed25519 {
+                    public_key,
+                    prehash,
+                    signature,
+                } => parse_ed25519(&public_key, &signature).and_then(|(public_key, signature)| {
+                    if is_batchable_ed25519(&public_key, &signature) {
+                        ed25519_items.push((index, public_key, prehash, signature));
+                        Ok(())
+                    } else {
+                        public_key
+                            .verify(&prehash, &signature)
+                            .map_err(|_| CryptoError::VerifyFailed)
+                    }
+                }),
             };
+            results.push(result);
         }
-        #[allow(non_snake_case)]
-        let R =
-            AffinePoint::<Secp256k1>::decompress(&r_bytes, u8::from(recovery_id.is_y_odd()).into());
 
-        if R.is_none().into() {
-            return Err(CryptoError::RecoveryFailed);
+        let messages: Vec<&[u8]> = ed25519_items.iter().map(|i| i.2.as_slice()).collect();
+        let signatures: Vec<Signature> = ed25519_items.iter().map(|i| i.3).collect();
+        let public_keys: Vec<VerifyingKey> = ed25519_items.iter().map(|i| i.1).collect();
+        if ed25519_dalek::verify_batch(&messages, &signatures, &public_keys).is_err() {
+            for (index, public_key, prehash, signature) in &ed25519_items {
+                results[*index] = public_key
+                    .verify(prehash, signature)
+                    .map_err(|_| CryptoError::VerifyFailed);
+            }
         }
+        results
+    }
 
-        #[allow(non_snake_case)]
-        let R = ProjectivePoint::<Secp256k1>::from(R.unwrap());
-        let r_inv = *r.invert();
-        let u1 = -(r_inv * z);
-        let u2 = r_inv * *s;
-        let pk = ProjectivePoint::<Secp256k1>::lincomb(
-            &ProjectivePoint::<Secp256k1>::GENERATOR,
-            &u1,
-            &R,
-            &u2,
-        );
-        let vk = VerifyingKey::from_affine(pk.into()).map_err(|_| CryptoError::RecoveryFailed)?;
-        Ok(vk.to_sec1_bytes().to_vec())
+    fn multisig_verify(
+        &mut self,
+        multisig_script: Vec<u8>,
+        message: Vec<u8>,
+        signatures: Vec<u8>,
+    ) -> Result<Vec<u8>, CryptoError> {
+        const SIGNATURE_SIZE: usize = 65;
+
+        let (header, pubkey_hashes) = multisig_script
+            .split_at_checked(4)
+            .ok_or(CryptoError::InvalidMultisigScript)?;
+        let (require_first_n, threshold, pubkeys) = (header[1], header[2], header[3]);
+        if header[0] != 0
+            || pubkeys == 0
+            || threshold == 0
+            || threshold > pubkeys
+            || require_first_n > threshold
+            || pubkey_hashes.len() != pubkeys as usize * 20
+        {
+            return Err(CryptoError::InvalidMultisigScript);
+        }
+        if message.len() != 32 {
+            return Err(CryptoError::InvalidPrehash);
+        }
+        if signatures.len() != threshold as usize * SIGNATURE_SIZE {
+            return Err(CryptoError::InvalidSig);
+        }
+
+        let mut used = vec![false; pubkeys as usize];
+        let mut signers = Vec::with_capacity(threshold as usize);
+        for signature in signatures.chunks(SIGNATURE_SIZE) {
+            let vk = recover_secp256k1(&message, &signature[..64], signature[64])?;
+            let mut blake2b = Blake2b::new(32, CKB_HASH_PERSONALIZATION);
+            blake2b.update(vk.to_encoded_point(true).as_bytes());
+            let hash = blake2b.finalize();
+
+            let index = pubkey_hashes
+                .chunks(20)
+                .enumerate()
+                .position(|(i, pubkey_hash)| !used[i] && pubkey_hash == &hash[..20])
+                .ok_or(CryptoError::UnknownSigner)?;
+            used[index] = true;
+            signers.push(index as u8);
+        }
+        if used[..require_first_n as usize].contains(&false) {
+            return Err(CryptoError::RequiredSignerMissing);
+        }
+        Ok(signers)
+    }
+
+    fn bls_verify(
+        &mut self,
+        public_key: Vec<u8>,
+        message: Vec<u8>,
+        signature: Vec<u8>,
+    ) -> Result<(), CryptoError> {
+        bls::verify(&public_key, &message, &signature)
+    }
+
+    fn bls_aggregate_verify(
+        &mut self,
+        public_keys: Vec<Vec<u8>>,
+        messages: Vec<Vec<u8>>,
+        signature: Vec<u8>,
+    ) -> Result<(), CryptoError> {
+        bls::aggregate_verify(&public_keys, &messages, &signature)
+    }
+
+    fn bls_fast_aggregate_verify(
+        &mut self,
+        public_keys: Vec<Vec<u8>>,
+        message: Vec<u8>,
+        signature: Vec<u8>,
+    ) -> Result<(), CryptoError> {
+        bls::fast_aggregate_verify(&public_keys, &message, &signature)
+    }
+
+    fn eth_personal_recover(
+        &mut self,
+        message: Vec<u8>,
+        signature: Vec<u8>,
+        expected_address: Option<Vec<u8>>,
+    ) -> Result<Vec<u8>, CryptoError> {
+        use sha3::{Digest, Keccak256};
+
+        let mut hasher = Keccak256::new();
+        hasher.update(b"\x19Ethereum Signed Message:\n");
+        hasher.update(alloc::format!("{}", message.len()));
+        hasher.update(&message);
+        recover_eth_address(&hasher.finalize(), &signature, expected_address)
+    }
+
+    fn eth_typed_data_recover(
+        &mut self,
+        domain_separator: Vec<u8>,
+        struct_hash: Vec<u8>,
+        signature: Vec<u8>,
+        expected_address: Option<Vec<u8>>,
+    ) -> Result<Vec<u8>, CryptoError> {
+        use sha3::{Digest, Keccak256};
+
+        if domain_separator.len() != 32 || struct_hash.len() != 32 {
+            return Err(CryptoError::InvalidPrehash);
+        }
+        let mut hasher = Keccak256::new();
+        hasher.update(b"\x19\x01");
+        hasher.update(&domain_separator);
+        hasher.update(&struct_hash);
+        recover_eth_address(&hasher.finalize(), &signature, expected_address)
+    }
+
+    fn bitcoin_message_verify(
+        &mut self,
+        address_type: BitcoinAddressType,
+        message: Vec<u8>,
+        signature: Vec<u8>,
+        pubkey_hash: Vec<u8>,
+    ) -> Result<(), CryptoError> {
+        bitcoin::message_verify(address_type, &message, &signature, &pubkey_hash)
+    }
+
+    fn bip322_verify(
+        &mut self,
+        address_type: BitcoinAddressType,
+        message: Vec<u8>,
+        signature: Vec<u8>,
+        pubkey_hash: Vec<u8>,
+    ) -> Result<(), CryptoError> {
+        bitcoin::bip322_verify(address_type, &message, &signature, &pubkey_hash)
     }
 
     fn secp256k1_verify(
@@ -238,19 +596,77 @@ impl CkbCrypto for CryptoServer {
         prehash: Vec<u8>,
         signature: Vec<u8>,
     ) -> Result<(), CryptoError> {
-        use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+        use ed25519_dalek::Verifier;
+
+        let (public_key, signature) = parse_ed25519(&public_key, &signature)?;
+        public_key
+            .verify(&prehash, &signature)
+            .map_err(|_| CryptoError::VerifyFailed)
+    }
+
+    fn secp256r1_verify(
+        &mut self,
+        public_key: Vec<u8>,
+        prehash: Vec<u8>,
+        signature: Vec<u8>,
+    ) -> Result<(), CryptoError> {
+        use p256::ecdsa::signature::hazmat::PrehashVerifier;
+        use p256::ecdsa::{Signature, VerifyingKey};
 
         let signature = Signature::from_slice(&signature).map_err(|_| CryptoError::InvalidSig)?;
-        let public_key = public_key
-            .try_into()
-            .map_err(|_| CryptoError::InvalidPubkey)?;
+        let vk =
+            VerifyingKey::from_sec1_bytes(&public_key).map_err(|_| CryptoError::InvalidPubkey)?;
+        vk.verify_prehash(&prehash, &signature)
+            .map_err(|_| CryptoError::VerifyFailed)
+    }
 
-        VerifyingKey::from_bytes(&public_key)
-            .map_err(|_| CryptoError::InvalidPubkey)?
-            .verify(&prehash, &signature)
-            .map_err(|_| CryptoError::VerifyFailed)?;
+    fn webauthn_verify(
+        &mut self,
+        public_key: Vec<u8>,
+        authenticator_data: Vec<u8>,
+        client_data_json: Vec<u8>,
+        challenge: Vec<u8>,
+        signature: Vec<u8>,
+    ) -> Result<(), CryptoError> {
+        use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+        use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+        use sha2::{Digest, Sha256};
 
-        Ok(())
+        #[derive(serde::Deserialize)]
+        struct ClientData {
+            #[serde(rename = "type")]
+            ty: alloc::string::String,
+            challenge: alloc::string::String,
+        }
+
+        // rpIdHash (32 bytes), flags (1 byte) and signCount (4 bytes)
+        const USER_PRESENT: u8 = 0x01;
+        if authenticator_data.len() < 37 {
+            return Err(CryptoError::InvalidAuthenticatorData);
+        }
+        if authenticator_data[32] & USER_PRESENT == 0 {
+            return Err(CryptoError::UserNotPresent);
+        }
+
+        let client_data: ClientData = serde_json::from_slice(&client_data_json)
+            .map_err(|_| CryptoError::InvalidClientData)?;
+        if client_data.ty != "webauthn.get" {
+            return Err(CryptoError::InvalidClientData);
+        }
+        if client_data.challenge != URL_SAFE_NO_PAD.encode(&challenge) {
+            return Err(CryptoError::ChallengeMismatch);
+        }
+
+        let signature = Signature::from_der(&signature).map_err(|_| CryptoError::InvalidSig)?;
+        let vk =
+            VerifyingKey::from_sec1_bytes(&public_key).map_err(|_| CryptoError::InvalidPubkey)?;
+        let message = [
+            authenticator_data.as_slice(),
+            &Sha256::digest(&client_data_json),
+        ]
+        .concat();
+        vk.verify(&message, &signature)
+            .map_err(|_| CryptoError::VerifyFailed)
     }
 }
 
@@ -14,7 +14,9 @@ use unit_tests_crypto_def::Cmd;
 
 use alloc::ffi::CString;
 use alloc::vec::Vec;
-use ckb_crypto_interface::{CkbCryptoClient, HasherType};
+use ckb_crypto_interface::{
+    BitcoinAddressType, CkbCryptoClient, CryptoError, HasherType, VerifyItem,
+};
 use ckb_script_ipc_common::pipe::Pipe;
 use ckb_std::log::{error, info};
 
@@ -23,10 +25,21 @@ use ckb_script_ipc_common::spawn::spawn_cell_server;
 struct CryptoInfo {
     cmd: Cmd,
     crypto_cli: CkbCryptoClient<Pipe, Pipe>,
+    code_hash: Vec<u8>,
     args: Vec<u8>,
     witness: Vec<u8>,
 }
 
+fn spawn_crypto_server(code_hash: &[u8]) -> CkbCryptoClient<Pipe, Pipe> {
+    let (read_pipe, write_pipe) = spawn_cell_server(
+        code_hash,
+        ckb_std::ckb_types::core::ScriptHashType::Data2,
+        &[CString::new("").unwrap().as_ref()],
+    )
+    .unwrap();
+    CkbCryptoClient::new(read_pipe, write_pipe)
+}
+
 impl CryptoInfo {
     fn new() -> Self {
         let args: Vec<u8> = ckb_std::high_level::load_script()
@@ -47,18 +60,14 @@ impl CryptoInfo {
                 .raw_data()
                 .to_vec();
 
-        let (read_pipe, write_pipe) = spawn_cell_server(
-            &args[0..32],
-            ckb_std::ckb_types::core::ScriptHashType::Data2,
-            &[CString::new("").unwrap().as_ref()],
-        )
-        .unwrap();
+        let code_hash = args[0..32].to_vec();
+        let crypto_cli = spawn_crypto_server(&code_hash);
         let args = args[32..].to_vec();
 
-        let crypto_cli = CkbCryptoClient::new(read_pipe, write_pipe);
         Self {
             cmd,
             crypto_cli,
+            code_hash,
             args,
             witness,
         }
@@ -68,7 +77,9 @@ impl CryptoInfo {
 fn unit_test_ckb_blake2b(crypto_info: CryptoInfo) -> i8 {
     let mut crypto_cli = crypto_info.crypto_cli;
 
-    let ctx = crypto_cli.hasher_new(HasherType::CkbBlake2b);
+    let ctx = crypto_cli
+        .hasher_new(HasherType::CkbBlake2b)
+        .expect("new ckb blake2b");
     crypto_cli
         .hasher_update(ctx.clone(), crypto_info.witness.clone())
         .expect("update ckb blake2b");
@@ -94,7 +105,9 @@ fn unit_test_ckb_blake2b(crypto_info: CryptoInfo) -> i8 {
 fn unit_test_blake2b(crypto_info: CryptoInfo) -> i8 {
     let mut crypto_cli = crypto_info.crypto_cli;
 
-    let ctx = crypto_cli.hasher_new(HasherType::Blake2b);
+    let ctx = crypto_cli
+        .hasher_new(HasherType::Blake2b)
+        .expect("new def blake2b");
     crypto_cli
         .hasher_update(ctx.clone(), crypto_info.witness.clone())
         .expect("update def blake2b");
@@ -122,7 +135,9 @@ fn unit_test_blake2b(crypto_info: CryptoInfo) -> i8 {
 fn unit_test_sha256(crypto_info: CryptoInfo) -> i8 {
     let mut crypto_cli = crypto_info.crypto_cli;
 
-    let ctx = crypto_cli.hasher_new(HasherType::Sha256);
+    let ctx = crypto_cli
+        .hasher_new(HasherType::Sha256)
+        .expect("new sha256");
     crypto_cli
         .hasher_update(ctx.clone(), crypto_info.witness.clone())
         .expect("update sha256");
@@ -147,7 +162,9 @@ fn unit_test_sha256(crypto_info: CryptoInfo) -> i8 {
 fn unit_test_ripemd160(crypto_info: CryptoInfo) -> i8 {
     let mut crypto_cli = crypto_info.crypto_cli;
 
-    let ctx = crypto_cli.hasher_new(HasherType::Ripemd160);
+    let ctx = crypto_cli
+        .hasher_new(HasherType::Ripemd160)
+        .expect("new ripemd160");
     crypto_cli
         .hasher_update(ctx.clone(), crypto_info.witness.clone())
         .expect("update ripemd160");
@@ -170,6 +187,155 @@ fn unit_test_ripemd160(crypto_info: CryptoInfo) -> i8 {
     }
 }
 
+fn check_hasher(crypto_info: CryptoInfo, hash_type: HasherType, name: &str) -> i8 {
+    let mut crypto_cli = crypto_info.crypto_cli;
+
+    let ctx = crypto_cli.hasher_new(hash_type).expect("hasher new");
+    crypto_cli
+        .hasher_update(ctx.clone(), crypto_info.witness.clone())
+        .expect("hasher update");
+    let hash = crypto_cli.hasher_finalize(ctx).expect("hasher finalize");
+    if hash.as_slice() != crypto_info.args.as_slice() {
+        error!(
+            "check {} error: \n0: {:02x?} \n1: {:02x?}",
+            name, hash, crypto_info.args
+        );
+        1
+    } else {
+        info!("check {} success", name);
+        0
+    }
+}
+
+fn unit_test_blake2b_custom(mut crypto_info: CryptoInfo) -> i8 {
+    // args: out_len, personal length, personal and the expected hash
+    let args = crypto_info.args.clone();
+    let out_len = args[0];
+    let personal_len = args[1] as usize;
+    let personal = args[2..2 + personal_len].to_vec();
+    crypto_info.args = args[2 + personal_len..].to_vec();
+
+    for (out_len, personal) in [(0, Vec::new()), (65, Vec::new()), (32, [0u8; 17].to_vec())] {
+        match crypto_info
+            .crypto_cli
+            .hasher_new(HasherType::Blake2bCustom { out_len, personal })
+        {
+            Err(CryptoError::InvalidHasherParams) => {}
+            _ => {
+                error!("invalid blake2b params of out_len {} accepted", out_len);
+                return 1;
+            }
+        }
+    }
+
+    check_hasher(
+        crypto_info,
+        HasherType::Blake2bCustom { out_len, personal },
+        "custom blake2b",
+    )
+}
+
+fn unit_test_hash(crypto_info: CryptoInfo) -> i8 {
+    let mut crypto_cli = crypto_info.crypto_cli;
+
+    let hash = crypto_cli
+        .hash(HasherType::CkbBlake2b, crypto_info.witness.clone())
+        .expect("hash");
+    if hash.as_slice() != crypto_info.args.as_slice() {
+        error!(
+            "check hash error: \n0: {:02x?} \n1: {:02x?}",
+            hash, crypto_info.args
+        );
+        return 1;
+    }
+    match crypto_cli.hash(
+        HasherType::Blake2bCustom {
+            out_len: 0,
+            personal: Vec::new(),
+        },
+        crypto_info.witness,
+    ) {
+        Err(CryptoError::InvalidHasherParams) => 0,
+        _ => {
+            error!("hash accepted invalid blake2b params");
+            1
+        }
+    }
+}
+
+fn unit_test_hasher_contexts(crypto_info: CryptoInfo) -> i8 {
+    let mut crypto_cli = crypto_info.crypto_cli;
+
+    let mut ctxs = Vec::new();
+    loop {
+        match crypto_cli.hasher_new(HasherType::Sha256) {
+            Ok(ctx) => ctxs.push(ctx),
+            Err(CryptoError::TooManyContexts) => break,
+            Err(e) => {
+                error!("hasher_new error: {:?}", e);
+                return 1;
+            }
+        }
+        if ctxs.len() > 1024 {
+            error!("hasher contexts are not bounded");
+            return 1;
+        }
+    }
+    info!("{} live hasher contexts at most", ctxs.len());
+
+    // dropping or finalizing a context frees its slot and invalidates it
+    let dropped = ctxs.pop().unwrap();
+    crypto_cli
+        .hasher_drop(dropped.clone())
+        .expect("hasher drop");
+    let finalized = ctxs.pop().unwrap();
+    crypto_cli
+        .hasher_finalize(finalized.clone())
+        .expect("hasher finalize");
+    for ctx in [dropped, finalized] {
+        if !matches!(
+            crypto_cli.hasher_update(ctx.clone(), Vec::new()),
+            Err(CryptoError::InvalidContext)
+        ) || !matches!(
+            crypto_cli.hasher_drop(ctx),
+            Err(CryptoError::InvalidContext)
+        ) {
+            error!("stale hasher context accepted");
+            return 1;
+        }
+    }
+    for _ in 0..2 {
+        let ctx = crypto_cli
+            .hasher_new(HasherType::Sha256)
+            .expect("hasher new");
+        if ctxs.iter().any(|c| c.0 == ctx.0) {
+            error!("hasher context {} reused", ctx.0);
+            return 1;
+        }
+        ctxs.push(ctx);
+    }
+    if !matches!(
+        crypto_cli.hasher_new(HasherType::Sha256),
+        Err(CryptoError::TooManyContexts)
+    ) {
+        error!("hasher contexts are not bounded after drop");
+        return 1;
+    }
+
+    // contexts belong to the server process of the channel they were created on
+    let mut other_cli = spawn_crypto_server(&crypto_info.code_hash);
+    for ctx in ctxs {
+        if !matches!(
+            other_cli.hasher_update(ctx, Vec::new()),
+            Err(CryptoError::InvalidContext)
+        ) {
+            error!("hasher context accepted by another server");
+            return 1;
+        }
+    }
+    0
+}
+
 fn unit_test_secp256k1_recovery(crypto_info: CryptoInfo) -> i8 {
     let mut crypto_cli = crypto_info.crypto_cli;
 
@@ -288,6 +454,243 @@ fn unit_test_ed25519_verify(crypto_info: CryptoInfo) -> i8 {
     }
 }
 
+/// Takes a field prefixed with its one byte length.
+fn take_field(buf: &mut &[u8]) -> Vec<u8> {
+    let len = buf[0] as usize;
+    let field = buf[1..len + 1].to_vec();
+    *buf = &buf[len + 1..];
+    field
+}
+
+/// Compares a result with the expected one, given as its debug format, e.g.
+/// `Ok(())` or `Err(VerifyFailed)`.
+fn check_result<T: core::fmt::Debug>(result: Result<T, CryptoError>, expected: &[u8]) -> i8 {
+    let result = alloc::format!("{:?}", result);
+    if result.as_bytes() == expected {
+        0
+    } else {
+        error!(
+            "unexpected result: {}, expected: {}",
+            result,
+            alloc::string::String::from_utf8_lossy(expected)
+        );
+        1
+    }
+}
+
+fn unit_test_secp256r1_verify(crypto_info: CryptoInfo) -> i8 {
+    let mut crypto_cli = crypto_info.crypto_cli;
+
+    let mut witness = crypto_info.witness.as_slice();
+    let prehash = take_field(&mut witness);
+    let signature = take_field(&mut witness);
+
+    match crypto_cli.secp256r1_verify(crypto_info.args, prehash, signature) {
+        Ok(_) => 0,
+        Err(e) => {
+            error!("secp256r1_verify error: {:?} ", e);
+            1
+        }
+    }
+}
+
+fn unit_test_webauthn_verify(crypto_info: CryptoInfo) -> i8 {
+    let mut crypto_cli = crypto_info.crypto_cli;
+
+    // args: the expected result and the public key
+    let mut args = crypto_info.args.as_slice();
+    let expected = take_field(&mut args);
+    let public_key = args.to_vec();
+
+    let mut witness = crypto_info.witness.as_slice();
+    let authenticator_data = take_field(&mut witness);
+    let client_data_json = take_field(&mut witness);
+    let challenge = take_field(&mut witness);
+    let signature = take_field(&mut witness);
+
+    let result = crypto_cli.webauthn_verify(
+        public_key,
+        authenticator_data,
+        client_data_json,
+        challenge,
+        signature,
+    );
+    check_result(result, &expected)
+}
+
+/// Splits args into the expected result and the expected address, if any.
+fn expected_with_address(args: &[u8]) -> (Vec<u8>, Option<Vec<u8>>) {
+    let mut args = args;
+    let expected = take_field(&mut args);
+    let address = if args.is_empty() {
+        None
+    } else {
+        Some(args.to_vec())
+    };
+    (expected, address)
+}
+
+fn unit_test_eth_personal_recover(crypto_info: CryptoInfo) -> i8 {
+    let mut crypto_cli = crypto_info.crypto_cli;
+    let (expected, address) = expected_with_address(&crypto_info.args);
+
+    let mut witness = crypto_info.witness.as_slice();
+    let message = take_field(&mut witness);
+    let signature = take_field(&mut witness);
+
+    check_result(
+        crypto_cli.eth_personal_recover(message, signature, address),
+        &expected,
+    )
+}
+
+fn unit_test_eth_typed_data_recover(crypto_info: CryptoInfo) -> i8 {
+    let mut crypto_cli = crypto_info.crypto_cli;
+    let (expected, address) = expected_with_address(&crypto_info.args);
+
+    let mut witness = crypto_info.witness.as_slice();
+    let domain_separator = take_field(&mut witness);
+    let struct_hash = take_field(&mut witness);
+    let signature = take_field(&mut witness);
+
+    check_result(
+        crypto_cli.eth_typed_data_recover(domain_separator, struct_hash, signature, address),
+        &expected,
+    )
+}
+
+fn unit_test_bitcoin_message(crypto_info: CryptoInfo, bip322: bool) -> i8 {
+    let mut crypto_cli = crypto_info.crypto_cli;
+
+    // args: the expected result, the address type and the pubkey hash
+    let mut args = crypto_info.args.as_slice();
+    let expected = take_field(&mut args);
+    let address_type = match args[0] {
+        0 => BitcoinAddressType::P2pkh,
+        1 => BitcoinAddressType::P2shP2wpkh,
+        2 => BitcoinAddressType::P2wpkh,
+        _ => BitcoinAddressType::P2tr,
+    };
+    let pubkey_hash = args[1..].to_vec();
+
+    let mut witness = crypto_info.witness.as_slice();
+    let message = take_field(&mut witness);
+    let signature = take_field(&mut witness);
+
+    let result = if bip322 {
+        crypto_cli.bip322_verify(address_type, message, signature, pubkey_hash)
+    } else {
+        crypto_cli.bitcoin_message_verify(address_type, message, signature, pubkey_hash)
+    };
+    check_result(result, &expected)
+}
+
+fn unit_test_batch_verify(crypto_info: CryptoInfo) -> i8 {
+    let mut crypto_cli = crypto_info.crypto_cli;
+
+    // witness: items of a scheme byte, the public key, prehash and signature
+    let mut witness = crypto_info.witness.as_slice();
+    let mut items = Vec::new();
+    while !witness.is_empty() {
+        let scheme = witness[0];
+        witness = &witness[1..];
+        let public_key = take_field(&mut witness);
+        let prehash = take_field(&mut witness);
+        let signature = take_field(&mut witness);
+        items.push(match scheme {
+            0 => VerifyItem::Secp256k1 {
+                public_key,
+                prehash,
+                signature,
+            },
+            1 => VerifyItem::Schnorr {
+                public_key,
+                prehash,
+                signature,
+            },
+            2 => VerifyItem::Ed25519 {
+                public_key,
+                prehash,
+                signature,
+            },
+            _ => VerifyItem::Secp256r1 {
+                public_key,
+                prehash,
+                signature,
+            },
+        });
+    }
+
+    let results = crypto_cli.batch_verify(items);
+    check_result(Ok::<_, CryptoError>(results), &crypto_info.args)
+}
+
+fn unit_test_multisig_verify(crypto_info: CryptoInfo) -> i8 {
+    let mut crypto_cli = crypto_info.crypto_cli;
+
+    let mut witness = crypto_info.witness.as_slice();
+    let multisig_script = take_field(&mut witness);
+    let message = take_field(&mut witness);
+    let signatures = take_field(&mut witness);
+
+    check_result(
+        crypto_cli.multisig_verify(multisig_script, message, signatures),
+        &crypto_info.args,
+    )
+}
+
+fn unit_test_bls_verify(crypto_info: CryptoInfo) -> i8 {
+    let mut crypto_cli = crypto_info.crypto_cli;
+
+    let mut witness = crypto_info.witness.as_slice();
+    let public_key = take_field(&mut witness);
+    let message = take_field(&mut witness);
+    let signature = take_field(&mut witness);
+
+    check_result(
+        crypto_cli.bls_verify(public_key, message, signature),
+        &crypto_info.args,
+    )
+}
+
+fn unit_test_bls_aggregate_verify(crypto_info: CryptoInfo) -> i8 {
+    let mut crypto_cli = crypto_info.crypto_cli;
+
+    // witness: the number of keys, each key and its message, and the signature
+    let mut witness = crypto_info.witness.as_slice();
+    let count = witness[0];
+    witness = &witness[1..];
+    let mut public_keys = Vec::new();
+    let mut messages = Vec::new();
+    for _ in 0..count {
+        public_keys.push(take_field(&mut witness));
+        messages.push(take_field(&mut witness));
+    }
+    let signature = take_field(&mut witness);
+
+    check_result(
+        crypto_cli.bls_aggregate_verify(public_keys, messages, signature),
+        &crypto_info.args,
+    )
+}
+
+fn unit_test_bls_fast_aggregate_verify(crypto_info: CryptoInfo) -> i8 {
+    let mut crypto_cli = crypto_info.crypto_cli;
+
+    // witness: the number of keys, the keys, the message and the signature
+    let mut witness = crypto_info.witness.as_slice();
+    let count = witness[0];
+    witness = &witness[1..];
+    let public_keys = (0..count).map(|_| take_field(&mut witness)).collect();
+    let message = take_field(&mut witness);
+    let signature = take_field(&mut witness);
+
+    check_result(
+        crypto_cli.bls_fast_aggregate_verify(public_keys, message, signature),
+        &crypto_info.args,
+    )
+}
+
 pub fn program_entry() -> i8 {
     drop(ckb_std::logger::init());
 
@@ -302,5 +705,23 @@ pub fn program_entry() -> i8 {
         Cmd::Secp256k1Verify => unit_test_secp256k1_verify(info), // todo
         Cmd::SchnorrVerify => unit_test_schnorr(info),            // todo
         Cmd::Ed25519Verfiy => unit_test_ed25519_verify(info),
+        Cmd::Keccak256 => check_hasher(info, HasherType::Keccak256, "keccak256"),
+        Cmd::Sha512 => check_hasher(info, HasherType::Sha512, "sha512"),
+        Cmd::Sha3_256 => check_hasher(info, HasherType::Sha3_256, "sha3-256"),
+        Cmd::Blake3 => check_hasher(info, HasherType::Blake3, "blake3"),
+        Cmd::Blake2bCustom => unit_test_blake2b_custom(info),
+        Cmd::Hash => unit_test_hash(info),
+        Cmd::HasherContexts => unit_test_hasher_contexts(info),
+        Cmd::Secp256r1Verify => unit_test_secp256r1_verify(info),
+        Cmd::WebauthnVerify => unit_test_webauthn_verify(info),
+        Cmd::EthPersonalRecover => unit_test_eth_personal_recover(info),
+        Cmd::EthTypedDataRecover => unit_test_eth_typed_data_recover(info),
+        Cmd::BitcoinMessageVerify => unit_test_bitcoin_message(info, false),
+        Cmd::Bip322Verify => unit_test_bitcoin_message(info, true),
+        Cmd::BatchVerify => unit_test_batch_verify(info),
+        Cmd::MultisigVerify => unit_test_multisig_verify(info),
+        Cmd::BlsVerify => unit_test_bls_verify(info),
+        Cmd::BlsAggregateVerify => unit_test_bls_aggregate_verify(info),
+        Cmd::BlsFastAggregateVerify => unit_test_bls_fast_aggregate_verify(info),
     }
 }
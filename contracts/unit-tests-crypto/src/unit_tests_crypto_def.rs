@@ -7,6 +7,24 @@ pub enum Cmd {
     Secp256k1Verify,
     SchnorrVerify,
     Ed25519Verfiy,
+    Keccak256,
+    Sha512,
+    Sha3_256,
+    Blake3,
+    Blake2bCustom,
+    Hash,
+    HasherContexts,
+    Secp256r1Verify,
+    WebauthnVerify,
+    EthPersonalRecover,
+    EthTypedDataRecover,
+    BitcoinMessageVerify,
+    Bip322Verify,
+    BatchVerify,
+    MultisigVerify,
+    BlsVerify,
+    BlsAggregateVerify,
+    BlsFastAggregateVerify,
 }
 
 impl From<u8> for Cmd {
@@ -20,6 +38,24 @@ impl From<u8> for Cmd {
             5 => Self::Secp256k1Verify,
             6 => Self::SchnorrVerify,
             7 => Self::Ed25519Verfiy,
+            8 => Self::Keccak256,
+            9 => Self::Sha512,
+            10 => Self::Sha3_256,
+            11 => Self::Blake3,
+            12 => Self::Blake2bCustom,
+            13 => Self::Hash,
+            14 => Self::HasherContexts,
+            15 => Self::Secp256r1Verify,
+            16 => Self::WebauthnVerify,
+            17 => Self::EthPersonalRecover,
+            18 => Self::EthTypedDataRecover,
+            19 => Self::BitcoinMessageVerify,
+            20 => Self::Bip322Verify,
+            21 => Self::BatchVerify,
+            22 => Self::MultisigVerify,
+            23 => Self::BlsVerify,
+            24 => Self::BlsAggregateVerify,
+            25 => Self::BlsFastAggregateVerify,
             _ => {
                 panic!("unknow Val");
             }
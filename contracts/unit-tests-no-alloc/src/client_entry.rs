@@ -0,0 +1,44 @@
+use crate::def::{MathRequest, MathResponse};
+use crate::error::Error;
+use ckb_script_ipc_common::{fixed::FixedChannel, pipe::Pipe};
+use ckb_std::{
+    ckb_constants::Source,
+    syscalls::{pipe, spawn, SpawnArgs},
+};
+
+pub fn client_entry() -> Result<(), Error> {
+    // `spawn_server` collects argv into a `Vec`, so the syscall is made with stack arrays
+    let (r1, w1) = pipe().map_err(|_| Error::CkbSysError)?;
+    let (r2, w2) = pipe().map_err(|_| Error::CkbSysError)?;
+    let inherited_fds = [r2, w1, 0];
+    let argv = [c"server".as_ptr() as *const i8];
+    let mut process_id = 0;
+    let mut spgs = SpawnArgs {
+        argc: argv.len() as u64,
+        argv: argv.as_ptr(),
+        process_id: &mut process_id,
+        inherited_fds: inherited_fds.as_ptr(),
+    };
+    spawn(0, Source::CellDep, 0, 0, &mut spgs).map_err(|_| Error::CkbSysError)?;
+
+    let mut buf = [0u8; 64];
+    let mut channel = FixedChannel::new(Pipe::from(r1), Pipe::from(w2), &mut buf);
+    let resp: MathResponse = channel
+        .call("add", MathRequest::Add { a: 1, b: 2 })
+        .map_err(|_| Error::ClientError)?;
+    if !matches!(resp, MathResponse::Add(3)) {
+        return Err(Error::UnexpectedResponse);
+    }
+    let resp: MathResponse = channel
+        .call(
+            "sum",
+            MathRequest::Sum {
+                values: [1, 2, 3, u32::MAX],
+            },
+        )
+        .map_err(|_| Error::ClientError)?;
+    if !matches!(resp, MathResponse::Sum(v) if v == u32::MAX as u64 + 6) {
+        return Err(Error::UnexpectedResponse);
+    }
+    Ok(())
+}
@@ -0,0 +1,5 @@
+#[ckb_script_ipc::service]
+pub trait Math {
+    fn add(a: u64, b: u64) -> u64;
+    fn sum(values: [u32; 4]) -> u64;
+}
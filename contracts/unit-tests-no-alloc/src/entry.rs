@@ -0,0 +1,14 @@
+use ckb_std::env::argv;
+
+use crate::client_entry;
+use crate::error::Error;
+use crate::server_entry;
+
+pub fn entry() -> Result<(), Error> {
+    if argv().is_empty() {
+        client_entry::client_entry()?;
+    } else {
+        server_entry::server_entry()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,8 @@
+#[repr(i8)]
+pub enum Error {
+    Unknown = 1,
+    CkbSysError,
+    ServerError,
+    ClientError,
+    UnexpectedResponse,
+}
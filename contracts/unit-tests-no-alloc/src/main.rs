@@ -0,0 +1,23 @@
+#![no_std]
+#![cfg_attr(not(test), no_main)]
+
+pub mod client_entry;
+pub mod def;
+pub mod entry;
+pub mod error;
+pub mod server_entry;
+
+#[cfg(not(test))]
+use ckb_script_ipc_common::fixed::NoAlloc;
+#[cfg(not(test))]
+ckb_std::entry!(program_entry);
+#[cfg(not(test))]
+#[global_allocator]
+static ALLOC: NoAlloc = NoAlloc;
+
+pub fn program_entry() -> i8 {
+    match entry::entry() {
+        Ok(_) => 0,
+        Err(e) => e as i8,
+    }
+}
@@ -0,0 +1,29 @@
+use crate::def::Math;
+use crate::error::Error;
+use ckb_script_ipc_common::{fixed::FixedChannel, pipe::Pipe};
+use ckb_std::syscalls::inherited_fds;
+
+struct MathServer;
+
+impl Math for MathServer {
+    fn add(&mut self, a: u64, b: u64) -> u64 {
+        a + b
+    }
+
+    fn sum(&mut self, values: [u32; 4]) -> u64 {
+        values.iter().map(|&v| v as u64).sum()
+    }
+}
+
+pub fn server_entry() -> Result<(), Error> {
+    // `run_server` collects the inherited fds into a `Vec`
+    let mut fds = [0u64; 2];
+    if inherited_fds(&mut fds) != 2 {
+        return Err(Error::CkbSysError);
+    }
+    let mut buf = [0u8; 64];
+    let channel = FixedChannel::new(Pipe::from(fds[0]), Pipe::from(fds[1]), &mut buf);
+    channel
+        .execute(&mut MathServer.server())
+        .map_err(|_| Error::ServerError)
+}
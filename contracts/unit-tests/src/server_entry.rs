@@ -82,6 +82,14 @@ impl UnitTests for UnitTestsServer {
     fn test_large_input_output(&mut self, input: Vec<u8>) -> Vec<u8> {
         input.into_iter().map(|x| x + 1).collect()
     }
+    // `debug!` prints nothing without debug assertions
+    #[cfg_attr(not(debug_assertions), allow(unused_variables))]
+    fn test_debug_print(&mut self, line: String) {
+        ckb_std::debug!("{}", line);
+    }
+    fn test_echo_integers(&mut self, signed: i128, unsigned: u128) -> (i128, u128) {
+        (signed, unsigned)
+    }
 }
 
 pub fn server_entry() -> Result<(), Error> {
@@ -13,6 +13,21 @@ pub enum CryptoError {
     InvalidPubkey,
     RecoveryFailed,
     VerifyFailed,
+    InvalidHasherParams,
+    TooManyContexts,
+    InvalidAuthenticatorData,
+    InvalidClientData,
+    ChallengeMismatch,
+    UserNotPresent,
+    InvalidAddress,
+    AddressMismatch,
+    InvalidAddressType,
+    InvalidWitness,
+    InvalidSighashType,
+    InvalidMultisigScript,
+    UnknownSigner,
+    RequiredSignerMissing,
+    InvalidAggregate,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -21,14 +36,65 @@ pub enum HasherType {
     Blake2b,
     Sha256,
     Ripemd160,
+    Keccak256,
+    Sha512,
+    Sha3_256,
+    Blake3,
+    /// Blake2b with an output length of 1 to 64 bytes and a personalization of
+    /// at most 16 bytes.
+    Blake2bCustom {
+        out_len: u8,
+        personal: Vec<u8>,
+    },
 }
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct HasherCtx(pub u64);
 
+/// A signature to verify with `batch_verify`, with the arguments of the
+/// method of its scheme, e.g. `ed25519_verify` for `Ed25519`.
+#[derive(Serialize, Deserialize)]
+pub enum VerifyItem {
+    Secp256k1 {
+        public_key: Vec<u8>,
+        prehash: Vec<u8>,
+        signature: Vec<u8>,
+    },
+    Schnorr {
+        public_key: Vec<u8>,
+        prehash: Vec<u8>,
+        signature: Vec<u8>,
+    },
+    Ed25519 {
+        public_key: Vec<u8>,
+        prehash: Vec<u8>,
+        signature: Vec<u8>,
+    },
+    Secp256r1 {
+        public_key: Vec<u8>,
+        prehash: Vec<u8>,
+        signature: Vec<u8>,
+    },
+}
+
+/// Type of a Bitcoin address that signed a message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BitcoinAddressType {
+    P2pkh,
+    P2shP2wpkh,
+    P2wpkh,
+    P2tr,
+}
+
+/// The crypto service. The C method ids of `CKB_CRYPTO_C_HEADER` follow the
+/// order of the methods, so new ones must be appended at the end.
 #[ckb_script_ipc::service]
 pub trait CkbCrypto {
-    fn hasher_new(hash_type: HasherType) -> HasherCtx;
+    /// Creates a hashing context. Contexts only exist in the server process
+    /// of the channel that created them, which is spawned for a single
+    /// client: that process is the session, and context ids are a plain
+    /// counter, neither secret nor valid on other servers.
+    fn hasher_new(hash_type: HasherType) -> Result<HasherCtx, CryptoError>;
     fn hasher_update(ctx: HasherCtx, data: Vec<u8>) -> Result<(), CryptoError>;
     fn hasher_finalize(ctx: HasherCtx) -> Result<Vec<u8>, CryptoError>;
 
@@ -55,4 +121,111 @@ pub trait CkbCrypto {
         prehash: Vec<u8>,
         signature: Vec<u8>,
     ) -> Result<(), CryptoError>;
+
+    fn hasher_drop(ctx: HasherCtx) -> Result<(), CryptoError>;
+    fn hash(hash_type: HasherType, data: Vec<u8>) -> Result<Vec<u8>, CryptoError>;
+
+    fn secp256r1_verify(
+        public_key: Vec<u8>,
+        prehash: Vec<u8>,
+        signature: Vec<u8>,
+    ) -> Result<(), CryptoError>;
+
+    /// Verifies a WebAuthn assertion of a P-256 passkey: `signature` is the
+    /// DER encoded signature over `authenticator_data` and the SHA-256 hash of
+    /// `client_data_json`, which must be of type `webauthn.get` and carry
+    /// `challenge`. The user present flag must be set.
+    fn webauthn_verify(
+        public_key: Vec<u8>,
+        authenticator_data: Vec<u8>,
+        client_data_json: Vec<u8>,
+        challenge: Vec<u8>,
+        signature: Vec<u8>,
+    ) -> Result<(), CryptoError>;
+
+    /// Recovers the 20-byte Ethereum address that signed `message` with
+    /// `personal_sign` (EIP-191 version `0x45`). `signature` is `r`, `s` and
+    /// `v` in 65 bytes, with `v` being 0, 1, 27 or 28. If `expected_address`
+    /// is given, it must match the recovered one.
+    fn eth_personal_recover(
+        message: Vec<u8>,
+        signature: Vec<u8>,
+        expected_address: Option<Vec<u8>>,
+    ) -> Result<Vec<u8>, CryptoError>;
+
+    /// Like `eth_personal_recover`, for EIP-712 typed data given by its
+    /// 32-byte domain separator and struct hash.
+    fn eth_typed_data_recover(
+        domain_separator: Vec<u8>,
+        struct_hash: Vec<u8>,
+        signature: Vec<u8>,
+        expected_address: Option<Vec<u8>>,
+    ) -> Result<Vec<u8>, CryptoError>;
+
+    /// Verifies a message signed in the legacy "Bitcoin Signed Message"
+    /// format, with the BIP-137 headers of segwit addresses: `signature` is
+    /// the header byte, `r` and `s` in 65 bytes. `pubkey_hash` is the 20-byte
+    /// hash of the address of `address_type`, which can't be `P2tr`.
+    fn bitcoin_message_verify(
+        address_type: BitcoinAddressType,
+        message: Vec<u8>,
+        signature: Vec<u8>,
+        pubkey_hash: Vec<u8>,
+    ) -> Result<(), CryptoError>;
+
+    /// Verifies a BIP-322 simple signature of a `P2wpkh` or `P2tr` address:
+    /// `signature` is the serialized witness stack. `pubkey_hash` is the
+    /// 20-byte public key hash of `P2wpkh`, or the 32-byte output key of
+    /// `P2tr`.
+    fn bip322_verify(
+        address_type: BitcoinAddressType,
+        message: Vec<u8>,
+        signature: Vec<u8>,
+        pubkey_hash: Vec<u8>,
+    ) -> Result<(), CryptoError>;
+
+    /// Verifies many signatures in one call, and returns the result of each.
+    /// Ed25519 signatures are verified together with ed25519-dalek's batch
+    /// verification, and one by one if that fails. Signatures whose public
+    /// key or R has a torsion component, e.g. is of small order, or with a
+    /// non-canonical R, are always verified one by one, as the batch could
+    /// accept them where `ed25519_verify` doesn't.
+    fn batch_verify(items: Vec<VerifyItem>) -> Vec<Result<(), CryptoError>>;
+
+    /// Verifies signatures of CKB's secp256k1 multisig lock.
+    /// `multisig_script` is the reserved byte 0, `require_first_n`,
+    /// `threshold`, the number of keys and their 20-byte blake160 hashes.
+    /// `signatures` are `threshold` 65-byte recoverable signatures of the
+    /// 32-byte `message`, each by a different key, with the first
+    /// `require_first_n` keys among them. Returns the index of the key of
+    /// each signature.
+    fn multisig_verify(
+        multisig_script: Vec<u8>,
+        message: Vec<u8>,
+        signatures: Vec<u8>,
+    ) -> Result<Vec<u8>, CryptoError>;
+
+    /// Verifies a BLS12-381 signature of Ethereum's beacon chain, with the
+    /// proof of possession ciphersuite: `public_key` is a 48-byte compressed
+    /// G1 point, and `signature` a 96-byte compressed G2 point.
+    fn bls_verify(
+        public_key: Vec<u8>,
+        message: Vec<u8>,
+        signature: Vec<u8>,
+    ) -> Result<(), CryptoError>;
+
+    /// Verifies an aggregate BLS signature of each message by its key.
+    fn bls_aggregate_verify(
+        public_keys: Vec<Vec<u8>>,
+        messages: Vec<Vec<u8>>,
+        signature: Vec<u8>,
+    ) -> Result<(), CryptoError>;
+
+    /// Verifies an aggregate BLS signature of one message by all keys. The
+    /// possession of each key must have been proven.
+    fn bls_fast_aggregate_verify(
+        public_keys: Vec<Vec<u8>>,
+        message: Vec<u8>,
+        signature: Vec<u8>,
+    ) -> Result<(), CryptoError>;
 }
@@ -0,0 +1,71 @@
+use ckb_script_ipc_cli::parse_hex;
+use ckb_script_ipc_common::packet::{inspect, PacketKind};
+use clap::{Parser, ValueEnum};
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// Decodes a raw packet stream, e.g. bytes dumped from a pipe, into requests or responses.
+///
+/// The stream is read as hex from the arguments, from `--file` or from stdin.
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    /// Kind of the packets in the stream
+    #[arg(short, long, value_enum, default_value_t = Kind::Request)]
+    kind: Kind,
+    /// Read the stream from FILE
+    #[arg(short, long, value_name = "FILE")]
+    file: Option<PathBuf>,
+    /// Read the file or stdin as raw bytes instead of hex
+    #[arg(long)]
+    raw: bool,
+    /// The stream in hex
+    hex: Vec<String>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Kind {
+    Request,
+    Response,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let input = if !cli.hex.is_empty() {
+        Ok(cli.hex.join(" ").into_bytes())
+    } else if let Some(path) = &cli.file {
+        std::fs::read(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))
+    } else {
+        let mut input = Vec::new();
+        std::io::stdin()
+            .read_to_end(&mut input)
+            .map(|_| input)
+            .map_err(|e| format!("failed to read stdin: {}", e))
+    };
+    let bytes = input.and_then(|input| {
+        if cli.raw && cli.hex.is_empty() {
+            Ok(input)
+        } else {
+            parse_hex(&String::from_utf8_lossy(&input))
+        }
+    });
+    let bytes = match bytes {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::from(2);
+        }
+    };
+    let kind = match cli.kind {
+        Kind::Request => PacketKind::Request,
+        Kind::Response => PacketKind::Response,
+    };
+    let inspection = inspect(kind, &bytes);
+    print!("{}", inspection);
+    if inspection.error.is_some() {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
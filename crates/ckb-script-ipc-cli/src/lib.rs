@@ -0,0 +1,228 @@
+//! Call the methods of an on-chain IPC service from the command line.
+//!
+//! A [`Session`] runs a server ELF with [`spawn_server_with_options`] and sends raw JSON
+//! requests to it, the same way as `Channel::send_json_request` and
+//! `Channel::receive_json_response` do. The server stops after returning an error, so the
+//! session respawns it before the next request.
+use ckb_script_ipc_common::{
+    channel::Channel,
+    error::IpcError,
+    native::{spawn_server_with_options, NativeError, NativeSpawnOptions, Pipe, ServerHandle},
+};
+use serde::de::IgnoredAny;
+use std::fmt::{self, Display, Formatter};
+use std::time::{Duration, Instant};
+
+/// Error of a single request sent by a [`Session`].
+#[derive(Debug)]
+pub enum CallError {
+    /// The request is not valid JSON. Nothing is sent to the server.
+    InvalidJson(serde_json::Error),
+    /// The server could not be spawned.
+    Spawn(NativeError),
+    /// The call failed. If the server has stopped, the reason is attached.
+    Ipc {
+        error: IpcError,
+        server: Option<NativeError>,
+    },
+}
+
+impl Display for CallError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CallError::InvalidJson(e) => write!(f, "invalid JSON request: {}", e),
+            CallError::Spawn(e) => write!(f, "failed to spawn server: {}", e),
+            CallError::Ipc { error, server } => {
+                match error {
+                    IpcError::ProtocolError(code) => {
+                        write!(f, "{:?} (error code {})", code, code.clone() as u64)?
+                    }
+                    e => write!(f, "{:?}", e)?,
+                }
+                if let Some(e) = server {
+                    write!(f, ", server stopped: {}", e)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for CallError {}
+
+/// A running server and the channel connected to it.
+pub struct Session {
+    binary: Vec<u8>,
+    args: Vec<String>,
+    options: NativeSpawnOptions,
+    server: Option<(Channel<Pipe, Pipe>, ServerHandle)>,
+}
+
+impl Session {
+    /// Spawns the server `binary` with `args`.
+    pub fn new(
+        binary: Vec<u8>,
+        args: Vec<String>,
+        options: NativeSpawnOptions,
+    ) -> Result<Self, NativeError> {
+        let mut session = Self {
+            binary,
+            args,
+            options,
+            server: None,
+        };
+        session.restart()?;
+        Ok(session)
+    }
+
+    /// Stops the current server, if any, and spawns a new one.
+    pub fn restart(&mut self) -> Result<(), NativeError> {
+        // dropping the channel closes the pipes, which stops the old server
+        self.server = None;
+        let args: Vec<&str> = self.args.iter().map(|s| s.as_str()).collect();
+        let (read_pipe, write_pipe, handle) =
+            spawn_server_with_options(&self.binary, &args, &self.options)?;
+        self.server = Some((Channel::new(read_pipe, write_pipe), handle));
+        Ok(())
+    }
+
+    /// Sends a JSON request and returns the JSON response.
+    ///
+    /// The request is validated and compacted before being sent. Its text is otherwise
+    /// sent as is, so numbers out of the range of `f64`, like `u128`, are kept exactly.
+    /// After a failed call, the server is respawned on the next call.
+    pub fn call(&mut self, request: &str) -> Result<String, CallError> {
+        serde_json::from_str::<IgnoredAny>(request).map_err(CallError::InvalidJson)?;
+        if self.server.is_none() {
+            self.restart().map_err(CallError::Spawn)?;
+        }
+        let (channel, _) = self.server.as_mut().unwrap();
+        let result = channel
+            .send_json_request(&reformat(request, false))
+            .and_then(|_| channel.receive_json_response());
+        result.map_err(|error| {
+            let (channel, handle) = self.server.take().unwrap();
+            drop(channel);
+            // give the server a moment to exit, but don't wait for one that is stuck
+            let deadline = Instant::now() + Duration::from_millis(200);
+            while !handle.is_finished() && Instant::now() < deadline {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            let server = if handle.is_finished() {
+                handle.join().err()
+            } else {
+                None
+            };
+            CallError::Ipc { error, server }
+        })
+    }
+}
+
+/// Pretty-prints a JSON response. Responses which are not valid JSON are returned as is.
+pub fn format_response(response: &str, compact: bool) -> String {
+    match serde_json::from_str::<IgnoredAny>(response) {
+        Ok(_) => reformat(response, !compact),
+        Err(_) => response.into(),
+    }
+}
+
+// Reformats valid JSON text without parsing its values, which would round large integers
+// to `f64`. Whitespace outside of strings is removed, and with `pretty` the text is laid out
+// like `serde_json::to_string_pretty` does.
+fn reformat(json: &str, pretty: bool) -> String {
+    let mut compact = String::with_capacity(json.len());
+    let (mut in_string, mut escaped) = (false, false);
+    for c in json.chars() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if c.is_ascii_whitespace() {
+            continue;
+        }
+        compact.push(c);
+    }
+    if !pretty {
+        return compact;
+    }
+
+    let chars: Vec<char> = compact.chars().collect();
+    let mut out = String::with_capacity(compact.len() * 2);
+    let mut indent = 0;
+    let newline = |out: &mut String, indent: usize| {
+        out.push('\n');
+        out.extend(std::iter::repeat_n(' ', indent * 2));
+    };
+    let (mut in_string, mut escaped) = (false, false);
+    for (i, &c) in chars.iter().enumerate() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            out.push(c);
+            continue;
+        }
+        match c {
+            '"' => {
+                in_string = true;
+                out.push(c);
+            }
+            '{' | '[' => {
+                out.push(c);
+                if !matches!(chars.get(i + 1), Some('}' | ']')) {
+                    indent += 1;
+                    newline(&mut out, indent);
+                }
+            }
+            '}' | ']' => {
+                // empty objects and arrays stay on one line
+                if !matches!(chars[i - 1], '{' | '[') {
+                    indent -= 1;
+                    newline(&mut out, indent);
+                }
+                out.push(c);
+            }
+            ',' => {
+                out.push(c);
+                newline(&mut out, indent);
+            }
+            ':' => out.push_str(": "),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Parses a hex dump, e.g. `0x0000 16 7b22...` or `00:00:16`. Whitespace, `,` and `:` are
+/// ignored, and each group may start with `0x`.
+pub fn parse_hex(dump: &str) -> Result<Vec<u8>, String> {
+    let mut digits = String::new();
+    for group in dump.split(|c: char| c.is_whitespace() || c == ',' || c == ':') {
+        let group = group
+            .strip_prefix("0x")
+            .or_else(|| group.strip_prefix("0X"))
+            .unwrap_or(group);
+        digits.push_str(group);
+    }
+    if let Some(c) = digits.chars().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(format!("invalid hex digit: {:?}", c));
+    }
+    if digits.len() % 2 != 0 {
+        return Err(format!("odd number of hex digits: {}", digits.len()));
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&digits[i..i + 2], 16)
+                .map_err(|_| format!("invalid hex digits: {:?}", &digits[i..i + 2]))
+        })
+        .collect()
+}
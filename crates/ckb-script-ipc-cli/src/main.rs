@@ -0,0 +1,148 @@
+use ckb_script_ipc_cli::{format_response, Session};
+use ckb_script_ipc_common::native::{set_default_timeout, DebugSink, NativeSpawnOptions};
+use clap::Parser;
+use std::io::{BufRead, BufReader, IsTerminal, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Spawns an on-chain IPC server natively and calls its methods with JSON requests.
+///
+/// Requests are read one per line, e.g. `{"Hello":{"name":"world"}}`. Empty lines and
+/// lines starting with `#` are skipped. Without `--file`, requests are read from stdin,
+/// interactively when it is a terminal.
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    /// Path to the server ELF
+    binary: PathBuf,
+    /// Arguments passed to the server, after `--`
+    #[arg(last = true)]
+    args: Vec<String>,
+    /// Read requests from FILE instead of stdin
+    #[arg(short, long, value_name = "FILE")]
+    file: Option<PathBuf>,
+    /// Maximum cycles the server may consume
+    #[arg(long)]
+    max_cycles: Option<u64>,
+    /// Seconds to wait for a response, 0 to wait forever
+    #[arg(long, default_value_t = 60)]
+    timeout: u64,
+    /// Name used to tag the debug output of the server
+    #[arg(long, default_value = "server")]
+    name: String,
+    /// Print responses on a single line
+    #[arg(long)]
+    compact: bool,
+    /// Don't print the debug output of the server
+    #[arg(short, long)]
+    quiet: bool,
+}
+
+const REPL_HELP: &str = "\
+Enter a JSON request per line, or a command:
+  :restart  respawn the server
+  :help     show this help
+  :quit     exit";
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let binary = match std::fs::read(&cli.binary) {
+        Ok(binary) => binary,
+        Err(e) => {
+            eprintln!("error: failed to read {}: {}", cli.binary.display(), e);
+            return ExitCode::from(2);
+        }
+    };
+    if cli.timeout > 0 {
+        set_default_timeout(Some(Duration::from_secs(cli.timeout)));
+    }
+    let mut options = NativeSpawnOptions {
+        name: cli.name.clone(),
+        // keep stdout for responses
+        debug_sink: if cli.quiet {
+            DebugSink::Discard
+        } else {
+            DebugSink::Callback(Arc::new(|name, line| eprintln!("[{}] {}", name, line)))
+        },
+        ..Default::default()
+    };
+    if let Some(max_cycles) = cli.max_cycles {
+        options.max_cycles = max_cycles;
+    }
+    let mut session = match Session::new(binary, cli.args.clone(), options) {
+        Ok(session) => session,
+        Err(e) => {
+            eprintln!("error: failed to spawn server: {}", e);
+            return ExitCode::from(2);
+        }
+    };
+
+    let result = match &cli.file {
+        Some(path) => match std::fs::File::open(path) {
+            Ok(file) => run_batch(&mut session, BufReader::new(file), cli.compact),
+            Err(e) => {
+                eprintln!("error: failed to open {}: {}", path.display(), e);
+                return ExitCode::from(2);
+            }
+        },
+        None if std::io::stdin().is_terminal() => run_repl(&mut session, cli.compact),
+        None => run_batch(&mut session, std::io::stdin().lock(), cli.compact),
+    };
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// Sends every request in `reader`. Returns false if any request failed.
+fn run_batch(session: &mut Session, reader: impl BufRead, compact: bool) -> std::io::Result<bool> {
+    let mut success = true;
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let request = line.trim();
+        if request.is_empty() || request.starts_with('#') {
+            continue;
+        }
+        match session.call(request) {
+            Ok(response) => println!("{}", format_response(&response, compact)),
+            Err(e) => {
+                println!("error (line {}): {}", index + 1, e);
+                success = false;
+            }
+        }
+    }
+    Ok(success)
+}
+
+fn run_repl(session: &mut Session, compact: bool) -> std::io::Result<bool> {
+    eprintln!("{}", REPL_HELP);
+    let mut stdin = std::io::stdin().lock();
+    let mut line = String::new();
+    loop {
+        print!("> ");
+        std::io::stdout().flush()?;
+        line.clear();
+        if stdin.read_line(&mut line)? == 0 {
+            return Ok(true);
+        }
+        match line.trim() {
+            "" => {}
+            ":quit" | ":q" => return Ok(true),
+            ":help" => eprintln!("{}", REPL_HELP),
+            ":restart" => match session.restart() {
+                Ok(_) => eprintln!("server restarted"),
+                Err(e) => eprintln!("error: failed to spawn server: {}", e),
+            },
+            request => match session.call(request) {
+                Ok(response) => println!("{}", format_response(&response, compact)),
+                Err(e) => println!("error: {}", e),
+            },
+        }
+    }
+}
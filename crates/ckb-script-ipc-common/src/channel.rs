@@ -2,12 +2,14 @@ use crate::io::{BufReader, BufWriter, Read, Write};
 use crate::{
     error::{IpcError, ProtocolErrorCode},
     ipc::Serve,
-    packet::{Packet, RequestPacket, ResponsePacket},
+    packet::{read_packet_into, write_packet},
+    stats::CallStats,
 };
+use alloc::boxed::Box;
 use alloc::string::String;
-use alloc::vec;
+use alloc::vec::Vec;
 use serde::{Deserialize, Serialize};
-use serde_json::{from_slice, to_vec};
+use serde_json::from_slice;
 
 /// The `Channel` struct facilitates communication between a client and a server.
 /// It handles the transmission of requests from the client to the server and the reception
@@ -17,18 +19,114 @@ use serde_json::{from_slice, to_vec};
 ///
 /// * `reader` - Responsible for reading data from the channel.
 /// * `writer` - Responsible for writing data to the channel.
+/// * `cycle_stats` - Optional per-method cycle statistics, see [`Channel::enable_cycle_stats`].
+/// * `canonical_vlq` - Whether received packets must use canonical VLQs, see
+///   [`Channel::set_canonical_vlq`].
+/// * `payload` - Payload of the packet being received. Received payloads are read into it,
+///   reusing its allocation.
+/// * `send_buffer` - Payload of the packet being sent. With the `std` feature, requests and
+///   responses are serialized into it with `serde_json::to_writer`, reusing its allocation.
+///   Without `std`, `serde_json` keeps its `Serializer` and `to_writer` private, so `to_vec`
+///   allocates a new buffer which replaces this one.
+///
+/// # Buffer sizes
+///
+/// The reader and writer are buffered, with capacities chosen by [`Channel::with_capacity`].
+/// Packets larger than a buffer don't grow it: their payloads bypass the buffer and are read
+/// directly into `payload`, which grows to the largest payload received, or written
+/// directly to the pipe. Small buffers save memory in tiny scripts, while large ones save
+/// `read`/`write` syscalls, and thus cycles, for payloads just above the default capacity.
 pub struct Channel<R: Read, W: Write> {
     reader: BufReader<R>,
     writer: BufWriter<W>,
+    cycle_stats: Option<CycleStats>,
+    canonical_vlq: bool,
+    payload: Vec<u8>,
+    send_buffer: Vec<u8>,
+}
+
+struct CycleStats {
+    stats: CallStats,
+    source: Box<dyn Fn() -> u64 + Send>,
 }
 
 impl<R: Read, W: Write> Channel<R, W> {
     pub fn new(reader: R, writer: W) -> Self {
+        Self::from_buffered(BufReader::new(reader), BufWriter::new(writer))
+    }
+
+    /// Creates a channel whose reader and writer buffers have the given capacities in bytes,
+    /// instead of the default 1 KiB used by [`Channel::new`]. See the "Buffer sizes" section
+    /// of [`Channel`] for the trade-off.
+    pub fn with_capacity(
+        reader: R,
+        writer: W,
+        read_capacity: usize,
+        write_capacity: usize,
+    ) -> Self {
+        Self::from_buffered(
+            BufReader::with_capacity(read_capacity, reader),
+            BufWriter::with_capacity(write_capacity, writer),
+        )
+    }
+
+    fn from_buffered(reader: BufReader<R>, writer: BufWriter<W>) -> Self {
         Self {
-            reader: BufReader::new(reader),
-            writer: BufWriter::new(writer),
+            reader,
+            writer,
+            cycle_stats: None,
+            canonical_vlq: false,
+            payload: Vec::new(),
+            send_buffer: Vec::new(),
         }
     }
+
+    /// Returns the capacities of the reader and writer buffers.
+    pub fn capacity(&self) -> (usize, usize) {
+        (self.reader.capacity(), self.writer.capacity())
+    }
+
+    /// Rejects received packets whose VLQs are not canonical, with
+    /// `IpcError::NonCanonicalVlq`. Packets are always sent with canonical VLQs, so when
+    /// both sides enable this, every packet has exactly one byte representation, e.g. for
+    /// hashing transcripts. Disabled by default.
+    pub fn set_canonical_vlq(&mut self, canonical: bool) {
+        self.canonical_vlq = canonical;
+    }
+
+    /// Enables per-method cycle statistics in [`Channel::call`].
+    ///
+    /// `current_cycles()` is sampled before the request is sent and after the response
+    /// is received, and the difference is recorded under the method name. Since
+    /// `current_cycles()` covers all processes in the script group, this includes the
+    /// cycles spent by the server. It only works on-chain, off-chain clients should use
+    /// [`Channel::enable_cycle_stats_with`] instead.
+    pub fn enable_cycle_stats(&mut self) {
+        self.enable_cycle_stats_with(ckb_std::syscalls::current_cycles);
+    }
+
+    /// Enables per-method cycle statistics in [`Channel::call`], sampling cycles from
+    /// `source`. For example, a client of a native server can pass the cycle counter
+    /// of the server.
+    pub fn enable_cycle_stats_with<F>(&mut self, source: F)
+    where
+        F: Fn() -> u64 + Send + 'static,
+    {
+        self.cycle_stats = Some(CycleStats {
+            stats: CallStats::new(),
+            source: Box::new(source),
+        });
+    }
+
+    /// Returns the cycle statistics recorded so far, or `None` if they are not enabled.
+    pub fn cycle_stats(&self) -> Option<&CallStats> {
+        self.cycle_stats.as_ref().map(|c| &c.stats)
+    }
+
+    /// Disables cycle statistics and returns the recorded table.
+    pub fn take_cycle_stats(&mut self) -> Option<CallStats> {
+        self.cycle_stats.take().map(|c| c.stats)
+    }
 }
 
 impl<R: Read, W: Write> Channel<R, W> {
@@ -88,7 +186,7 @@ impl<R: Read, W: Write> Channel<R, W> {
     ///
     /// # Arguments
     ///
-    /// * `_method_name` - A static string slice representing the name of the method being called.
+    /// * `method_name` - A static string slice representing the name of the method being called.
     /// * `req` - The request message to be sent to the server. It must implement `Serialize` and `Deserialize`.
     ///
     /// # Type Parameters
@@ -120,33 +218,36 @@ impl<R: Read, W: Write> Channel<R, W> {
     /// let request = MyRequest { /* fields */ };
     /// let response: MyResponse = channel.call("my_method", request).expect("Failed to call method");
     /// ```
-    pub fn call<Req, Resp>(
-        &mut self,
-        _method_name: &'static str,
-        req: Req,
-    ) -> Result<Resp, IpcError>
+    pub fn call<Req, Resp>(&mut self, method_name: &'static str, req: Req) -> Result<Resp, IpcError>
     where
         Req: Serialize + for<'de> Deserialize<'de>,
         Resp: Serialize + for<'de> Deserialize<'de>,
     {
+        let start_cycles = self.cycle_stats.as_ref().map(|c| (c.source)());
         let result = self.send_request(req).and_then(|_| self.receive_response());
+        if let (Some(cycle_stats), Some(start_cycles)) = (self.cycle_stats.as_mut(), start_cycles) {
+            let cycles = (cycle_stats.source)().saturating_sub(start_cycles);
+            cycle_stats.stats.record(method_name, cycles);
+        }
         match result {
             Ok(resp) => Ok(resp),
             Err(e) => {
                 #[cfg(feature = "enable-logging")]
-                log::error!("Error in call({}): {:?}", _method_name, e);
+                log::error!("Error in call({}): {:?}", method_name, e);
                 Err(e)
             }
         }
     }
     pub(crate) fn send_request<Req: Serialize>(&mut self, req: Req) -> Result<(), IpcError> {
-        let serialized_req = to_vec(&req).map_err(|_| IpcError::SerializeError)?;
-        let packet = RequestPacket::new(serialized_req);
+        self.serialize_payload(&req)?;
         #[cfg(feature = "enable-logging")]
-        log::info!("send request: {:?}", packet);
+        log::info!(
+            "send request: {} bytes payload: {}",
+            self.send_buffer.len(),
+            String::from_utf8_lossy(&self.send_buffer)
+        );
 
-        let bytes = packet.serialize();
-        self.writer.write(&bytes)?;
+        write_packet(&mut self.writer, 0, 0, &self.send_buffer)?;
         self.writer.flush()?;
         Ok(())
     }
@@ -166,23 +267,23 @@ impl<R: Read, W: Write> Channel<R, W> {
     /// A `Result` indicating whether the request was successfully sent, or an `IpcError` if
     /// writing to the channel fails.
     pub fn send_json_request(&mut self, json: &str) -> Result<(), IpcError> {
-        let packet = RequestPacket::new(json.as_bytes().to_vec());
         #[cfg(feature = "enable-logging")]
-        log::info!("send request: {:?}", packet);
+        log::info!("send request: {} bytes payload: {}", json.len(), json);
 
-        let bytes = packet.serialize();
-        self.writer.write(&bytes)?;
+        write_packet(&mut self.writer, 0, 0, json.as_bytes())?;
         self.writer.flush()?;
         Ok(())
     }
     pub(crate) fn send_response<Resp: Serialize>(&mut self, resp: Resp) -> Result<(), IpcError> {
-        let serialized_resp = to_vec(&resp).map_err(|_| IpcError::SerializeError)?;
-        let packet = ResponsePacket::new(0, serialized_resp);
+        self.serialize_payload(&resp)?;
         #[cfg(feature = "enable-logging")]
-        log::info!("send response: {:?}", packet);
+        log::info!(
+            "send response: {} bytes payload: {}",
+            self.send_buffer.len(),
+            String::from_utf8_lossy(&self.send_buffer)
+        );
 
-        let bytes = packet.serialize();
-        self.writer.write(&bytes)?;
+        write_packet(&mut self.writer, 0, 0, &self.send_buffer)?;
         self.writer.flush()?;
         Ok(())
     }
@@ -190,41 +291,69 @@ impl<R: Read, W: Write> Channel<R, W> {
         &mut self,
         error_code: ProtocolErrorCode,
     ) -> Result<(), IpcError> {
-        let packet = ResponsePacket::new(error_code.clone() as u64, vec![]);
         #[cfg(feature = "enable-logging")]
-        log::info!("send error code: {:?}", error_code as u64);
-        let bytes = packet.serialize();
-        self.writer.write(&bytes)?;
+        log::info!("send error code: {:?}", error_code.clone() as u64);
+        write_packet(&mut self.writer, 0, error_code as u64, &[])?;
         self.writer.flush()?;
         Ok(())
     }
+    // Serializes `value` into `self.send_buffer`, see the `send_buffer` field of `Channel`.
+    fn serialize_payload<T: Serialize>(&mut self, value: &T) -> Result<(), IpcError> {
+        self.send_buffer.clear();
+        #[cfg(feature = "std")]
+        serde_json::to_writer(&mut self.send_buffer, value)
+            .map_err(|_| IpcError::SerializeError)?;
+        #[cfg(not(feature = "std"))]
+        {
+            self.send_buffer = serde_json::to_vec(value).map_err(|_| IpcError::SerializeError)?;
+        }
+        Ok(())
+    }
+    // Reads a packet into `self.payload`, returning its method id or error code.
+    fn read_packet(&mut self) -> Result<u64, IpcError> {
+        let (_version, id) =
+            read_packet_into(&mut self.reader, self.canonical_vlq, &mut self.payload)?;
+        Ok(id)
+    }
     pub(crate) fn receive_request<Req: for<'de> Deserialize<'de>>(
         &mut self,
     ) -> Result<Req, IpcError> {
-        let packet = RequestPacket::read_from(&mut self.reader)?;
+        self.read_packet()?;
         #[cfg(feature = "enable-logging")]
-        log::info!("receive request: {:?}", packet);
-        let req = from_slice(packet.payload()).map_err(|_| IpcError::DeserializeError)?;
+        log::info!(
+            "receive request: {} bytes payload: {}",
+            self.payload.len(),
+            String::from_utf8_lossy(&self.payload)
+        );
+        let req = from_slice(&self.payload).map_err(|_| IpcError::DeserializeError)?;
         Ok(req)
     }
     pub(crate) fn receive_response<Resp: for<'de> Deserialize<'de>>(
         &mut self,
     ) -> Result<Resp, IpcError> {
-        let packet = ResponsePacket::read_from(&mut self.reader)?;
+        self.receive_response_payload()?;
+        from_slice(&self.payload).map_err(|_| IpcError::DeserializeError)
+    }
+    // Reads a response into `self.payload`, failing if it has an error code.
+    fn receive_response_payload(&mut self) -> Result<(), IpcError> {
+        let error_code = self.read_packet()?;
 
         #[cfg(feature = "enable-logging")]
-        log::info!("Received response: {:?}", packet);
+        log::info!(
+            "Received response: error_code: {}, {} bytes payload: {}",
+            error_code,
+            self.payload.len(),
+            String::from_utf8_lossy(&self.payload)
+        );
 
-        let error_code = ProtocolErrorCode::from(packet.error_code());
-        match error_code {
-            ProtocolErrorCode::Ok => {}
+        match ProtocolErrorCode::from(error_code) {
+            ProtocolErrorCode::Ok => Ok(()),
             e => {
                 #[cfg(feature = "enable-logging")]
                 log::error!("Received error code: {:?}", e);
-                return Err(IpcError::ProtocolError(e));
+                Err(IpcError::ProtocolError(e))
             }
         }
-        from_slice(packet.payload()).map_err(|_| IpcError::DeserializeError)
     }
 
     /// Receives a JSON string response from the server.
@@ -242,20 +371,52 @@ impl<R: Read, W: Write> Channel<R, W> {
     ///   - The response payload contains invalid UTF-8
     ///
     pub fn receive_json_response(&mut self) -> Result<String, IpcError> {
-        let packet = ResponsePacket::read_from(&mut self.reader)?;
+        self.receive_response_payload()?;
+        Ok(String::from_utf8_lossy(&self.payload).into_owned())
+    }
+}
 
-        #[cfg(feature = "enable-logging")]
-        log::info!("Received response: {:?}", packet);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
 
-        let error_code = ProtocolErrorCode::from(packet.error_code());
-        match error_code {
-            ProtocolErrorCode::Ok => {}
-            e => {
-                #[cfg(feature = "enable-logging")]
-                log::error!("Received error code: {:?}", e);
-                return Err(IpcError::ProtocolError(e));
-            }
-        }
-        Ok(String::from_utf8_lossy(packet.payload()).into_owned())
+    #[test]
+    fn test_payload_capacity_survives_call() {
+        let large = serde_json::to_vec(&vec![7u8; 4096]).unwrap();
+        let mut responses = Vec::new();
+        write_packet(&mut responses, 0, 0, &large).unwrap();
+        write_packet(&mut responses, 0, 0, b"[1]").unwrap();
+
+        let mut channel = Channel::new(&responses[..], Vec::new());
+        let resp: Vec<u8> = channel.call("echo", vec![7u8; 4096]).unwrap();
+        assert_eq!(resp.len(), 4096);
+        let capacity = channel.payload.capacity();
+        assert!(capacity >= large.len());
+
+        // sending a small request must not replace the receive buffer
+        let resp: Vec<u8> = channel.call("echo", vec![1u8]).unwrap();
+        assert_eq!(resp, [1]);
+        assert_eq!(channel.payload.capacity(), capacity);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_send_buffer_capacity_survives_call() {
+        let mut responses = Vec::new();
+        write_packet(&mut responses, 0, 0, b"[]").unwrap();
+        write_packet(&mut responses, 0, 0, b"[]").unwrap();
+
+        let mut channel = Channel::new(&responses[..], Vec::new());
+        let _: Vec<u8> = channel.call("echo", vec![7u8; 4096]).unwrap();
+        let capacity = channel.send_buffer.capacity();
+        let ptr = channel.send_buffer.as_ptr();
+        assert!(capacity >= 4096);
+
+        // a smaller request is serialized into the same allocation
+        let _: Vec<u8> = channel.call("echo", vec![1u8]).unwrap();
+        assert_eq!(channel.send_buffer, b"[1]");
+        assert_eq!(channel.send_buffer.capacity(), capacity);
+        assert_eq!(channel.send_buffer.as_ptr(), ptr);
     }
 }
@@ -1,4 +1,4 @@
-use ckb_rust_std::io::Error as CoreIoError;
+use ckb_rust_std::io::{Error as CoreIoError, ErrorKind as CoreIoErrorKind};
 use ckb_std::error::SysError;
 use core::fmt::{self, Debug, Display};
 use enumn::N;
@@ -16,6 +16,8 @@ pub enum IpcError {
     UnexpectedEof,
     IncompleteVlqSeq,
     DecodeVlqOverflow,
+    /// A VLQ is not in its shortest form. Only returned when decoding canonical VLQs.
+    NonCanonicalVlq,
     ReadVlqError,
     SerializeError,
     DeserializeError,
@@ -24,9 +26,27 @@ pub enum IpcError {
     ReadExactError,
     BufReaderError,
     GeneralIoError,
+    /// A read or write on the underlying pipe timed out. Only returned by pipes with
+    /// timeouts, e.g. native pipes. The channel may be left in the middle of a packet
+    /// and should not be used anymore.
+    Timeout,
+    /// A payload does not fit into the buffer of a
+    /// [`FixedChannel`](crate::fixed::FixedChannel).
+    BufferTooSmall,
     ProtocolError(ProtocolErrorCode),
 }
 
+impl IpcError {
+    /// Converts an IO error into `IpcError::Timeout` if it timed out, otherwise into `other`.
+    pub(crate) fn from_io_error(err: CoreIoError, other: IpcError) -> Self {
+        if err.kind() == CoreIoErrorKind::TimedOut {
+            IpcError::Timeout
+        } else {
+            other
+        }
+    }
+}
+
 impl Display for IpcError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{self:?}")
@@ -40,9 +60,8 @@ impl Error for IpcError {
 }
 
 impl From<CoreIoError> for IpcError {
-    fn from(_: CoreIoError) -> Self {
-        // TODO
-        IpcError::GeneralIoError
+    fn from(err: CoreIoError) -> Self {
+        IpcError::from_io_error(err, IpcError::GeneralIoError)
     }
 }
 
@@ -90,9 +109,16 @@ pub enum ProtocolErrorCode {
     DeserializeError = 27,
     /// general IO error
     GeneralIoError = 28,
+    /// VLQ error: the encoding is not canonical
+    NonCanonicalVlq = 29,
+    /// A payload does not fit into a fixed buffer
+    BufferTooSmall = 30,
+    /// The method of a request is unknown to the server, e.g. an unregistered method id of
+    /// a C server
+    UnknownMethod = 31,
 
     // increase when appending new error codes
-    EndOfError = 29,
+    EndOfError = 32,
 }
 
 impl From<IpcError> for ProtocolErrorCode {
@@ -113,6 +139,8 @@ impl From<IpcError> for ProtocolErrorCode {
             IpcError::UnexpectedEof => ProtocolErrorCode::UnexpectedEof,
             IpcError::IncompleteVlqSeq => ProtocolErrorCode::IncompleteVlqSeq,
             IpcError::DecodeVlqOverflow => ProtocolErrorCode::DecodeVlqOverflow,
+            IpcError::NonCanonicalVlq => ProtocolErrorCode::NonCanonicalVlq,
+            IpcError::BufferTooSmall => ProtocolErrorCode::BufferTooSmall,
             IpcError::ReadVlqError => ProtocolErrorCode::ReadVlqError,
             IpcError::SerializeError => ProtocolErrorCode::SerializeError,
             IpcError::DeserializeError => ProtocolErrorCode::DeserializeError,
@@ -120,14 +148,15 @@ impl From<IpcError> for ProtocolErrorCode {
             | IpcError::BufReaderError
             | IpcError::ReadUntilError
             | IpcError::ReadExactError => ProtocolErrorCode::GeneralIoError,
-            IpcError::GeneralIoError => ProtocolErrorCode::GeneralIoError,
+            IpcError::GeneralIoError | IpcError::Timeout => ProtocolErrorCode::GeneralIoError,
             IpcError::ProtocolError(e) => e,
         }
     }
 }
 
 impl From<u64> for ProtocolErrorCode {
+    /// Unknown codes, e.g. from a newer peer, are mapped to `UnknownError`.
     fn from(e: u64) -> Self {
-        Self::n(e).unwrap()
+        Self::n(e).unwrap_or(ProtocolErrorCode::UnknownError)
     }
 }
@@ -0,0 +1,341 @@
+use crate::io::{Read, Write};
+use crate::{
+    error::{IpcError, ProtocolErrorCode},
+    ipc::Serve,
+    packet::{read_vlq, write_packet},
+};
+use core::alloc::{GlobalAlloc, Layout};
+use serde::{Deserialize, Serialize};
+use serde_json_core::{from_slice, ser, to_slice};
+
+/// A global allocator which fails every allocation, for scripts using [`FixedChannel`]
+/// instead of `default_alloc!()`.
+///
+/// `ckb-std` links the `alloc` crate, so a script must still declare a global allocator.
+/// With this one, the script doesn't reserve a heap, and any allocation aborts it:
+///
+/// ```rust,ignore
+/// #[global_allocator]
+/// static ALLOC: NoAlloc = NoAlloc;
+/// ```
+pub struct NoAlloc;
+
+unsafe impl GlobalAlloc for NoAlloc {
+    unsafe fn alloc(&self, _layout: Layout) -> *mut u8 {
+        core::ptr::null_mut()
+    }
+
+    unsafe fn dealloc(&self, _ptr: *mut u8, _layout: Layout) {}
+}
+
+/// A channel which never allocates, for scripts without a heap.
+///
+/// It speaks the same wire format as [`Channel`](crate::channel::Channel), so a
+/// `FixedChannel` client can talk to a `Channel` server and vice versa. Payloads are
+/// serialized into, and received into, a buffer provided by the caller, which bounds the
+/// size of both requests and responses. Payloads which don't fit fail with
+/// `IpcError::BufferTooSmall`.
+///
+/// Payloads are encoded with `serde-json-core`, which supports a subset of what
+/// `serde_json` does: `u128`/`i128` and strings with escape sequences are not supported.
+/// Unlike `Channel`, the reader and writer are not buffered, so each VLQ of a packet
+/// header is read with its own `read` call.
+///
+/// # Fields
+///
+/// * `reader` - Responsible for reading data from the channel.
+/// * `writer` - Responsible for writing data to the channel.
+/// * `buf` - Buffer holding the payload being sent or received.
+/// * `canonical_vlq` - Whether received packets must use canonical VLQs, see
+///   [`FixedChannel::set_canonical_vlq`].
+pub struct FixedChannel<'a, R: Read, W: Write> {
+    reader: R,
+    writer: W,
+    buf: &'a mut [u8],
+    canonical_vlq: bool,
+}
+
+impl<'a, R: Read, W: Write> FixedChannel<'a, R, W> {
+    pub fn new(reader: R, writer: W, buf: &'a mut [u8]) -> Self {
+        Self {
+            reader,
+            writer,
+            buf,
+            canonical_vlq: false,
+        }
+    }
+
+    /// Rejects received packets whose VLQs are not canonical, see
+    /// [`Channel::set_canonical_vlq`](crate::channel::Channel::set_canonical_vlq).
+    pub fn set_canonical_vlq(&mut self, canonical: bool) {
+        self.canonical_vlq = canonical;
+    }
+
+    /// Executes the server loop, see [`Channel::execute`](crate::channel::Channel::execute).
+    ///
+    /// A request which doesn't fit into the buffer is answered with
+    /// `ProtocolErrorCode::BufferTooSmall`, and ends the loop like any other error.
+    pub fn execute<Req, Resp, S>(mut self, serve: &mut S) -> Result<(), IpcError>
+    where
+        Req: Serialize + for<'de> Deserialize<'de>,
+        Resp: Serialize + for<'de> Deserialize<'de>,
+        S: Serve<Req = Req, Resp = Resp>,
+    {
+        loop {
+            let result = self
+                .receive_request()
+                .and_then(|req| serve.serve(req))
+                .and_then(|resp| self.send(&resp));
+
+            if let Err(e) = result {
+                #[cfg(feature = "enable-logging")]
+                log::error!("Error in execute loop: {:?}", e);
+                // notify client
+                self.send_error_code(e.clone().into()).unwrap();
+                return Err(e);
+            }
+        }
+    }
+
+    /// Sends a request to the server and waits for a response, see
+    /// [`Channel::call`](crate::channel::Channel::call).
+    ///
+    /// Fails with `IpcError::BufferTooSmall` if the request or the response doesn't fit into
+    /// the buffer. An oversized response is skipped, so the channel can still be used.
+    pub fn call<Req, Resp>(
+        &mut self,
+        _method_name: &'static str,
+        req: Req,
+    ) -> Result<Resp, IpcError>
+    where
+        Req: Serialize + for<'de> Deserialize<'de>,
+        Resp: Serialize + for<'de> Deserialize<'de>,
+    {
+        let result = self.send(&req).and_then(|_| self.receive_response());
+        #[cfg(feature = "enable-logging")]
+        if let Err(e) = &result {
+            log::error!("Error in call({}): {:?}", _method_name, e);
+        }
+        result
+    }
+
+    fn send<T: Serialize>(&mut self, value: &T) -> Result<(), IpcError> {
+        let len = to_slice(value, self.buf).map_err(|e| match e {
+            ser::Error::BufferFull => IpcError::BufferTooSmall,
+            _ => IpcError::SerializeError,
+        })?;
+        write_packet(&mut self.writer, 0, 0, &self.buf[..len])?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    fn send_error_code(&mut self, error_code: ProtocolErrorCode) -> Result<(), IpcError> {
+        write_packet(&mut self.writer, 0, error_code as u64, &[])?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    // Reads a packet into the buffer, returning its method id or error code and the length
+    // of its payload. A payload larger than the buffer is read and dropped, so the next
+    // packet can still be read. It's drained through a stack array, as the buffer may be
+    // empty.
+    fn read_packet(&mut self) -> Result<(u64, usize), IpcError> {
+        let _version = read_vlq(&mut self.reader, self.canonical_vlq)?;
+        let id = read_vlq(&mut self.reader, self.canonical_vlq)?;
+        let len = read_vlq(&mut self.reader, self.canonical_vlq)?;
+        if len > self.buf.len() as u64 {
+            let mut drain = [0u8; 64];
+            let mut remaining = len;
+            while remaining > 0 {
+                let n = remaining.min(drain.len() as u64) as usize;
+                self.reader
+                    .read_exact(&mut drain[..n])
+                    .map_err(|e| IpcError::from_io_error(e, IpcError::ReadExactError))?;
+                remaining -= n as u64;
+            }
+            return Err(IpcError::BufferTooSmall);
+        }
+        self.read_exact(len as usize)?;
+        Ok((id, len as usize))
+    }
+
+    fn read_exact(&mut self, len: usize) -> Result<(), IpcError> {
+        self.reader
+            .read_exact(&mut self.buf[..len])
+            .map_err(|e| IpcError::from_io_error(e, IpcError::ReadExactError))
+    }
+
+    fn receive_request<Req: for<'de> Deserialize<'de>>(&mut self) -> Result<Req, IpcError> {
+        let (_method_id, len) = self.read_packet()?;
+        self.deserialize(len)
+    }
+
+    fn receive_response<Resp: for<'de> Deserialize<'de>>(&mut self) -> Result<Resp, IpcError> {
+        let (error_code, len) = self.read_packet()?;
+        match ProtocolErrorCode::from(error_code) {
+            ProtocolErrorCode::Ok => self.deserialize(len),
+            e => Err(IpcError::ProtocolError(e)),
+        }
+    }
+
+    fn deserialize<T: for<'de> Deserialize<'de>>(&self, len: usize) -> Result<T, IpcError> {
+        from_slice(&self.buf[..len])
+            .map(|(value, _)| value)
+            .map_err(|_| IpcError::DeserializeError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channel::Channel;
+    use crate::packet::{Packet, RequestPacket, ResponsePacket};
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Request {
+        Add { a: u32, b: u32 },
+        Echo { data: Vec<u8> },
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Response {
+        Add(u64),
+        Echo(Vec<u8>),
+    }
+
+    struct Service;
+
+    impl Serve for Service {
+        type Req = Request;
+        type Resp = Response;
+
+        fn serve(&mut self, req: Request) -> Result<Response, IpcError> {
+            Ok(match req {
+                Request::Add { a, b } => Response::Add(a as u64 + b as u64),
+                Request::Echo { data } => Response::Echo(data),
+            })
+        }
+    }
+
+    fn responses(bytes: &[u8]) -> Vec<ResponsePacket> {
+        let mut reader = bytes;
+        let mut packets = Vec::new();
+        while !reader.is_empty() {
+            packets.push(ResponsePacket::read_from(&mut reader).unwrap());
+        }
+        packets
+    }
+
+    #[test]
+    fn test_same_wire_format() {
+        let requests = [
+            Request::Add { a: 1, b: 2 },
+            Request::Echo { data: vec![1, 2] },
+        ];
+        let mut fixed_bytes = Vec::new();
+        let mut channel_bytes = Vec::new();
+        let mut buf = [0u8; 64];
+        for req in requests {
+            let mut fixed = FixedChannel::new(&[][..], &mut fixed_bytes, &mut buf);
+            fixed.send(&req).unwrap();
+            let mut channel = Channel::new(&[][..], &mut channel_bytes);
+            channel.send_request(req).unwrap();
+        }
+        assert_eq!(fixed_bytes, channel_bytes);
+
+        // requests sent by `FixedChannel` are served by `FixedChannel` and `Channel` alike
+        let mut output = Vec::new();
+        let result =
+            FixedChannel::new(&fixed_bytes[..], &mut output, &mut buf).execute(&mut Service);
+        assert!(result.is_err());
+        let mut channel_output = Vec::new();
+        let result = Channel::new(&fixed_bytes[..], &mut channel_output).execute(&mut Service);
+        assert!(result.is_err());
+        assert_eq!(output, channel_output);
+
+        let packets = responses(&output);
+        assert_eq!(packets.len(), 3);
+        assert_eq!(packets[0].payload(), br#"{"Add":3}"#);
+        assert_eq!(packets[1].payload(), br#"{"Echo":[1,2]}"#);
+        assert_eq!(
+            packets[2].error_code(),
+            ProtocolErrorCode::IncompleteVlqSeq as u64
+        );
+
+        let mut input = output.as_slice();
+        let mut fixed = FixedChannel::new(&mut input, Vec::new(), &mut buf);
+        let resp: Response = fixed.call("add", Request::Add { a: 1, b: 2 }).unwrap();
+        assert_eq!(resp, Response::Add(3));
+        let resp: Response = fixed.call("echo", Request::Echo { data: vec![] }).unwrap();
+        assert_eq!(resp, Response::Echo(vec![1, 2]));
+        let resp: Result<Response, _> = fixed.call("add", Request::Add { a: 1, b: 2 });
+        assert!(matches!(
+            resp,
+            Err(IpcError::ProtocolError(ProtocolErrorCode::IncompleteVlqSeq))
+        ));
+    }
+
+    #[test]
+    fn test_buffer_too_small() {
+        let mut buf = [0u8; 24];
+        let mut output = Vec::new();
+        let mut fixed = FixedChannel::new(&[][..], &mut output, &mut buf);
+        let resp: Result<Response, _> = fixed.call("echo", Request::Echo { data: vec![0; 16] });
+        assert!(matches!(resp, Err(IpcError::BufferTooSmall)));
+        assert!(output.is_empty());
+
+        // an oversized response is skipped and the next one is received
+        let mut input =
+            ResponsePacket::new(0, br#"{"Echo":[0,0,0,0,0,0,0,0]}"#.to_vec()).serialize();
+        input.extend(ResponsePacket::new(0, br#"{"Add":3}"#.to_vec()).serialize());
+        let mut reader = input.as_slice();
+        let mut fixed = FixedChannel::new(&mut reader, Vec::new(), &mut buf);
+        let resp: Result<Response, _> = fixed.call("add", Request::Add { a: 1, b: 2 });
+        assert!(matches!(resp, Err(IpcError::BufferTooSmall)));
+        let resp: Response = fixed.call("add", Request::Add { a: 1, b: 2 }).unwrap();
+        assert_eq!(resp, Response::Add(3));
+
+        // an oversized request is answered with an error code
+        let input = RequestPacket::new(br#"{"Echo":{"data":[0,0,0,0,0,0]}}"#.to_vec()).serialize();
+        let mut output = Vec::new();
+        let result = FixedChannel::new(&input[..], &mut output, &mut buf).execute(&mut Service);
+        assert!(matches!(result, Err(IpcError::BufferTooSmall)));
+        let packets = responses(&output);
+        assert_eq!(packets.len(), 1);
+        assert_eq!(
+            packets[0].error_code(),
+            ProtocolErrorCode::BufferTooSmall as u64
+        );
+    }
+
+    #[test]
+    fn test_empty_buffer() {
+        let mut output = Vec::new();
+        let mut fixed = FixedChannel::new(&[][..], &mut output, &mut []);
+        let resp: Result<Response, _> = fixed.call("add", Request::Add { a: 1, b: 2 });
+        assert!(matches!(resp, Err(IpcError::BufferTooSmall)));
+
+        // a non-empty payload is skipped, and an empty one is received
+        let mut input = ResponsePacket::new(0, br#"{"Add":3}"#.to_vec()).serialize();
+        input.extend(ResponsePacket::new(0, Vec::new()).serialize());
+        let mut reader = input.as_slice();
+        let mut fixed = FixedChannel::new(&mut reader, Vec::new(), &mut []);
+        assert!(matches!(fixed.read_packet(), Err(IpcError::BufferTooSmall)));
+        assert!(matches!(fixed.read_packet(), Ok((0, 0))));
+        drop(fixed);
+        assert!(reader.is_empty());
+
+        let input = RequestPacket::new(br#"{"Add":{"a":1,"b":2}}"#.to_vec()).serialize();
+        let mut output = Vec::new();
+        let result = FixedChannel::new(&input[..], &mut output, &mut []).execute(&mut Service);
+        assert!(matches!(result, Err(IpcError::BufferTooSmall)));
+        let packets = responses(&output);
+        assert_eq!(packets.len(), 1);
+        assert_eq!(
+            packets[0].error_code(),
+            ProtocolErrorCode::BufferTooSmall as u64
+        );
+    }
+}
@@ -2,11 +2,15 @@
 extern crate alloc;
 pub mod channel;
 pub mod error;
+#[cfg(feature = "no_alloc")]
+pub mod fixed;
 pub mod io;
 pub mod ipc;
 #[cfg(feature = "std")]
 pub mod native;
 pub mod packet;
 pub mod pipe;
+pub mod record;
 pub mod spawn;
+pub mod stats;
 pub mod vlq;
@@ -4,8 +4,10 @@ use alloc::boxed::Box;
 use alloc::string::String;
 use alloc::vec;
 use alloc::vec::Vec;
-use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
-use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use ckb_vm::cost_model::estimate_cycles;
 use ckb_vm::registers::{A0, A1, A2, A7};
@@ -13,6 +15,9 @@ use ckb_vm::{Bytes, Memory, Register, SupportMachine, Syscalls};
 
 use crate::io::{Error, ErrorKind, Read, Write};
 
+pub use ckb_vm::machine::{VERSION0, VERSION1, VERSION2};
+pub use ckb_vm::{ISA_A, ISA_B, ISA_IMC, ISA_MOP, MEMORY_FRAMESIZE, RISCV_MAX_MEMORY};
+
 pub const SPAWN: i32 = 2601;
 pub const WAIT: i32 = 2602;
 pub const PROCESS_ID: i32 = 2603;
@@ -27,7 +32,94 @@ pub const SPAWN_YIELD_CYCLES_BASE: u64 = 800;
 
 pub const FIRST_FD_SLOT: u64 = 2;
 
-struct DebugSyscall {}
+/// A running counter of the cycles consumed by a native server.
+///
+/// The counter is updated when the server writes to its pipe, just before the
+/// data is sent, and once more when the server exits. Sampling it before and
+/// after an IPC call therefore gives the cycles the server spent between its
+/// previous response and this one, without any work done after the reply. The
+/// first call also includes the startup of the server. It can be cloned and
+/// shared with other threads.
+#[derive(Clone, Debug, Default)]
+pub struct CycleCounter(Arc<AtomicU64>);
+
+impl CycleCounter {
+    /// Returns the cycles consumed by the server so far.
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Acquire)
+    }
+
+    fn set(&self, cycles: u64) {
+        self.0.store(cycles, Ordering::Release);
+    }
+}
+
+/// A line printed by a native server through the debug syscall.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DebugLine {
+    /// Name of the server, see [`NativeSpawnOptions::name`].
+    pub name: String,
+    /// The printed line, without the trailing newline.
+    pub line: String,
+}
+
+/// Callback of [`DebugSink::Callback`], called with the name of the server and the line.
+pub type DebugCallback = Arc<dyn Fn(&str, &str) + Send + Sync>;
+
+/// Destination of the debug output (e.g. `ckb_std::debug!`) of a native server.
+///
+/// The output is split into lines, and each line is tagged with the name of the server.
+#[derive(Clone, Default)]
+pub enum DebugSink {
+    /// Prints `[name] line` to stdout.
+    #[default]
+    Stdout,
+    /// Calls the function with the name of the server and the line.
+    Callback(DebugCallback),
+    /// Sends each line to the channel. Lines are dropped once the receiver is gone.
+    Channel(std::sync::mpsc::Sender<DebugLine>),
+    /// Logs each line at info level with target `ckb_script_ipc::native`.
+    #[cfg(feature = "enable-logging")]
+    Log,
+    /// Discards the output.
+    Discard,
+}
+
+impl std::fmt::Debug for DebugSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DebugSink::Stdout => write!(f, "Stdout"),
+            DebugSink::Callback(_) => write!(f, "Callback"),
+            DebugSink::Channel(_) => write!(f, "Channel"),
+            #[cfg(feature = "enable-logging")]
+            DebugSink::Log => write!(f, "Log"),
+            DebugSink::Discard => write!(f, "Discard"),
+        }
+    }
+}
+
+impl DebugSink {
+    fn emit(&self, name: &str, line: &str) {
+        match self {
+            DebugSink::Stdout => std::println!("[{}] {}", name, line),
+            DebugSink::Callback(callback) => callback(name, line),
+            DebugSink::Channel(sender) => {
+                let _ = sender.send(DebugLine {
+                    name: name.into(),
+                    line: line.into(),
+                });
+            }
+            #[cfg(feature = "enable-logging")]
+            DebugSink::Log => log::info!(target: "ckb_script_ipc::native", "[{}] {}", name, line),
+            DebugSink::Discard => {}
+        }
+    }
+}
+
+struct DebugSyscall {
+    name: String,
+    sink: DebugSink,
+}
 
 impl<Mac: SupportMachine> Syscalls<Mac> for DebugSyscall {
     fn initialize(&mut self, _machine: &mut Mac) -> Result<(), ckb_vm::error::Error> {
@@ -65,7 +157,9 @@ impl<Mac: SupportMachine> Syscalls<Mac> for DebugSyscall {
         }
 
         let s = String::from_utf8_lossy(&buffer);
-        std::println!("{:?}", s);
+        for line in s.lines() {
+            self.sink.emit(&self.name, line);
+        }
         machine.set_register(A0, Mac::REG::from_u8(0));
         Ok(true)
     }
@@ -124,11 +218,12 @@ impl<Mac: SupportMachine> Syscalls<Mac> for ReadSyscall {
 
 struct WriteSyscall {
     pipe: Pipe,
+    counter: CycleCounter,
 }
 
 impl WriteSyscall {
-    pub fn new(pipe: Pipe) -> Self {
-        Self { pipe }
+    pub fn new(pipe: Pipe, counter: CycleCounter) -> Self {
+        Self { pipe, counter }
     }
 }
 
@@ -162,6 +257,9 @@ impl<Mac: SupportMachine> Syscalls<Mac> for WriteSyscall {
         let bytes = machine
             .memory_mut()
             .load_bytes(buffer_addr.to_u64(), length)?;
+        // Snapshot before the data is sent, so a client sampling the counter
+        // after reading the response never sees cycles spent after it.
+        self.counter.set(machine.cycles());
         // the pipe write can't write partial data so we don't need to check result length.
         self.pipe
             .write(&bytes)
@@ -268,6 +366,13 @@ impl<Mac: SupportMachine> Syscalls<Mac> for CloseSyscall {
 /// - Either `tx` or `rx` will be `Some`, but never both, determining whether the pipe is
 ///   for reading or writing
 ///
+/// # Timeouts
+/// By default, reads and writes block until the other end is ready. Use
+/// [`Pipe::set_read_timeout`] and [`Pipe::set_write_timeout`] to fail with an
+/// [`ErrorKind::TimedOut`] error instead, which surfaces as
+/// [`IpcError::Timeout`](crate::error::IpcError::Timeout) in a channel. The pipes returned by
+/// [`spawn_server`] use the timeout set by [`set_default_timeout`].
+///
 /// # Thread Safety
 /// - The receiver is wrapped in a `Mutex` to ensure thread-safe access
 /// - The sender is naturally thread-safe through `SyncSender`
@@ -279,6 +384,33 @@ pub struct Pipe {
     tx: Option<SyncSender<Vec<u8>>>,
     rx: Option<Mutex<Receiver<Vec<u8>>>>,
     buf: Vec<u8>,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+}
+
+// Default timeout in milliseconds, 0 means no timeout.
+static DEFAULT_TIMEOUT_MS: AtomicU64 = AtomicU64::new(0);
+
+/// Sets the read and write timeout applied to the client pipes returned by
+/// [`spawn_server`] and [`spawn_server_with_options`]. `None` (the initial value)
+/// means blocking forever.
+///
+/// This is mainly meant for test harnesses, so that a server which stops responding
+/// fails the test instead of hanging it:
+/// ```rust,ignore
+/// ckb_script_ipc_common::native::set_default_timeout(Some(Duration::from_secs(10)));
+/// ```
+pub fn set_default_timeout(timeout: Option<Duration>) {
+    let ms = timeout.map_or(0, |t| (t.as_millis() as u64).max(1));
+    DEFAULT_TIMEOUT_MS.store(ms, Ordering::Relaxed);
+}
+
+/// Returns the timeout set by [`set_default_timeout`].
+pub fn default_timeout() -> Option<Duration> {
+    match DEFAULT_TIMEOUT_MS.load(Ordering::Relaxed) {
+        0 => None,
+        ms => Some(Duration::from_millis(ms)),
+    }
 }
 
 impl Pipe {
@@ -289,14 +421,34 @@ impl Pipe {
                 tx: None,
                 rx: Some(Mutex::new(rx)),
                 buf: vec![],
+                read_timeout: None,
+                write_timeout: None,
             },
             Self {
                 tx: Some(tx),
                 rx: None,
                 buf: vec![],
+                read_timeout: None,
+                write_timeout: None,
             },
         )
     }
+    /// Sets the timeout of reads. `None` means blocking until data arrives or the
+    /// other end is closed.
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.read_timeout = timeout;
+    }
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.read_timeout
+    }
+    /// Sets the timeout of writes. `None` means blocking until the other end reads
+    /// the data or is closed.
+    pub fn set_write_timeout(&mut self, timeout: Option<Duration>) {
+        self.write_timeout = timeout;
+    }
+    pub fn write_timeout(&self) -> Option<Duration> {
+        self.write_timeout
+    }
     pub fn close(&mut self) {
         if self.tx.is_some() {
             drop(self.tx.take());
@@ -310,9 +462,19 @@ impl Pipe {
 impl Read for Pipe {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
         if self.buf.is_empty() {
-            match self.rx.as_ref().unwrap().lock().unwrap().recv() {
+            let rx = self.rx.as_ref().unwrap().lock().unwrap();
+            let result = match self.read_timeout {
+                Some(timeout) => rx.recv_timeout(timeout),
+                None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            match result {
                 Ok(data) => self.buf = data,
-                Err(_) => {
+                Err(RecvTimeoutError::Timeout) => {
+                    #[cfg(feature = "enable-logging")]
+                    log::error!("Pipe Read: timed out");
+                    return Err(Error::new(ErrorKind::TimedOut, "read timed out"));
+                }
+                Err(RecvTimeoutError::Disconnected) => {
                     #[cfg(feature = "enable-logging")]
                     log::info!("Pipe Read: channel is closed");
                     // if channel is closed, return EOF
@@ -335,16 +497,27 @@ impl Write for Pipe {
         if buf.is_empty() {
             return Ok(0);
         }
-        match self.tx.as_mut().unwrap().send(buf.to_vec()) {
+        let tx = self.tx.as_mut().unwrap();
+        let result = match self.write_timeout {
+            Some(timeout) => send_timeout(tx, buf.to_vec(), timeout),
+            None => tx
+                .send(buf.to_vec())
+                .map_err(|e| TrySendError::Disconnected(e.0)),
+        };
+        match result {
             Ok(_) => {
                 #[cfg(feature = "enable-logging")]
                 log::info!("Pipe Write: write {} bytes", buf.len());
                 Ok(buf.len())
             }
-            Err(e) => {
+            Err(TrySendError::Full(_)) => {
                 #[cfg(feature = "enable-logging")]
-                log::error!("Pipe Write: channel is closed {:?}", e);
-                drop(e);
+                log::error!("Pipe Write: timed out");
+                Err(Error::new(ErrorKind::TimedOut, "write timed out"))
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                #[cfg(feature = "enable-logging")]
+                log::error!("Pipe Write: channel is closed");
                 Err(Error::new(ErrorKind::Other, "channel is closed"))
             }
         }
@@ -355,62 +528,297 @@ impl Write for Pipe {
     }
 }
 
-#[cfg(has_asm)]
-fn ckb_vm_entry(
-    code: Bytes,
-    args: Vec<Bytes>,
+// `SyncSender::send_timeout` is not stable yet: poll `try_send` until the deadline.
+// A zero-capacity channel only accepts the data when the receiver is waiting, so
+// `TrySendError::Full` is returned on timeout.
+fn send_timeout(
+    tx: &SyncSender<Vec<u8>>,
+    mut data: Vec<u8>,
+    timeout: Duration,
+) -> Result<(), TrySendError<Vec<u8>>> {
+    let deadline = Instant::now() + timeout;
+    let mut spins = 0u32;
+    loop {
+        match tx.try_send(data) {
+            Err(TrySendError::Full(d)) => {
+                if Instant::now() >= deadline {
+                    return Err(TrySendError::Full(d));
+                }
+                data = d;
+                if spins < 64 {
+                    spins += 1;
+                    std::thread::yield_now();
+                } else {
+                    std::thread::sleep(Duration::from_micros(100));
+                }
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Selects the ckb-vm machine implementation used to run a native server.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MachineKind {
+    /// The assembly machine. Only available when ckb-vm is built with asm support
+    /// (the `asm` or `detect-asm` feature on a supported target).
+    Asm,
+    /// The portable interpreter machine, available on every target.
+    Interpreter,
+}
+
+impl Default for MachineKind {
+    fn default() -> Self {
+        if cfg!(has_asm) {
+            MachineKind::Asm
+        } else {
+            MachineKind::Interpreter
+        }
+    }
+}
+
+/// VM parameters used by [`spawn_server_with_options`].
+///
+/// The default value matches the current mainnet VM: `ISA_IMC | ISA_B | ISA_MOP`,
+/// `VERSION2`, the full 4M memory and the asm machine when it is available. The
+/// default cycle limit is `u64::MAX`, i.e. unlimited.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use ckb_script_ipc_common::native::{MachineKind, NativeSpawnOptions, VERSION1};
+///
+/// // run the server like a script on the Mirana hardfork, limited to 70M cycles
+/// let options = NativeSpawnOptions {
+///     version: VERSION1,
+///     max_cycles: 70_000_000,
+///     machine: MachineKind::Interpreter,
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Clone, Debug)]
+pub struct NativeSpawnOptions {
+    /// ISA flags, a combination of [`ISA_IMC`], [`ISA_B`], [`ISA_MOP`] and [`ISA_A`].
+    pub isa: u8,
+    /// VM version, one of [`VERSION0`], [`VERSION1`] and [`VERSION2`].
+    pub version: u32,
+    /// Maximum cycles the server may consume. Exceeding it stops the server with
+    /// [`NativeError::CyclesExceeded`].
+    pub max_cycles: u64,
+    /// VM memory size in bytes. It must be a non-zero multiple of [`MEMORY_FRAMESIZE`]
+    /// and no larger than [`RISCV_MAX_MEMORY`].
+    pub memory_size: usize,
+    /// Machine implementation to run the server on.
+    pub machine: MachineKind,
+    /// Name of the server, used to tag its debug output.
+    pub name: String,
+    /// Where the debug output of the server goes.
+    pub debug_sink: DebugSink,
+}
+
+impl Default for NativeSpawnOptions {
+    fn default() -> Self {
+        Self {
+            isa: ISA_IMC | ISA_B | ISA_MOP,
+            version: VERSION2,
+            max_cycles: u64::MAX,
+            memory_size: RISCV_MAX_MEMORY,
+            machine: MachineKind::default(),
+            name: "server".into(),
+            debug_sink: DebugSink::default(),
+        }
+    }
+}
+
+impl NativeSpawnOptions {
+    fn validate(&self) -> Result<(), NativeError> {
+        if self.memory_size == 0
+            || self.memory_size > RISCV_MAX_MEMORY
+            || self.memory_size % MEMORY_FRAMESIZE != 0
+        {
+            return Err(NativeError::InvalidMemorySize(self.memory_size));
+        }
+        if self.machine == MachineKind::Asm && !cfg!(has_asm) {
+            return Err(NativeError::AsmUnavailable);
+        }
+        Ok(())
+    }
+}
+
+/// Errors reported by a native server.
+#[derive(Debug)]
+pub enum NativeError {
+    /// The memory size in [`NativeSpawnOptions`] is not accepted by ckb-vm.
+    InvalidMemorySize(usize),
+    /// [`MachineKind::Asm`] was requested but ckb-vm is built without asm support.
+    AsmUnavailable,
+    /// The server consumed more than `max_cycles`.
+    CyclesExceeded { max_cycles: u64 },
+    /// Any other error raised by ckb-vm, e.g. an invalid binary or memory fault.
+    Vm(ckb_vm::error::Error),
+    /// The server thread panicked.
+    Panicked,
+}
+
+impl std::fmt::Display for NativeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NativeError::InvalidMemorySize(size) => write!(f, "invalid memory size: {}", size),
+            NativeError::AsmUnavailable => write!(f, "asm machine is not available"),
+            NativeError::CyclesExceeded { max_cycles } => {
+                write!(f, "max cycles exceeded: {}", max_cycles)
+            }
+            NativeError::Vm(e) => write!(f, "ckb-vm error: {}", e),
+            NativeError::Panicked => write!(f, "server thread panicked"),
+        }
+    }
+}
+
+impl std::error::Error for NativeError {}
+
+/// A handle to a server spawned by [`spawn_server_with_options`].
+///
+/// Dropping the handle detaches the server thread.
+pub struct ServerHandle {
+    join_handle: std::thread::JoinHandle<Result<i8, NativeError>>,
+    cycles: CycleCounter,
+}
+
+impl ServerHandle {
+    /// Waits for the server to exit and returns its exit code, or the error that
+    /// stopped it.
+    pub fn join(self) -> Result<i8, NativeError> {
+        self.join_handle
+            .join()
+            .unwrap_or(Err(NativeError::Panicked))
+    }
+
+    /// Returns true if the server has exited.
+    pub fn is_finished(&self) -> bool {
+        self.join_handle.is_finished()
+    }
+
+    /// Returns the cycles consumed by the server so far.
+    pub fn cycles(&self) -> u64 {
+        self.cycles.get()
+    }
+
+    /// Returns a counter of the cycles consumed by the server, which stays valid
+    /// after the handle is joined or dropped.
+    ///
+    /// It can be used as the cycle source of a client channel:
+    /// ```rust,ignore
+    /// let counter = handle.cycle_counter();
+    /// client.channel_mut().enable_cycle_stats_with(move || counter.get());
+    /// ```
+    pub fn cycle_counter(&self) -> CycleCounter {
+        self.cycles.clone()
+    }
+}
+
+fn install_syscalls<Inner: SupportMachine>(
+    builder: ckb_vm::DefaultMachineBuilder<Inner>,
     read_pipe: Pipe,
     write_pipe: Pipe,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let asm_core = ckb_vm::machine::asm::AsmCoreMachine::new(
-        ckb_vm::ISA_IMC | ckb_vm::ISA_B | ckb_vm::ISA_MOP,
-        ckb_vm::machine::VERSION2,
-        u64::MAX,
-    );
-    let core = ckb_vm::DefaultMachineBuilder::new(asm_core)
+    options: &NativeSpawnOptions,
+    counter: CycleCounter,
+) -> ckb_vm::DefaultMachineBuilder<Inner> {
+    builder
         .instruction_cycle_func(Box::new(estimate_cycles))
-        .syscall(Box::new(DebugSyscall {}))
+        .syscall(Box::new(DebugSyscall {
+            name: options.name.clone(),
+            sink: options.debug_sink.clone(),
+        }))
         .syscall(Box::new(ReadSyscall::new(read_pipe)))
-        .syscall(Box::new(WriteSyscall::new(write_pipe)))
+        .syscall(Box::new(WriteSyscall::new(write_pipe, counter)))
         .syscall(Box::new(InheritedFdSyscall {}))
         .syscall(Box::new(CloseSyscall {}))
-        .syscall(Box::new(DebugSyscall {}))
-        .build();
+}
+
+#[cfg(has_asm)]
+fn run_asm_machine(
+    code: Bytes,
+    args: Vec<Bytes>,
+    read_pipe: Pipe,
+    write_pipe: Pipe,
+    options: &NativeSpawnOptions,
+    counter: CycleCounter,
+) -> Result<i8, ckb_vm::error::Error> {
+    let asm_core = ckb_vm::machine::asm::AsmCoreMachine::new_with_memory(
+        options.isa,
+        options.version,
+        options.max_cycles,
+        options.memory_size,
+    );
+    let core = install_syscalls(
+        ckb_vm::DefaultMachineBuilder::new(asm_core),
+        read_pipe,
+        write_pipe,
+        options,
+        counter.clone(),
+    )
+    .build();
     let mut machine = ckb_vm::machine::asm::AsmMachine::new(core);
     let args_iter = args.into_iter().map(Ok);
     machine.load_program(&code, args_iter)?;
-    let _exit = machine.run();
-    let _cycles = machine.machine.cycles();
-    Ok(())
+    let result = machine.run();
+    counter.set(machine.machine.cycles());
+    result
 }
 
-#[cfg(not(has_asm))]
-fn ckb_vm_entry(
+fn run_interpreter_machine(
     code: Bytes,
     args: Vec<Bytes>,
     read_pipe: Pipe,
     write_pipe: Pipe,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let core_machine = ckb_vm::DefaultCoreMachine::<u64, ckb_vm::SparseMemory<u64>>::new(
-        ckb_vm::ISA_IMC | ckb_vm::ISA_B | ckb_vm::ISA_MOP,
-        ckb_vm::machine::VERSION2,
-        u64::MAX,
-    );
-    let machine_builder = ckb_vm::DefaultMachineBuilder::new(core_machine)
-        .instruction_cycle_func(Box::new(estimate_cycles));
-    let mut machine = machine_builder
-        .instruction_cycle_func(Box::new(estimate_cycles))
-        .syscall(Box::new(DebugSyscall {}))
-        .syscall(Box::new(ReadSyscall::new(read_pipe)))
-        .syscall(Box::new(WriteSyscall::new(write_pipe)))
-        .syscall(Box::new(InheritedFdSyscall {}))
-        .syscall(Box::new(CloseSyscall {}))
-        .build();
+    options: &NativeSpawnOptions,
+    counter: CycleCounter,
+) -> Result<i8, ckb_vm::error::Error> {
+    let core_machine =
+        ckb_vm::DefaultCoreMachine::<u64, ckb_vm::SparseMemory<u64>>::new_with_memory(
+            options.isa,
+            options.version,
+            options.max_cycles,
+            options.memory_size,
+        );
+    let mut machine = install_syscalls(
+        ckb_vm::DefaultMachineBuilder::new(core_machine),
+        read_pipe,
+        write_pipe,
+        options,
+        counter.clone(),
+    )
+    .build();
     let args_iter = args.into_iter().map(Ok);
     machine.load_program(&code, args_iter)?;
-    let _exit = machine.run();
-    let _cycles = machine.cycles();
-    Ok(())
+    let result = machine.run();
+    counter.set(machine.cycles());
+    result
+}
+
+fn ckb_vm_entry(
+    code: Bytes,
+    args: Vec<Bytes>,
+    read_pipe: Pipe,
+    write_pipe: Pipe,
+    options: NativeSpawnOptions,
+    counter: CycleCounter,
+) -> Result<i8, NativeError> {
+    let result = match options.machine {
+        #[cfg(has_asm)]
+        MachineKind::Asm => run_asm_machine(code, args, read_pipe, write_pipe, &options, counter),
+        #[cfg(not(has_asm))]
+        MachineKind::Asm => return Err(NativeError::AsmUnavailable),
+        MachineKind::Interpreter => {
+            run_interpreter_machine(code, args, read_pipe, write_pipe, &options, counter)
+        }
+    };
+    result.map_err(|e| match e {
+        ckb_vm::error::Error::CyclesExceeded => NativeError::CyclesExceeded {
+            max_cycles: options.max_cycles,
+        },
+        e => NativeError::Vm(e),
+    })
 }
 /// Spawns a new CKB-VM instance running the provided script binary in a
 /// separate thread with bidirectional communication channels.
@@ -455,18 +863,62 @@ pub fn spawn_server(
     script_binary: &[u8],
     args: &[&str],
 ) -> Result<(Pipe, Pipe), Box<dyn std::error::Error>> {
+    let (read_pipe, write_pipe, _handle) =
+        spawn_server_with_options(script_binary, args, &NativeSpawnOptions::default())?;
+    Ok((read_pipe, write_pipe))
+}
+
+/// Same as [`spawn_server`], but runs the script with the VM parameters in `options`
+/// and returns a [`ServerHandle`] in addition to the pipes.
+///
+/// VM errors, including running out of cycles, stop the server and close its pipes,
+/// so the client observes an IPC error. The cause can then be retrieved with
+/// [`ServerHandle::join`].
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let options = NativeSpawnOptions {
+///     max_cycles: 10_000_000,
+///     ..Default::default()
+/// };
+/// let (read_pipe, write_pipe, handle) = spawn_server_with_options(binary, &[], &options)?;
+/// // ... use the pipes, then drop them
+/// match handle.join() {
+///     Err(NativeError::CyclesExceeded { .. }) => { /* over budget */ }
+///     _ => {}
+/// }
+/// ```
+pub fn spawn_server_with_options(
+    script_binary: &[u8],
+    args: &[&str],
+    options: &NativeSpawnOptions,
+) -> Result<(Pipe, Pipe, ServerHandle), NativeError> {
+    options.validate()?;
     // channel: ckb-vm -> native
-    let (read_pipe1, write_pipe1) = Pipe::new_pair();
+    let (mut read_pipe1, write_pipe1) = Pipe::new_pair();
     // channel: native -> ckb-vm
-    let (read_pipe2, write_pipe2) = Pipe::new_pair();
+    let (read_pipe2, mut write_pipe2) = Pipe::new_pair();
+    read_pipe1.set_read_timeout(default_timeout());
+    write_pipe2.set_write_timeout(default_timeout());
 
     let code = Bytes::copy_from_slice(script_binary);
     let args = args
         .iter()
         .map(|s| Bytes::copy_from_slice(s.as_bytes()))
         .collect();
-    std::thread::spawn(move || {
-        let _ = ckb_vm_entry(code, args, read_pipe2, write_pipe1);
+    let options = options.clone();
+    let cycles = CycleCounter::default();
+    let counter = cycles.clone();
+    let join_handle = std::thread::spawn(move || {
+        ckb_vm_entry(code, args, read_pipe2, write_pipe1, options, counter)
     });
-    Ok((read_pipe1, write_pipe2))
+    Ok((
+        read_pipe1,
+        write_pipe2,
+        ServerHandle {
+            join_handle,
+            cycles,
+        },
+    ))
 }
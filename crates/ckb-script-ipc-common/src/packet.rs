@@ -1,11 +1,10 @@
 use alloc::string::String;
-use alloc::vec;
 use alloc::vec::Vec;
-use core::fmt::{Debug, Formatter, Result as FmtResult};
+use core::fmt::{Debug, Display, Formatter, Result as FmtResult};
 
-use crate::error::IpcError;
-use crate::io::Read;
-use crate::vlq::{vlq_decode, vlq_encode};
+use crate::error::{IpcError, ProtocolErrorCode};
+use crate::io::{BufRead, Read, Write};
+use crate::vlq::{vlq_decode_canonical, vlq_decode_prefix, vlq_encode_into, MAX_VLQ_LEN};
 
 /// The `Packet` trait defines the interface for handling packets in an IPC context.
 /// Types implementing this trait can be used to represent and manipulate packets.
@@ -50,13 +49,7 @@ impl Packet for RequestPacket {
         &self.payload
     }
     fn read_from<R: Read>(reader: &mut R) -> Result<Self, IpcError> {
-        let version = read_next_vlq(reader)? as u8;
-        let method_id = read_next_vlq(reader)?;
-        let payload_length = read_next_vlq(reader)?;
-        let mut payload = vec![0u8; payload_length as usize];
-        reader
-            .read_exact(&mut payload[..])
-            .map_err(|_| IpcError::ReadExactError)?;
+        let (version, method_id, payload) = read_packet(reader, false)?;
         Ok(RequestPacket {
             version,
             method_id,
@@ -64,12 +57,7 @@ impl Packet for RequestPacket {
         })
     }
     fn serialize(&self) -> Vec<u8> {
-        let mut buf = vec![];
-        buf.extend_from_slice(&vlq_encode(self.version as u64));
-        buf.extend_from_slice(&vlq_encode(self.method_id));
-        buf.extend_from_slice(&vlq_encode(self.payload.len() as u64));
-        buf.extend_from_slice(&self.payload);
-        buf
+        serialize_packet(self.version, self.method_id, &self.payload)
     }
 }
 
@@ -84,6 +72,16 @@ impl RequestPacket {
     pub fn method_id(&self) -> u64 {
         self.method_id
     }
+    /// Like [`Packet::read_from`], but rejects VLQs which are not canonical, see
+    /// [`vlq_decode_canonical`].
+    pub fn read_canonical_from<R: Read>(reader: &mut R) -> Result<Self, IpcError> {
+        let (version, method_id, payload) = read_packet(reader, true)?;
+        Ok(RequestPacket {
+            version,
+            method_id,
+            payload,
+        })
+    }
 }
 
 pub struct ResponsePacket {
@@ -112,13 +110,7 @@ impl Packet for ResponsePacket {
         &self.payload
     }
     fn read_from<R: Read>(reader: &mut R) -> Result<Self, IpcError> {
-        let version = read_next_vlq(reader)? as u8;
-        let error_code = read_next_vlq(reader)?;
-        let payload_length = read_next_vlq(reader)?;
-        let mut payload = vec![0u8; payload_length as usize];
-        reader
-            .read_exact(&mut payload[..])
-            .map_err(|_| IpcError::ReadExactError)?;
+        let (version, error_code, payload) = read_packet(reader, false)?;
         Ok(ResponsePacket {
             version,
             error_code,
@@ -126,12 +118,7 @@ impl Packet for ResponsePacket {
         })
     }
     fn serialize(&self) -> Vec<u8> {
-        let mut buf = vec![];
-        buf.extend_from_slice(&vlq_encode(self.version as u64));
-        buf.extend_from_slice(&vlq_encode(self.error_code));
-        buf.extend_from_slice(&vlq_encode(self.payload.len() as u64));
-        buf.extend_from_slice(&self.payload);
-        buf
+        serialize_packet(self.version, self.error_code, &self.payload)
     }
 }
 
@@ -146,20 +133,590 @@ impl ResponsePacket {
     pub fn error_code(&self) -> u64 {
         self.error_code
     }
+    /// Like [`Packet::read_from`], but rejects VLQs which are not canonical, see
+    /// [`vlq_decode_canonical`].
+    pub fn read_canonical_from<R: Read>(reader: &mut R) -> Result<Self, IpcError> {
+        let (version, error_code, payload) = read_packet(reader, true)?;
+        Ok(ResponsePacket {
+            version,
+            error_code,
+            payload,
+        })
+    }
+}
+
+// Reads the fields shared by requests and responses: version, method id or error code,
+// and payload.
+fn read_packet<R: Read>(reader: &mut R, canonical: bool) -> Result<(u8, u64, Vec<u8>), IpcError> {
+    let version = read_vlq(reader, canonical)? as u8;
+    let id = read_vlq(reader, canonical)?;
+    let payload_length = read_vlq(reader, canonical)?;
+    let payload = read_payload(reader, payload_length)?;
+    Ok((version, id, payload))
+}
+
+fn serialize_packet(version: u8, id: u64, payload: &[u8]) -> Vec<u8> {
+    let mut header = [0u8; MAX_HEADER_LEN];
+    let header_len = encode_header(version, id, payload.len(), &mut header);
+    let mut buf = Vec::with_capacity(header_len + payload.len());
+    buf.extend_from_slice(&header[..header_len]);
+    buf.extend_from_slice(payload);
+    buf
+}
+
+/// Maximum length of a packet header: version, method id or error code, and payload length.
+pub const MAX_HEADER_LEN: usize = 3 * MAX_VLQ_LEN;
+
+/// Encodes the header of a packet into `buf` and returns its length. The payload follows
+/// the header directly.
+pub fn encode_header(
+    version: u8,
+    id: u64,
+    payload_len: usize,
+    buf: &mut [u8; MAX_HEADER_LEN],
+) -> usize {
+    let mut len = 0;
+    let mut vlq = [0u8; MAX_VLQ_LEN];
+    for value in [version as u64, id, payload_len as u64] {
+        let n = vlq_encode_into(value, &mut vlq);
+        buf[len..len + n].copy_from_slice(&vlq[..n]);
+        len += n;
+    }
+    len
+}
+
+/// Writes a packet without building it in memory first: the header is encoded on the
+/// stack, and the payload is written as is. `id` is the method id of a request, or the
+/// error code of a response. The writer is not flushed.
+pub fn write_packet<W: Write>(
+    writer: &mut W,
+    version: u8,
+    id: u64,
+    payload: &[u8],
+) -> Result<(), IpcError> {
+    let mut header = [0u8; MAX_HEADER_LEN];
+    let header_len = encode_header(version, id, payload.len(), &mut header);
+    writer.write_all(&header[..header_len])?;
+    writer.write_all(payload)?;
+    Ok(())
+}
+
+/// Reads a packet from a buffered reader, storing its payload into `payload`. The
+/// allocation of `payload` is reused, so a buffer kept across calls avoids allocating
+/// for every packet. VLQs are decoded from the buffer of the reader directly.
+///
+/// Returns the version and the method id of a request, or the error code of a response.
+/// Non-canonical VLQs are rejected if `canonical` is set.
+pub fn read_packet_into<R: BufRead>(
+    reader: &mut R,
+    canonical: bool,
+    payload: &mut Vec<u8>,
+) -> Result<(u8, u64), IpcError> {
+    let version = read_buffered_vlq(reader, canonical)? as u8;
+    let id = read_buffered_vlq(reader, canonical)?;
+    let payload_length = read_buffered_vlq(reader, canonical)?;
+    read_payload_into(reader, payload_length, payload)?;
+    Ok((version, id))
+}
+
+// Decodes a VLQ from the buffer of `reader` when it is complete there, which is the
+// common case. Otherwise, e.g. when it spans two reads, falls back to reading it byte by byte.
+fn read_buffered_vlq<R: BufRead>(reader: &mut R, canonical: bool) -> Result<u64, IpcError> {
+    let buf = reader
+        .fill_buf()
+        .map_err(|e| IpcError::from_io_error(e, IpcError::ReadVlqError))?;
+    let end = buf.iter().take(MAX_VLQ_LEN).position(|b| b & 0x80 == 0);
+    match end {
+        Some(end) => {
+            let decoded = if canonical {
+                vlq_decode_canonical(&buf[..=end])
+            } else {
+                vlq_decode_prefix(&buf[..=end])
+            };
+            let (value, len) = decoded?;
+            reader.consume(len);
+            Ok(value)
+        }
+        None => read_vlq(reader, canonical),
+    }
+}
+
+// Payloads are read in chunks of this size, see `read_payload`.
+const PAYLOAD_CHUNK_SIZE: usize = 64 * 1024;
+
+// Reads a payload of `len` bytes. The buffer grows with the data actually read instead of
+// being allocated upfront, so a bogus length from the other side can't trigger a huge
+// allocation.
+fn read_payload<R: Read>(reader: &mut R, len: u64) -> Result<Vec<u8>, IpcError> {
+    let mut payload = Vec::new();
+    read_payload_into(reader, len, &mut payload)?;
+    Ok(payload)
+}
+
+// Like `read_payload`, reusing the allocation of `payload`.
+fn read_payload_into<R: Read>(
+    reader: &mut R,
+    len: u64,
+    payload: &mut Vec<u8>,
+) -> Result<(), IpcError> {
+    payload.clear();
+    let mut remaining = len;
+    while remaining > 0 {
+        let n = remaining.min(PAYLOAD_CHUNK_SIZE as u64) as usize;
+        let start = payload.len();
+        payload.resize(start + n, 0);
+        reader
+            .read_exact(&mut payload[start..])
+            .map_err(|e| IpcError::from_io_error(e, IpcError::ReadExactError))?;
+        remaining -= n as u64;
+    }
+    Ok(())
 }
 
 pub fn read_next_vlq(reader: &mut impl Read) -> Result<u64, IpcError> {
-    let mut peek = [0u8; 1];
-    let mut buf = vec![];
-    loop {
-        let n = reader.read(&mut peek).map_err(|_| IpcError::ReadVlqError)?;
+    read_vlq(reader, false)
+}
+
+/// Like [`read_next_vlq`], but rejects VLQs which are not canonical, see
+/// [`vlq_decode_canonical`].
+pub fn read_next_vlq_canonical(reader: &mut impl Read) -> Result<u64, IpcError> {
+    read_vlq(reader, true)
+}
+
+pub(crate) fn read_vlq(reader: &mut impl Read, canonical: bool) -> Result<u64, IpcError> {
+    let mut buf = [0u8; MAX_VLQ_LEN];
+    let mut len = 0;
+    while len < MAX_VLQ_LEN {
+        let n = reader
+            .read(&mut buf[len..len + 1])
+            .map_err(|e| IpcError::from_io_error(e, IpcError::ReadVlqError))?;
         if n == 0 {
             break;
         }
-        buf.push(peek[0]);
-        if peek[0] & 0x80 == 0 {
+        len += 1;
+        if buf[len - 1] & 0x80 == 0 {
             break;
         }
     }
-    vlq_decode(&buf)
+    let decoded = if canonical {
+        vlq_decode_canonical(&buf[..len])
+    } else {
+        vlq_decode_prefix(&buf[..len])
+    };
+    decoded.map(|(value, _)| value)
+}
+
+/// Kind of a packet: requests are sent by clients, responses by servers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PacketKind {
+    Request,
+    Response,
+}
+
+/// A packet decoded by [`inspect`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InspectedPacket {
+    /// Position of the packet in the stream, starting from 0.
+    pub index: usize,
+    pub kind: PacketKind,
+    /// Offset of the first byte of the packet in the stream.
+    pub offset: usize,
+    pub version: u64,
+    /// Method id of a request, or error code of a response.
+    pub id: u64,
+    /// Offset of the first byte of the payload in the stream.
+    pub payload_offset: usize,
+    pub payload: Vec<u8>,
+}
+
+impl InspectedPacket {
+    /// Offset of the first byte after the packet.
+    pub fn end(&self) -> usize {
+        self.payload_offset + self.payload.len()
+    }
+
+    /// Returns the error code of a response, or `None` for requests and unknown codes.
+    pub fn error_code(&self) -> Option<ProtocolErrorCode> {
+        match self.kind {
+            PacketKind::Request => None,
+            PacketKind::Response => ProtocolErrorCode::n(self.id),
+        }
+    }
+}
+
+impl Display for InspectedPacket {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self.kind {
+            PacketKind::Request => writeln!(
+                f,
+                "#{} request, bytes {}..{}",
+                self.index,
+                self.offset,
+                self.end()
+            )?,
+            PacketKind::Response => writeln!(
+                f,
+                "#{} response, bytes {}..{}",
+                self.index,
+                self.offset,
+                self.end()
+            )?,
+        }
+        writeln!(f, "  version: {}", self.version)?;
+        match self.kind {
+            PacketKind::Request => writeln!(f, "  method id: {}", self.id)?,
+            PacketKind::Response => match self.error_code() {
+                Some(code) => writeln!(f, "  error code: {} ({:?})", self.id, code)?,
+                None => writeln!(f, "  error code: {} (unknown)", self.id)?,
+            },
+        }
+        writeln!(f, "  payload length: {}", self.payload.len())?;
+        if self.payload.is_empty() {
+            return Ok(());
+        }
+        let json = serde_json::from_slice::<serde_json::Value>(&self.payload)
+            .ok()
+            .and_then(|value| serde_json::to_string_pretty(&value).ok());
+        match (json, core::str::from_utf8(&self.payload)) {
+            (Some(json), _) => {
+                write!(f, "  payload:")?;
+                for line in json.lines() {
+                    write!(f, "\n    {}", line)?;
+                }
+                writeln!(f)
+            }
+            (None, Ok(text)) => writeln!(f, "  payload (not JSON): {:?}", text),
+            (None, Err(_)) => {
+                write!(f, "  payload (hex): ")?;
+                for byte in &self.payload {
+                    write!(f, "{:02x}", byte)?;
+                }
+                writeln!(f)
+            }
+        }
+    }
+}
+
+/// Where and why [`inspect`] stopped decoding a stream.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InspectError {
+    /// The stream ends in the middle of `field`, which starts at `offset`.
+    /// For the payload, `needed` is its length.
+    Truncated {
+        offset: usize,
+        field: &'static str,
+        available: usize,
+        needed: Option<u64>,
+    },
+    /// `field` at `offset` can't be decoded.
+    Malformed {
+        offset: usize,
+        field: &'static str,
+        reason: &'static str,
+    },
+}
+
+impl Display for InspectError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            InspectError::Truncated {
+                offset,
+                field,
+                available,
+                needed: Some(needed),
+            } => write!(
+                f,
+                "truncated at byte {}: {} needs {} bytes, {} available",
+                offset, field, needed, available
+            ),
+            InspectError::Truncated {
+                offset,
+                field,
+                available,
+                needed: None,
+            } => write!(
+                f,
+                "truncated at byte {}: incomplete {}, {} bytes available",
+                offset, field, available
+            ),
+            InspectError::Malformed {
+                offset,
+                field,
+                reason,
+            } => write!(f, "malformed {} at byte {}: {}", field, offset, reason),
+        }
+    }
+}
+
+/// The result of [`inspect`]: the packets decoded, and the error which stopped decoding.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Inspection {
+    pub packets: Vec<InspectedPacket>,
+    pub error: Option<InspectError>,
+}
+
+impl Display for Inspection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        for packet in &self.packets {
+            write!(f, "{}", packet)?;
+        }
+        match &self.error {
+            Some(error) => writeln!(f, "error: {}", error),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Decodes a raw byte stream into packets of `kind`, e.g. to find out what was written
+/// to a pipe. Decoding stops at the first truncated or malformed packet, which is reported
+/// in [`Inspection::error`] with its exact offset. The `Display` implementation of the
+/// result prints a human readable report, with JSON payloads pretty-printed and error codes
+/// shown as [`ProtocolErrorCode`].
+///
+/// ```rust,ignore
+/// let inspection = inspect(PacketKind::Response, &bytes);
+/// println!("{}", inspection);
+/// ```
+pub fn inspect(kind: PacketKind, bytes: &[u8]) -> Inspection {
+    let id_field = match kind {
+        PacketKind::Request => "method id",
+        PacketKind::Response => "error code",
+    };
+    let mut packets = Vec::new();
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let mut pos = offset;
+        let mut fields = [0u64; 3];
+        for (value, field) in fields
+            .iter_mut()
+            .zip(["version", id_field, "payload length"])
+        {
+            match vlq_decode_prefix(&bytes[pos..]) {
+                Err(IpcError::IncompleteVlqSeq) => {
+                    return Inspection {
+                        packets,
+                        error: Some(InspectError::Truncated {
+                            offset: pos,
+                            field,
+                            available: bytes.len() - pos,
+                            needed: None,
+                        }),
+                    }
+                }
+                Err(_) => {
+                    return Inspection {
+                        packets,
+                        error: Some(InspectError::Malformed {
+                            offset: pos,
+                            field,
+                            reason: "VLQ overflows u64",
+                        }),
+                    }
+                }
+                Ok((v, n)) => {
+                    *value = v;
+                    pos += n;
+                }
+            }
+        }
+        let [version, id, payload_length] = fields;
+        if version > u8::MAX as u64 {
+            return Inspection {
+                packets,
+                error: Some(InspectError::Malformed {
+                    offset,
+                    field: "version",
+                    reason: "version doesn't fit in u8",
+                }),
+            };
+        }
+        let available = bytes.len() - pos;
+        if payload_length > available as u64 {
+            return Inspection {
+                packets,
+                error: Some(InspectError::Truncated {
+                    offset: pos,
+                    field: "payload",
+                    available,
+                    needed: Some(payload_length),
+                }),
+            };
+        }
+        let end = pos + payload_length as usize;
+        packets.push(InspectedPacket {
+            index: packets.len(),
+            kind,
+            offset,
+            version,
+            id,
+            payload_offset: pos,
+            payload: bytes[pos..end].to_vec(),
+        });
+        offset = end;
+    }
+    Inspection {
+        packets,
+        error: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vlq::vlq_encode;
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    #[test]
+    fn test_read_from() {
+        let mut bytes = RequestPacket::new(vec![1; PAYLOAD_CHUNK_SIZE + 1]).serialize();
+        bytes.extend(ResponsePacket::new(100, vec![]).serialize());
+        let mut reader = &bytes[..];
+        let request = RequestPacket::read_from(&mut reader).unwrap();
+        assert_eq!(request.payload(), &vec![1; PAYLOAD_CHUNK_SIZE + 1][..]);
+        let response = ResponsePacket::read_from(&mut reader).unwrap();
+        assert_eq!(response.error_code(), 100);
+        assert!(reader.is_empty());
+
+        // a bogus payload length fails on the missing data instead of allocating it
+        let mut bytes = vec![0, 0];
+        bytes.extend(vlq_encode(u64::MAX));
+        bytes.extend([1, 2, 3]);
+        assert!(matches!(
+            RequestPacket::read_from(&mut &bytes[..]),
+            Err(IpcError::ReadExactError)
+        ));
+    }
+
+    #[test]
+    fn test_read_canonical_from() {
+        // version 0 as [0x80, 0x00]
+        let bytes = [0x80, 0x00, 0x01, 0x01, b'x'];
+        let packet = RequestPacket::read_from(&mut &bytes[..]).unwrap();
+        assert_eq!((packet.method_id(), packet.payload()), (1, &b"x"[..]));
+        assert!(matches!(
+            RequestPacket::read_canonical_from(&mut &bytes[..]),
+            Err(IpcError::NonCanonicalVlq)
+        ));
+        let bytes = ResponsePacket::new(300, b"x".to_vec()).serialize();
+        let packet = ResponsePacket::read_canonical_from(&mut &bytes[..]).unwrap();
+        assert_eq!((packet.error_code(), packet.payload()), (300, &b"x"[..]));
+    }
+
+    #[test]
+    fn test_write_read_packet() {
+        let mut bytes = Vec::new();
+        write_packet(&mut bytes, 0, 300, b"hello").unwrap();
+        write_packet(&mut bytes, 1, 0, &[]).unwrap();
+        write_packet(&mut bytes, 0, u64::MAX, &[7; 200]).unwrap();
+        assert_eq!(
+            &bytes[..9],
+            &ResponsePacket::new(300, b"hello".to_vec()).serialize()[..]
+        );
+
+        // small buffers make VLQs span several reads
+        for capacity in [1, 2, 3, 1024] {
+            let mut reader = crate::io::BufReader::with_capacity(capacity, &bytes[..]);
+            let mut payload = Vec::new();
+            assert_eq!(
+                read_packet_into(&mut reader, true, &mut payload).unwrap(),
+                (0, 300)
+            );
+            assert_eq!(payload, b"hello");
+            assert_eq!(
+                read_packet_into(&mut reader, true, &mut payload).unwrap(),
+                (1, 0)
+            );
+            assert!(payload.is_empty());
+            assert_eq!(
+                read_packet_into(&mut reader, true, &mut payload).unwrap(),
+                (0, u64::MAX)
+            );
+            assert_eq!(payload, [7; 200]);
+            // the allocation is reused
+            let ptr = payload.as_ptr();
+            let mut reader = crate::io::BufReader::with_capacity(capacity, &bytes[..]);
+            read_packet_into(&mut reader, true, &mut payload).unwrap();
+            assert_eq!(payload.as_ptr(), ptr);
+            assert!(matches!(
+                read_packet_into(&mut &[0x80, 0x00, 0x00, 0x00][..], true, &mut payload),
+                Err(IpcError::NonCanonicalVlq)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_inspect() {
+        let mut bytes =
+            RequestPacket::new(b"{\"Hello\":{\"name\":\"world\"}}".to_vec()).serialize();
+        bytes.extend(RequestPacket::new(vec![]).serialize());
+        let inspection = inspect(PacketKind::Request, &bytes);
+        assert_eq!(inspection.error, None);
+        assert_eq!(inspection.packets.len(), 2);
+        assert_eq!(inspection.packets[0].payload_offset, 3);
+        assert_eq!(inspection.packets[1].offset, inspection.packets[0].end());
+        assert_eq!(inspection.packets[1].end(), bytes.len());
+        let report = inspection.to_string();
+        assert!(report.contains("    {\n      \"Hello\": {\n        \"name\": \"world\"\n"));
+
+        let bytes = ResponsePacket::new(27, vec![]).serialize();
+        let inspection = inspect(PacketKind::Response, &bytes);
+        assert!(matches!(
+            inspection.packets[0].error_code(),
+            Some(ProtocolErrorCode::DeserializeError)
+        ));
+        assert!(inspection
+            .to_string()
+            .contains("error code: 27 (DeserializeError)"));
+    }
+
+    #[test]
+    fn test_inspect_errors() {
+        // payload shorter than its length
+        let mut bytes = RequestPacket::new(vec![b'1'; 4]).serialize();
+        bytes.extend_from_slice(&[0, 0, 10, b'1']);
+        let inspection = inspect(PacketKind::Request, &bytes);
+        assert_eq!(inspection.packets.len(), 1);
+        assert_eq!(
+            inspection.error,
+            Some(InspectError::Truncated {
+                offset: 10,
+                field: "payload",
+                available: 1,
+                needed: Some(10),
+            })
+        );
+
+        // incomplete VLQ
+        let inspection = inspect(PacketKind::Response, &[0, 0x80]);
+        assert_eq!(
+            inspection.error,
+            Some(InspectError::Truncated {
+                offset: 1,
+                field: "error code",
+                available: 1,
+                needed: None,
+            })
+        );
+
+        // overlong VLQ
+        let inspection = inspect(
+            PacketKind::Request,
+            &[
+                0, 0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 1,
+            ],
+        );
+        assert_eq!(
+            inspection.error,
+            Some(InspectError::Malformed {
+                offset: 2,
+                field: "payload length",
+                reason: "VLQ overflows u64",
+            })
+        );
+        assert_eq!(
+            inspection.to_string(),
+            "error: malformed payload length at byte 2: VLQ overflows u64\n"
+        );
+    }
 }
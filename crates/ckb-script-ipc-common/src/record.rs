@@ -0,0 +1,491 @@
+//! Recording and replaying of the traffic of a [`Channel`](crate::channel::Channel).
+//!
+//! [`Recorder`] wraps the reader and writer passed to `Channel::new` and splits the bytes
+//! going through them into packets. Every packet is passed to a [`RecordSink`] as a
+//! [`Record`], numbered in the order it was sent or received. Records are kept in memory
+//! with a `Vec<Record>`, or printed through the debug syscall with [`DebugSyscallSink`] in
+//! on-chain debug builds.
+//!
+//! Records print as one line each, e.g. `0 > request 000005...`, and [`parse_transcript`]
+//! reads them back, including from a log with other output mixed in. [`Replay`] then plays
+//! the recorded session against a client or server for deterministic regression tests.
+//!
+//! ```rust,ignore
+//! // record
+//! let recorder = Recorder::new(Role::Client, Vec::new());
+//! let mut client = WorldClient::new(recorder.reader(read_pipe), recorder.writer(write_pipe));
+//! client.hello("world".into());
+//! let records = recorder.with_sink(|records| records.clone());
+//!
+//! // replay the responses to a new client, without a server
+//! let replay = Replay::new(records);
+//! let mut client = WorldClient::new(replay.clone(), replay.clone());
+//! client.hello("world".into());
+//! assert!(replay.is_finished());
+//!
+//! // or replay the requests to a server
+//! let replay = Replay::mirrored(&records);
+//! let _ = Channel::new(replay.clone(), replay.clone()).execute(&mut WorldServer.server());
+//! assert!(replay.is_finished() && replay.mismatch().is_none());
+//! ```
+//!
+//! On-chain, pass [`DebugSyscallSink`] instead of a `Vec` and extract the records from the
+//! debug output with [`parse_transcript`].
+use crate::error::IpcError;
+use crate::io::{Error, ErrorKind, Read, Write};
+pub use crate::packet::PacketKind;
+use crate::packet::{Packet, RequestPacket, ResponsePacket};
+use crate::vlq::vlq_decode_prefix;
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::fmt::{self, Display, Formatter, Write as FmtWrite};
+
+/// Marker of the lines printed by [`DebugSyscallSink`].
+pub const RECORD_MARKER: &str = "ipc-record: ";
+
+/// Direction of a packet, seen from the recorded side.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+impl Direction {
+    fn symbol(self) -> char {
+        match self {
+            Direction::Sent => '>',
+            Direction::Received => '<',
+        }
+    }
+}
+
+/// Role of the recorded side, which tells requests from responses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    Client,
+    Server,
+}
+
+/// A packet sent or received by the recorded side.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Record {
+    /// Position of the packet in the session, starting from 0.
+    pub seq: u64,
+    pub direction: Direction,
+    pub kind: PacketKind,
+    /// The serialized packet, including its header.
+    pub data: Vec<u8>,
+}
+
+impl Record {
+    /// Decodes the packet as a request.
+    pub fn request(&self) -> Result<RequestPacket, IpcError> {
+        RequestPacket::read_from(&mut self.data.as_slice())
+    }
+
+    /// Decodes the packet as a response.
+    pub fn response(&self) -> Result<ResponsePacket, IpcError> {
+        ResponsePacket::read_from(&mut self.data.as_slice())
+    }
+
+    /// Returns the same record seen from the other side.
+    pub fn mirror(&self) -> Self {
+        Self {
+            direction: match self.direction {
+                Direction::Sent => Direction::Received,
+                Direction::Received => Direction::Sent,
+            },
+            ..self.clone()
+        }
+    }
+}
+
+impl Display for Record {
+    /// Formats the record as `<seq> <'>' or '<'> <request or response> <hex data>`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            PacketKind::Request => "request",
+            PacketKind::Response => "response",
+        };
+        write!(f, "{} {} {} ", self.seq, self.direction.symbol(), kind)?;
+        for byte in &self.data {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses the records printed by `Display` for [`Record`], one per line.
+///
+/// If any line contains [`RECORD_MARKER`], only such lines are parsed, from the marker on,
+/// so the output of [`DebugSyscallSink`] can be parsed from a whole log. Empty lines and
+/// lines starting with `#` are skipped. Returns the number of the first invalid line on
+/// error.
+pub fn parse_transcript(transcript: &str) -> Result<Vec<Record>, usize> {
+    let marked = transcript.contains(RECORD_MARKER);
+    let mut records = Vec::new();
+    for (index, line) in transcript.lines().enumerate() {
+        let line = match line.find(RECORD_MARKER) {
+            Some(pos) => &line[pos + RECORD_MARKER.len()..],
+            None if marked => continue,
+            None => line,
+        }
+        .trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        records.push(parse_record(line).ok_or(index + 1)?);
+    }
+    Ok(records)
+}
+
+fn parse_record(line: &str) -> Option<Record> {
+    let mut fields = line.split_ascii_whitespace();
+    let seq = fields.next()?.parse().ok()?;
+    let direction = match fields.next()? {
+        ">" => Direction::Sent,
+        "<" => Direction::Received,
+        _ => return None,
+    };
+    let kind = match fields.next()? {
+        "request" => PacketKind::Request,
+        "response" => PacketKind::Response,
+        _ => return None,
+    };
+    let hex = fields.next().unwrap_or("");
+    if fields.next().is_some() || hex.len() % 2 != 0 {
+        return None;
+    }
+    let data = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    Some(Record {
+        seq,
+        direction,
+        kind,
+        data,
+    })
+}
+
+/// Destination of the records of a [`Recorder`].
+pub trait RecordSink {
+    fn record(&mut self, record: Record);
+}
+
+impl RecordSink for Vec<Record> {
+    fn record(&mut self, record: Record) {
+        self.push(record);
+    }
+}
+
+/// Prints every record through the debug syscall, prefixed with [`RECORD_MARKER`].
+///
+/// Like `ckb_std::debug!`, nothing is printed unless debug assertions are enabled.
+pub struct DebugSyscallSink;
+
+impl RecordSink for DebugSyscallSink {
+    fn record(&mut self, record: Record) {
+        if cfg!(debug_assertions) {
+            let mut line = String::from(RECORD_MARKER);
+            let _ = write!(line, "{}", record);
+            ckb_std::syscalls::debug(line);
+        }
+    }
+}
+
+struct RecorderState<S> {
+    role: Role,
+    sink: S,
+    seq: u64,
+    sent: Vec<u8>,
+    received: Vec<u8>,
+}
+
+impl<S: RecordSink> RecorderState<S> {
+    fn push(&mut self, direction: Direction, bytes: &[u8]) {
+        let pending = match direction {
+            Direction::Sent => &mut self.sent,
+            Direction::Received => &mut self.received,
+        };
+        pending.extend_from_slice(bytes);
+        while let Some(len) = packet_len(pending) {
+            let data: Vec<u8> = pending.drain(..len).collect();
+            let kind = match (self.role, direction) {
+                (Role::Client, Direction::Sent) | (Role::Server, Direction::Received) => {
+                    PacketKind::Request
+                }
+                _ => PacketKind::Response,
+            };
+            let record = Record {
+                seq: self.seq,
+                direction,
+                kind,
+                data,
+            };
+            self.seq += 1;
+            self.sink.record(record);
+        }
+    }
+}
+
+// Returns the length of the first packet in `bytes` once it is complete. Both requests and
+// responses consist of 3 VLQs followed by the payload, whose length is the last VLQ. Bytes
+// which can't be decoded are flushed as a whole, so that they still show up in the record.
+fn packet_len(bytes: &[u8]) -> Option<usize> {
+    let mut pos = 0;
+    let mut value = 0;
+    for _ in 0..3 {
+        match vlq_decode_prefix(&bytes[pos..]) {
+            Ok((v, n)) => {
+                value = v;
+                pos += n;
+            }
+            Err(IpcError::IncompleteVlqSeq) => return None,
+            Err(_) => return Some(bytes.len()),
+        }
+    }
+    let len = usize::try_from(value)
+        .ok()
+        .and_then(|len| len.checked_add(pos));
+    match len {
+        Some(len) if len <= bytes.len() => Some(len),
+        Some(_) => None,
+        None => Some(bytes.len()),
+    }
+}
+
+/// Records the packets going through a reader and writer pair, see the
+/// [module documentation](self).
+///
+/// The recorder is a handle shared by the wrappers it creates, so the sink can be
+/// inspected while the channel is still in use.
+pub struct Recorder<S: RecordSink> {
+    state: Rc<RefCell<RecorderState<S>>>,
+}
+
+impl<S: RecordSink> Clone for Recorder<S> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<S: RecordSink> Recorder<S> {
+    pub fn new(role: Role, sink: S) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(RecorderState {
+                role,
+                sink,
+                seq: 0,
+                sent: Vec::new(),
+                received: Vec::new(),
+            })),
+        }
+    }
+
+    /// Wraps the reader of a channel. Bytes read from it are recorded as received.
+    pub fn reader<R: Read>(&self, inner: R) -> RecordingReader<R, S> {
+        RecordingReader {
+            inner,
+            recorder: self.clone(),
+        }
+    }
+
+    /// Wraps the writer of a channel. Bytes written to it are recorded as sent.
+    pub fn writer<W: Write>(&self, inner: W) -> RecordingWriter<W, S> {
+        RecordingWriter {
+            inner,
+            recorder: self.clone(),
+        }
+    }
+
+    /// Calls `f` with the sink.
+    pub fn with_sink<T>(&self, f: impl FnOnce(&mut S) -> T) -> T {
+        f(&mut self.state.borrow_mut().sink)
+    }
+
+    /// Returns the number of bytes of incomplete packets, which are not recorded yet.
+    pub fn pending_len(&self) -> usize {
+        let state = self.state.borrow();
+        state.sent.len() + state.received.len()
+    }
+}
+
+/// A reader recording the bytes read, created by [`Recorder::reader`].
+pub struct RecordingReader<R: Read, S: RecordSink> {
+    inner: R,
+    recorder: Recorder<S>,
+}
+
+impl<R: Read, S: RecordSink> Read for RecordingReader<R, S> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let n = self.inner.read(buf)?;
+        self.recorder
+            .state
+            .borrow_mut()
+            .push(Direction::Received, &buf[..n]);
+        Ok(n)
+    }
+}
+
+/// A writer recording the bytes written, created by [`Recorder::writer`].
+pub struct RecordingWriter<W: Write, S: RecordSink> {
+    inner: W,
+    recorder: Recorder<S>,
+}
+
+impl<W: Write, S: RecordSink> Write for RecordingWriter<W, S> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        let n = self.inner.write(buf)?;
+        self.recorder
+            .state
+            .borrow_mut()
+            .push(Direction::Sent, &buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.inner.flush()
+    }
+}
+
+struct ReplayState {
+    records: Vec<Record>,
+    // index of the current record and the offset in its data
+    index: usize,
+    offset: usize,
+    mismatch: Option<String>,
+    trailing: Vec<u8>,
+}
+
+impl ReplayState {
+    // Skips the current record once it is fully consumed.
+    fn current(&mut self) -> Option<&Record> {
+        while self
+            .records
+            .get(self.index)
+            .is_some_and(|r| self.offset == r.data.len())
+        {
+            self.index += 1;
+            self.offset = 0;
+        }
+        self.records.get(self.index)
+    }
+
+    fn fail(&mut self, message: String) -> Error {
+        let error = Error::new(ErrorKind::InvalidData, message.clone());
+        self.mismatch.get_or_insert(message);
+        error
+    }
+}
+
+/// Plays a recorded session to a client or server, in place of its peer.
+///
+/// The records are seen from the side under test: reads return the data of the
+/// [`Direction::Received`] records, and writes are checked against the data of the
+/// [`Direction::Sent`] records, in order. Use [`Replay::mirrored`] to replay records taken
+/// on the other side. `Replay` is a shared handle, pass a clone as both the reader and the
+/// writer of a channel.
+///
+/// A write which doesn't match the record, or a read while a write is expected, fails with
+/// an `InvalidData` error. Once all records are replayed, reads return EOF and writes are
+/// discarded, see [`Replay::trailing_written`].
+#[derive(Clone)]
+pub struct Replay {
+    state: Rc<RefCell<ReplayState>>,
+}
+
+impl Replay {
+    pub fn new(records: Vec<Record>) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(ReplayState {
+                records,
+                index: 0,
+                offset: 0,
+                mismatch: None,
+                trailing: Vec::new(),
+            })),
+        }
+    }
+
+    /// Replays records taken on the other side of the channel.
+    pub fn mirrored(records: &[Record]) -> Self {
+        Self::new(records.iter().map(Record::mirror).collect())
+    }
+
+    /// Returns true once every record has been replayed.
+    pub fn is_finished(&self) -> bool {
+        self.state.borrow_mut().current().is_none()
+    }
+
+    /// Returns the first mismatch between the session and the records, if any.
+    pub fn mismatch(&self) -> Option<String> {
+        self.state.borrow().mismatch.clone()
+    }
+
+    /// Returns the bytes written after all records were replayed.
+    pub fn trailing_written(&self) -> Vec<u8> {
+        self.state.borrow().trailing.clone()
+    }
+}
+
+impl Read for Replay {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let mut state = self.state.borrow_mut();
+        let (seq, direction) = match state.current() {
+            None => return Ok(0),
+            Some(record) => (record.seq, record.direction),
+        };
+        if direction == Direction::Sent {
+            return Err(state.fail(format!("record {}: expected a write, got a read", seq)));
+        }
+        let offset = state.offset;
+        let data = &state.records[state.index].data[offset..];
+        let n = data.len().min(buf.len());
+        buf[..n].copy_from_slice(&data[..n]);
+        state.offset += n;
+        Ok(n)
+    }
+}
+
+impl Write for Replay {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        let mut state = self.state.borrow_mut();
+        let (seq, direction) = match state.current() {
+            None => {
+                state.trailing.extend_from_slice(buf);
+                return Ok(buf.len());
+            }
+            Some(record) => (record.seq, record.direction),
+        };
+        if direction == Direction::Received {
+            return Err(state.fail(format!("record {}: expected a read, got a write", seq)));
+        }
+        let offset = state.offset;
+        let expected = &state.records[state.index].data[offset..];
+        let n = expected.len().min(buf.len());
+        let same = expected[..n]
+            .iter()
+            .zip(buf)
+            .take_while(|(a, b)| a == b)
+            .count();
+        if same < n {
+            return Err(state.fail(format!(
+                "record {}: written data differs at byte {}",
+                seq,
+                offset + same
+            )));
+        }
+        state.offset += n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+use alloc::collections::BTreeMap;
+use core::fmt::{self, Display, Formatter};
+
+/// Cycle statistics of a single IPC method.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MethodStats {
+    /// Number of recorded calls.
+    pub calls: u64,
+    /// Sum of the cycles of all recorded calls.
+    pub total_cycles: u64,
+    /// Cycles of the cheapest call.
+    pub min_cycles: u64,
+    /// Cycles of the most expensive call.
+    pub max_cycles: u64,
+}
+
+impl MethodStats {
+    /// Average cycles per call, 0 when nothing is recorded.
+    pub fn avg_cycles(&self) -> u64 {
+        if self.calls == 0 {
+            0
+        } else {
+            self.total_cycles / self.calls
+        }
+    }
+
+    fn record(&mut self, cycles: u64) {
+        if self.calls == 0 {
+            self.min_cycles = cycles;
+            self.max_cycles = cycles;
+        } else {
+            self.min_cycles = self.min_cycles.min(cycles);
+            self.max_cycles = self.max_cycles.max(cycles);
+        }
+        self.calls += 1;
+        self.total_cycles = self.total_cycles.saturating_add(cycles);
+    }
+}
+
+/// A per-method table of cycles spent in IPC calls.
+///
+/// The table is filled by [`Channel::call`](crate::channel::Channel::call) once cycle
+/// statistics are enabled on the channel, or manually through [`CallStats::record`].
+/// Its `Display` implementation renders the table, so it can be dumped at the end
+/// of a run with `ckb_std::debug!("{}", stats)` or `println!("{}", stats)`.
+#[derive(Clone, Debug, Default)]
+pub struct CallStats {
+    methods: BTreeMap<&'static str, MethodStats>,
+}
+
+impl CallStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records one call of `method` which took `cycles` cycles.
+    pub fn record(&mut self, method: &'static str, cycles: u64) {
+        self.methods.entry(method).or_default().record(cycles);
+    }
+
+    /// Returns the statistics of `method`, if it has been recorded.
+    pub fn get(&self, method: &str) -> Option<&MethodStats> {
+        self.methods.get(method)
+    }
+
+    /// Iterates over all recorded methods, ordered by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &MethodStats)> {
+        self.methods.iter().map(|(name, stats)| (*name, stats))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.methods.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.methods.clear();
+    }
+}
+
+impl Display for CallStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let width = self
+            .methods
+            .keys()
+            .map(|name| name.len())
+            .max()
+            .unwrap_or(0)
+            .max("method".len());
+        writeln!(
+            f,
+            "{:<width$} {:>8} {:>14} {:>12} {:>12} {:>12}",
+            "method", "calls", "total", "avg", "min", "max"
+        )?;
+        for (name, stats) in &self.methods {
+            writeln!(
+                f,
+                "{:<width$} {:>8} {:>14} {:>12} {:>12} {:>12}",
+                name,
+                stats.calls,
+                stats.total_cycles,
+                stats.avg_cycles(),
+                stats.min_cycles,
+                stats.max_cycles
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_record() {
+        let mut stats = CallStats::new();
+        stats.record("World.hello", 300);
+        stats.record("World.hello", 100);
+        stats.record("World.hello", 200);
+        stats.record("World.bye", 50);
+
+        let hello = stats.get("World.hello").unwrap();
+        assert_eq!(hello.calls, 3);
+        assert_eq!(hello.total_cycles, 600);
+        assert_eq!(hello.avg_cycles(), 200);
+        assert_eq!(hello.min_cycles, 100);
+        assert_eq!(hello.max_cycles, 300);
+        assert_eq!(stats.get("World.bye").unwrap().calls, 1);
+        assert!(stats.get("World.unknown").is_none());
+    }
+
+    #[test]
+    fn test_display() {
+        let mut stats = CallStats::new();
+        stats.record("World.hello", 100);
+        let table = stats.to_string();
+        let mut lines = table.lines();
+        assert!(lines.next().unwrap().starts_with("method"));
+        assert!(lines.next().unwrap().starts_with("World.hello"));
+        assert!(lines.next().is_none());
+    }
+}
@@ -1,36 +1,73 @@
 use alloc::vec::Vec;
 
 use crate::error::IpcError;
-/// Encodes an integer using VLQ (Variable-Length Quantity) encoding.
-pub fn vlq_encode(mut value: u64) -> Vec<u8> {
-    let mut buffer = Vec::new();
+
+/// Maximum length of a VLQ encoding a `u64`.
+pub const MAX_VLQ_LEN: usize = 10;
+
+/// Encodes an integer using VLQ (Variable-Length Quantity) encoding into `buf`, returning
+/// the number of bytes written. The encoding is canonical: it is the shortest one, without
+/// trailing zero groups.
+pub fn vlq_encode_into(mut value: u64, buf: &mut [u8; MAX_VLQ_LEN]) -> usize {
+    let mut len = 0;
     loop {
         let mut byte = (value & 0x7F) as u8;
         value >>= 7;
         if value != 0 {
             byte |= 0x80;
         }
-        buffer.push(byte);
+        buf[len] = byte;
+        len += 1;
         if value == 0 {
-            break;
+            return len;
         }
     }
-    buffer
+}
+
+/// Encodes an integer using VLQ (Variable-Length Quantity) encoding.
+pub fn vlq_encode(value: u64) -> Vec<u8> {
+    let mut buf = [0u8; MAX_VLQ_LEN];
+    let len = vlq_encode_into(value, &mut buf);
+    buf[..len].to_vec()
 }
 
 /// Decodes a VLQ (Variable-Length Quantity) encoded byte slice into an integer.
+/// Overlong encodings are accepted, see [`vlq_decode_prefix`].
 pub fn vlq_decode(bytes: &[u8]) -> Result<u64, IpcError> {
+    vlq_decode_prefix(bytes).map(|(value, _)| value)
+}
+
+/// Decodes a VLQ at the start of `bytes`, returning the value and the number of bytes used.
+///
+/// Overlong encodings with trailing zero groups, e.g. `[0x80, 0x00]` for 0, are accepted.
+/// Use [`vlq_decode_canonical`] to reject them.
+pub fn vlq_decode_prefix(bytes: &[u8]) -> Result<(u64, usize), IpcError> {
+    decode(bytes, false)
+}
+
+/// Like [`vlq_decode_prefix`], but only accepts the canonical encoding produced by
+/// [`vlq_encode`], so that every value has exactly one byte representation. Overlong
+/// encodings fail with `IpcError::NonCanonicalVlq`.
+pub fn vlq_decode_canonical(bytes: &[u8]) -> Result<(u64, usize), IpcError> {
+    decode(bytes, true)
+}
+
+fn decode(bytes: &[u8], canonical: bool) -> Result<(u64, usize), IpcError> {
     let mut value = 0u64;
-    let mut shift = 0;
-    for &byte in bytes {
-        value |= ((byte & 0x7F) as u64) << shift;
-        if byte & 0x80 == 0 {
-            return Ok(value);
-        }
-        shift += 7;
-        if shift >= 64 {
+    for (i, &byte) in bytes.iter().enumerate() {
+        let shift = 7 * i;
+        let bits = (byte & 0x7F) as u64;
+        // the 10th byte holds the highest bit of a u64, and must be the last one
+        if shift >= 63 && (bits > 1 || byte & 0x80 != 0) {
             return Err(IpcError::DecodeVlqOverflow);
         }
+        value |= bits << shift;
+        if byte & 0x80 == 0 {
+            if canonical && byte == 0 && i > 0 {
+                return Err(IpcError::NonCanonicalVlq);
+            }
+            return Ok((value, i + 1));
+        }
     }
     Err(IpcError::IncompleteVlqSeq)
 }
@@ -85,5 +122,40 @@ mod tests {
             vlq_decode(&[128]),
             Err(IpcError::IncompleteVlqSeq)
         ));
+        // bits beyond u64
+        assert!(matches!(
+            vlq_decode(&[255, 255, 255, 255, 255, 255, 255, 255, 255, 2]),
+            Err(IpcError::DecodeVlqOverflow)
+        ));
+    }
+
+    #[test]
+    fn test_vlq_encode_into() {
+        for value in [0, 1, 127, 128, 16384, u64::MAX] {
+            let mut buf = [0xffu8; MAX_VLQ_LEN];
+            let len = vlq_encode_into(value, &mut buf);
+            assert_eq!(&buf[..len], &vlq_encode(value)[..]);
+            assert_eq!(vlq_decode_canonical(&buf).unwrap(), (value, len));
+        }
+    }
+
+    #[test]
+    fn test_vlq_decode_canonical() {
+        assert_eq!(vlq_decode_prefix(&[0x80, 0x00, 0x05]).unwrap(), (0, 2));
+        assert_eq!(vlq_decode_prefix(&[0x81, 0x80, 0x00]).unwrap(), (1, 3));
+        assert!(matches!(
+            vlq_decode_canonical(&[0x80, 0x00]),
+            Err(IpcError::NonCanonicalVlq)
+        ));
+        assert!(matches!(
+            vlq_decode_canonical(&[0x81, 0x80, 0x00]),
+            Err(IpcError::NonCanonicalVlq)
+        ));
+        assert_eq!(vlq_decode_canonical(&[0x00, 0x00]).unwrap(), (0, 1));
+        assert_eq!(vlq_decode_canonical(&[0x80, 0x01]).unwrap(), (128, 2));
+        assert!(matches!(
+            vlq_decode_canonical(&[0x80]),
+            Err(IpcError::IncompleteVlqSeq)
+        ));
     }
 }
@@ -0,0 +1,603 @@
+//! Generates a C header from a service definition, see `ServiceGenerator::c_header`.
+//!
+//! Payloads are encoded as JSON with the helpers of `c/ckb_script_ipc.h`, exactly like serde_json encodes the
+//! generated request and response enums, so C and Rust scripts can call each other.
+
+use quote::ToTokens;
+use syn::{GenericArgument, PathArguments, Type};
+
+/// A Rust type as seen from C.
+enum CType {
+    Unit,
+    Bool,
+    Uint {
+        c: &'static str,
+        max: &'static str,
+    },
+    Int {
+        c: &'static str,
+        min: &'static str,
+        max: &'static str,
+    },
+    Bytes,
+    Str,
+    /// Any type without a C counterpart, e.g. structs, enums and maps, kept as JSON text.
+    Raw,
+    Option(Box<CType>),
+    /// Only for returned types.
+    Result(Box<CType>, Box<CType>),
+}
+
+impl CType {
+    fn from_type(ty: &Type, returned: bool) -> Self {
+        match ty {
+            Type::Tuple(tuple) if tuple.elems.is_empty() => CType::Unit,
+            Type::Paren(paren) => CType::from_type(&paren.elem, returned),
+            Type::Group(group) => CType::from_type(&group.elem, returned),
+            Type::Array(array) if is_u8(&array.elem) => CType::Bytes,
+            Type::Reference(reference) => match &*reference.elem {
+                Type::Slice(slice) if is_u8(&slice.elem) => CType::Bytes,
+                Type::Path(path) if path.path.is_ident("str") => CType::Str,
+                _ => CType::Raw,
+            },
+            Type::Path(path) if path.qself.is_none() => {
+                let Some(segment) = path.path.segments.last() else {
+                    return CType::Raw;
+                };
+                let generics: Vec<&Type> = match &segment.arguments {
+                    PathArguments::AngleBracketed(args) => args
+                        .args
+                        .iter()
+                        .filter_map(|arg| match arg {
+                            GenericArgument::Type(ty) => Some(ty),
+                            _ => None,
+                        })
+                        .collect(),
+                    _ => Vec::new(),
+                };
+                match (segment.ident.to_string().as_str(), generics.as_slice()) {
+                    ("bool", []) => CType::Bool,
+                    ("u8", []) => CType::Uint {
+                        c: "uint8_t",
+                        max: "UINT8_MAX",
+                    },
+                    ("u16", []) => CType::Uint {
+                        c: "uint16_t",
+                        max: "UINT16_MAX",
+                    },
+                    ("u32", []) => CType::Uint {
+                        c: "uint32_t",
+                        max: "UINT32_MAX",
+                    },
+                    ("u64" | "usize", []) => CType::Uint {
+                        c: "uint64_t",
+                        max: "UINT64_MAX",
+                    },
+                    ("i8", []) => CType::Int {
+                        c: "int8_t",
+                        min: "INT8_MIN",
+                        max: "INT8_MAX",
+                    },
+                    ("i16", []) => CType::Int {
+                        c: "int16_t",
+                        min: "INT16_MIN",
+                        max: "INT16_MAX",
+                    },
+                    ("i32", []) => CType::Int {
+                        c: "int32_t",
+                        min: "INT32_MIN",
+                        max: "INT32_MAX",
+                    },
+                    ("i64" | "isize", []) => CType::Int {
+                        c: "int64_t",
+                        min: "INT64_MIN",
+                        max: "INT64_MAX",
+                    },
+                    ("String" | "str", []) => CType::Str,
+                    ("Vec", [elem]) if is_u8(elem) => CType::Bytes,
+                    ("Option", [inner]) => match CType::from_type(inner, false) {
+                        // `None`, `Some(())` and `Some(None)` can't be told apart in C
+                        CType::Unit | CType::Option(_) => CType::Raw,
+                        inner => CType::Option(Box::new(inner)),
+                    },
+                    ("Result", [ok, err]) if returned => CType::Result(
+                        Box::new(CType::from_type(ok, false)),
+                        Box::new(CType::from_type(err, false)),
+                    ),
+                    _ => CType::Raw,
+                }
+            }
+            _ => CType::Raw,
+        }
+    }
+
+    fn c_type(&self) -> &'static str {
+        match self {
+            CType::Bool => "bool",
+            CType::Uint { c, .. } | CType::Int { c, .. } => c,
+            CType::Bytes => "CSIBytes",
+            CType::Str => "CSIString",
+            _ => "CSIRawJson",
+        }
+    }
+
+    /// Declarations of the struct fields holding a value named `name`.
+    fn fields(&self, name: &str, out: &mut Vec<String>) {
+        match self {
+            CType::Unit => {}
+            CType::Option(inner) => {
+                out.push(format!("bool has_{name};"));
+                inner.fields(name, out);
+            }
+            CType::Result(ok, err) => {
+                out.push("bool is_ok;".into());
+                ok.fields("ok", out);
+                err.fields("err", out);
+            }
+            _ => out.push(format!("{} {name};", self.c_type())),
+        }
+    }
+
+    /// Statements writing the value `{prefix}{name}` to `writer`, a pointer.
+    fn encode(&self, writer: &str, prefix: &str, name: &str, out: &mut Lines) {
+        let value = format!("{prefix}{name}");
+        match self {
+            CType::Unit => out.push(format!("csi_json_write_null({writer});")),
+            CType::Bool => out.push(format!("csi_json_write_bool({writer}, {value});")),
+            CType::Uint { .. } => out.push(format!("csi_json_write_uint({writer}, {value});")),
+            CType::Int { .. } => out.push(format!("csi_json_write_int({writer}, {value});")),
+            CType::Bytes => out.push(format!(
+                "csi_json_write_bytes({writer}, {value}.ptr, {value}.len);"
+            )),
+            CType::Str => out.push(format!(
+                "csi_json_write_string({writer}, {value}.ptr, {value}.len);"
+            )),
+            CType::Raw => out.push(format!(
+                "csi_json_write_raw({writer}, {value}.ptr, {value}.len);"
+            )),
+            CType::Option(inner) => {
+                out.open(format!("if ({prefix}has_{name}) {{"));
+                inner.encode(writer, prefix, name, out);
+                out.reopen("} else {");
+                out.push(format!("csi_json_write_null({writer});"));
+                out.close("}");
+            }
+            CType::Result(ok, err) => {
+                out.push(format!("csi_json_begin_object({writer});"));
+                out.open(format!("if ({prefix}is_ok) {{"));
+                out.push(format!("csi_json_write_key({writer}, \"Ok\");"));
+                ok.encode(writer, prefix, "ok", out);
+                out.reopen("} else {");
+                out.push(format!("csi_json_write_key({writer}, \"Err\");"));
+                err.encode(writer, prefix, "err", out);
+                out.close("}");
+                out.push(format!("csi_json_end_object({writer});"));
+            }
+        }
+    }
+
+    /// Statements reading the value `{prefix}{name}` from `reader`, a `CSIJsonReader`, when `cond` holds. Errors
+    /// are stored in `err`.
+    fn decode(&self, cond: &str, prefix: &str, name: &str, out: &mut Lines) {
+        let value = format!("{prefix}{name}");
+        match self {
+            CType::Unit => out.push(format!("if ({cond}) err = csi_json_read_unit(&reader);")),
+            CType::Bool => out.push(format!(
+                "if ({cond}) err = csi_json_read_bool(&reader, &{value});"
+            )),
+            CType::Uint { c: "uint64_t", .. } => out.push(format!(
+                "if ({cond}) err = csi_json_read_uint(&reader, &{value});"
+            )),
+            CType::Int { c: "int64_t", .. } => out.push(format!(
+                "if ({cond}) err = csi_json_read_int(&reader, &{value});"
+            )),
+            CType::Uint { c, max } => {
+                out.open(format!("if ({cond}) {{"));
+                out.push("uint64_t value = 0;");
+                out.push(format!(
+                    "err = csi_json_read_uint_max(&reader, {max}, &value);"
+                ));
+                out.push(format!("{value} = ({c})value;"));
+                out.close("}");
+            }
+            CType::Int { c, min, max } => {
+                out.open(format!("if ({cond}) {{"));
+                out.push("int64_t value = 0;");
+                out.push(format!(
+                    "err = csi_json_read_int_range(&reader, {min}, {max}, &value);"
+                ));
+                out.push(format!("{value} = ({c})value;"));
+                out.close("}");
+            }
+            CType::Bytes => out.push(format!(
+                "if ({cond}) err = csi_json_read_bytes_in(&reader, scratch, &{value});"
+            )),
+            CType::Str => out.push(format!(
+                "if ({cond}) err = csi_json_read_string_in(&reader, scratch, &{value});"
+            )),
+            CType::Raw => out.push(format!(
+                "if ({cond}) err = csi_json_read_raw_in(&reader, scratch, &{value});"
+            )),
+            CType::Option(inner) => {
+                out.open(format!("if ({cond}) {{"));
+                out.push("bool is_null = false;");
+                out.push("err = csi_json_read_null(&reader, &is_null);");
+                out.push(format!("{prefix}has_{name} = !is_null;"));
+                out.close("}");
+                inner.decode(&format!("{cond} && {prefix}has_{name}"), prefix, name, out);
+            }
+            CType::Result(ok, err) => {
+                out.open(format!("if ({cond}) {{"));
+                out.push("const char* variant = NULL;");
+                out.push("size_t variant_len = 0;");
+                out.push("err = csi_json_read_begin_envelope(&reader, &variant, &variant_len);");
+                out.push(format!(
+                    "{prefix}is_ok = err == 0 && csi_json_key_equals(variant, variant_len, \"Ok\");"
+                ));
+                out.push(format!(
+                    "if (err == 0 && !{prefix}is_ok && !csi_json_key_equals(variant, variant_len, \"Err\")) err = CSI_ERROR_JSON;"
+                ));
+                out.close("}");
+                ok.decode(&format!("{cond} && {prefix}is_ok"), prefix, "ok", out);
+                err.decode(&format!("{cond} && !{prefix}is_ok"), prefix, "err", out);
+                out.push(format!(
+                    "if ({cond}) err = csi_json_read_end_object(&reader);"
+                ));
+            }
+        }
+    }
+}
+
+fn is_u8(ty: &Type) -> bool {
+    matches!(ty, Type::Path(path) if path.qself.is_none() && path.path.is_ident("u8"))
+}
+
+/// Indented lines of C code.
+struct Lines {
+    lines: Vec<String>,
+    indent: usize,
+}
+
+impl Lines {
+    fn new(indent: usize) -> Self {
+        Self {
+            lines: Vec::new(),
+            indent,
+        }
+    }
+
+    fn push(&mut self, line: impl AsRef<str>) {
+        self.lines
+            .push(format!("{}{}\n", "    ".repeat(self.indent), line.as_ref()));
+    }
+
+    fn open(&mut self, line: impl AsRef<str>) {
+        self.push(line);
+        self.indent += 1;
+    }
+
+    fn reopen(&mut self, line: impl AsRef<str>) {
+        self.indent -= 1;
+        self.open(line);
+    }
+
+    fn close(&mut self, line: impl AsRef<str>) {
+        self.indent -= 1;
+        self.push(line);
+    }
+
+    fn into_string(self) -> String {
+        self.lines.concat()
+    }
+}
+
+/// A method of a service.
+pub(crate) struct CMethod<'a> {
+    /// Name in snake case, e.g. `hasher_new`.
+    pub name: String,
+    /// Name in the request and response enums, e.g. `HasherNew`.
+    pub variant: String,
+    pub args: Vec<(String, &'a Type)>,
+    pub output: &'a Type,
+}
+
+/// Converts `CkbCrypto` into `ckb_crypto`.
+pub(crate) fn camel_to_snake(ident: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in ident.chars().enumerate() {
+        if c.is_uppercase() && i > 0 && !snake.ends_with('_') {
+            snake.push('_');
+        }
+        snake.extend(c.to_lowercase());
+    }
+    snake
+}
+
+// Formats a type like it's written in Rust, for comments.
+fn type_name(ty: &Type) -> String {
+    ty.to_token_stream()
+        .to_string()
+        .replace(" < ", "<")
+        .replace(" <", "<")
+        .replace(" >", ">")
+        .replace(" ,", ",")
+        .replace(" :: ", "::")
+        .replace("& ", "&")
+        .replace(" ;", ";")
+}
+
+/// Generates the C header of service `service`, e.g. `CkbCrypto`.
+pub(crate) fn c_header(service: &str, methods: &[CMethod]) -> String {
+    let prefix = camel_to_snake(service);
+    let upper = prefix.to_uppercase();
+    let mut out = String::new();
+    out += &format!(
+        "// Generated from the `{service}` service by `#[ckb_script_ipc::service]`, do not edit.\n"
+    );
+    out += &format!("#ifndef __{upper}_IPC_H__\n#define __{upper}_IPC_H__\n");
+    out += "#include \"ckb_script_ipc.h\"\n\n";
+
+    out += "// Method IDs, sent in requests. Rust servers tell methods apart by their names in payloads instead.\n";
+    out += "// IDs follow the declaration order of the methods, so new methods must be appended to the service.\n";
+    for (id, method) in methods.iter().enumerate() {
+        out += &format!("#define {upper}_{} {id}\n", method.name.to_uppercase());
+    }
+
+    struct Method<'a> {
+        method: &'a CMethod<'a>,
+        args: Vec<(&'a str, CType)>,
+        output: CType,
+        request: Option<String>,
+        response: Option<String>,
+    }
+    let methods: Vec<Method> = methods
+        .iter()
+        .map(|method| {
+            let args: Vec<_> = method
+                .args
+                .iter()
+                .map(|(name, ty)| (name.as_str(), CType::from_type(ty, false)))
+                .collect();
+            let output = CType::from_type(method.output, true);
+            let mut request_fields = Vec::new();
+            for (name, ty) in &args {
+                ty.fields(name, &mut request_fields);
+            }
+            let mut response_fields = Vec::new();
+            output.fields("ret", &mut response_fields);
+            Method {
+                method,
+                args,
+                output,
+                request: (!request_fields.is_empty())
+                    .then(|| format!("{service}{}Request", method.variant)),
+                response: (!response_fields.is_empty())
+                    .then(|| format!("{service}{}Response", method.variant)),
+            }
+        })
+        .collect();
+
+    // structs
+    for method in &methods {
+        let args = method
+            .method
+            .args
+            .iter()
+            .map(|(name, ty)| format!("{name}: {}", type_name(ty)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        out += &format!(
+            "\n// {}({args}) -> {}\n",
+            method.method.name,
+            type_name(method.method.output)
+        );
+        if let Some(request) = &method.request {
+            out += &format!("typedef struct {request} {{\n");
+            for (name, ty) in &method.args {
+                let mut fields = Vec::new();
+                ty.fields(name, &mut fields);
+                for field in fields {
+                    out += &format!("    {field}\n");
+                }
+            }
+            out += &format!("}} {request};\n");
+        }
+        if let Some(response) = &method.response {
+            let mut fields = Vec::new();
+            method.output.fields("ret", &mut fields);
+            out += &format!("typedef struct {response} {{\n");
+            for field in fields {
+                out += &format!("    {field}\n");
+            }
+            out += &format!("}} {response};\n");
+        }
+    }
+
+    // client
+    out += "\n// Client side. Each call encodes its request into `scratch`, and then decodes the bytes, strings and\n";
+    out += "// raw JSON of its response into `scratch`. They stay valid until `scratch` is used by another call.\n";
+    for method in &methods {
+        let name = &method.method.name;
+        let variant = &method.method.variant;
+        let mut params = vec!["CSIChannel* channel".to_string()];
+        if let Some(request) = &method.request {
+            params.push(format!("const {request}* request"));
+        }
+        if let Some(response) = &method.response {
+            params.push(format!("{response}* response"));
+        }
+        params.push("CSIScratch* scratch".into());
+        out += &signature(&format!("static inline int {prefix}_{name}"), &params, " {");
+        let mut body = Lines::new(1);
+        body.push("int err = 0;");
+        body.push("CSIJsonWriter writer;");
+        body.push("CSIJsonReader reader;");
+        body.push("CSIResponsePacket packet = {0};");
+        body.push("scratch->pos = 0;");
+        body.push("csi_json_writer_init(&writer, scratch->buf, scratch->len);");
+        body.push(format!("csi_json_begin_request(&writer, \"{variant}\");"));
+        for (arg, ty) in &method.args {
+            body.push(format!("csi_json_write_key(&writer, \"{arg}\");"));
+            ty.encode("&writer", "request->", arg, &mut body);
+        }
+        body.push("csi_json_end_request(&writer);");
+        body.push(format!(
+            "err = csi_json_call(channel, {upper}_{}, &writer, \"{variant}\", &packet, &reader);",
+            name.to_uppercase()
+        ));
+        method
+            .output
+            .decode("err == 0", "response->", "ret", &mut body);
+        body.push("if (err == 0) err = csi_json_read_end_envelope(&reader);");
+        body.push("csi_client_free_response_payload(&packet);");
+        body.push("return err;");
+        out += &body.into_string();
+        out += "}\n\n";
+    }
+
+    // server
+    out += "// Server side. Implement these handlers, and dispatch requests from the callback of csi_run_server:\n";
+    out +=
+        "//     static int serve(const CSIRequestPacket* request, CSIResponsePacket* response) {\n";
+    out += "//         static uint8_t buf[4096];\n";
+    out += "//         CSIScratch scratch = {buf, sizeof(buf), 0};\n";
+    out += &format!("//         return {prefix}_dispatch(request, response, &scratch);\n");
+    out += "//     }\n";
+    out += "// The bytes, strings and raw JSON of requests are stored in `scratch`, and handlers may allocate those of\n";
+    out += "// their responses from it with csi_scratch_alloc.\n";
+    for method in &methods {
+        let params = handler_params(method.request.as_deref(), method.response.as_deref());
+        out += &signature(
+            &format!("int {prefix}_serve_{}", method.method.name),
+            &params,
+            ";",
+        );
+    }
+    out += "\n";
+    let params = [
+        "const CSIRequestPacket* request".to_string(),
+        "CSIResponsePacket* response".into(),
+        "CSIScratch* scratch".into(),
+    ];
+    out += &signature(
+        &format!("static inline int {prefix}_dispatch"),
+        &params,
+        " {",
+    );
+    let mut body = Lines::new(1);
+    body.push("int err = 0;");
+    body.push("CSIJsonReader reader;");
+    body.push("CSIJsonWriter writer;");
+    body.push("const char* method = NULL;");
+    body.push("size_t method_len = 0;");
+    body.push("scratch->pos = 0;");
+    body.push("csi_json_reader_init(&reader, request->payload, request->payload_len);");
+    body.push("err = csi_json_read_begin_envelope(&reader, &method, &method_len);");
+    body.push("if (err == 0) err = csi_json_read_begin_object(&reader);");
+    body.push("if (err) return err;");
+    for method in &methods {
+        body.open(format!(
+            "if (csi_json_key_equals(method, method_len, \"{}\")) {{",
+            method.method.variant
+        ));
+        if let Some(request) = &method.request {
+            body.push(format!("{request} req = {{0}};"));
+        }
+        if let Some(response) = &method.response {
+            body.push(format!("{response} resp = {{0}};"));
+        }
+        body.open("while (err == 0) {");
+        body.push("const char* key = NULL;");
+        body.push("size_t key_len = 0;");
+        body.push("bool end = false;");
+        body.push("err = csi_json_read_key(&reader, &key, &key_len, &end);");
+        body.push("if (err || end) break;");
+        for (i, (arg, ty)) in method.args.iter().enumerate() {
+            let test = format!("csi_json_key_equals(key, key_len, \"{arg}\")");
+            if i == 0 {
+                body.open(format!("if ({test}) {{"));
+            } else {
+                body.reopen(format!("}} else if ({test}) {{"));
+            }
+            ty.decode("err == 0", "req.", arg, &mut body);
+        }
+        if method.args.is_empty() {
+            body.push("err = csi_json_skip_value(&reader);");
+        } else {
+            body.reopen("} else {");
+            body.push("err = csi_json_skip_value(&reader);");
+            body.close("}");
+        }
+        body.close("}");
+        body.push("if (err == 0) err = csi_json_read_end_envelope(&reader);");
+        let mut args = Vec::new();
+        if method.request.is_some() {
+            args.push("&req");
+        }
+        if method.response.is_some() {
+            args.push("&resp");
+        }
+        args.push("scratch");
+        body.push(format!(
+            "if (err == 0) err = {prefix}_serve_{}({});",
+            method.method.name,
+            args.join(", ")
+        ));
+        body.open("if (err == 0) {");
+        body.push("csi_json_writer_init(&writer, scratch->buf + scratch->pos, scratch->len - scratch->pos);");
+        body.push(format!(
+            "csi_json_begin_response(&writer, \"{}\");",
+            method.method.variant
+        ));
+        method.output.encode("&writer", "resp.", "ret", &mut body);
+        body.push("csi_json_end_response(&writer);");
+        body.push("err = csi_json_set_response_payload(&writer, response);");
+        body.close("}");
+        body.push("return err;");
+        body.close("}");
+    }
+    body.push("response->error_code = CSI_UNKNOWN_METHOD;");
+    body.push("return 0;");
+    out += &body.into_string();
+    out += "}\n\n#endif\n";
+    out
+}
+
+/// Formats a function signature like clang-format does with the style of `c/Makefile`, wrapping parameters
+/// which go beyond 120 columns.
+fn signature(head: &str, params: &[String], end: &str) -> String {
+    let indent = " ".repeat(head.len() + 1);
+    let mut out = format!("{head}(");
+    let mut line_len = out.len();
+    for (i, param) in params.iter().enumerate() {
+        let sep = if i + 1 == params.len() { ")" } else { "," };
+        let tail = if i + 1 == params.len() { end.len() } else { 0 };
+        if i > 0 {
+            if line_len + 1 + param.len() + sep.len() + tail > 120 {
+                out += &format!("\n{indent}");
+                line_len = indent.len();
+            } else {
+                out.push(' ');
+                line_len += 1;
+            }
+        }
+        out += param;
+        out += sep;
+        line_len += param.len() + sep.len();
+    }
+    out += end;
+    out += "\n";
+    out
+}
+
+fn handler_params(request: Option<&str>, response: Option<&str>) -> Vec<String> {
+    let mut params = Vec::new();
+    if let Some(request) = request {
+        params.push(format!("const {request}* request"));
+    }
+    if let Some(response) = response {
+        params.push(format!("{response}* response"));
+    }
+    params.push("CSIScratch* scratch".into());
+    params
+}
@@ -4,6 +4,8 @@ extern crate proc_macro2;
 extern crate quote;
 extern crate syn;
 
+mod c_header;
+
 use proc_macro::TokenStream;
 use proc_macro2::TokenStream as TokenStream2;
 use quote::{format_ident, quote, ToTokens};
@@ -55,14 +57,19 @@ impl Parse for Service {
         }
         let mut ident_errors = Ok(());
         for ipc in &ipcs {
-            if ipc.ident == "new" {
+            if ipc.ident == "new"
+                || ipc.ident == "with_capacity"
+                || ipc.ident == "channel"
+                || ipc.ident == "channel_mut"
+            {
                 extend_errors!(
                     ident_errors,
                     syn::Error::new(
                         ipc.ident.span(),
                         format!(
-                            "method name conflicts with generated fn `{}Client::new`",
-                            ident.unraw()
+                            "method name conflicts with generated fn `{}Client::{}`",
+                            ident.unraw(),
+                            ipc.ident
                         )
                     )
                 );
@@ -148,6 +155,22 @@ fn collect_cfg_attrs(ipcs: &[IpcMethod]) -> Vec<Vec<&Attribute>> {
         .collect::<Vec<_>>()
 }
 
+/// Generates an IPC client and server for a trait, see the crate documentation.
+///
+/// Methods can't be named after the functions generated on the client (`new`,
+/// `with_capacity`, `channel` and `channel_mut`) or on the trait (`serve`):
+/// ```compile_fail
+/// #[ckb_script_ipc::service]
+/// pub trait World {
+///     fn with_capacity(size: u64) -> u64;
+/// }
+/// ```
+/// ```
+/// #[ckb_script_ipc::service]
+/// pub trait World {
+///     fn capacity(size: u64) -> u64;
+/// }
+/// ```
 #[proc_macro_attribute]
 pub fn service(_attr: TokenStream, input: TokenStream) -> TokenStream {
     let unit_type: &Type = &parse_quote!(());
@@ -381,6 +404,29 @@ impl ServiceGenerator<'_> {
                     let channel = ckb_script_ipc_common::channel::Channel::new(reader, writer);
                     Self { channel }
                 }
+
+                #vis fn with_capacity(
+                    reader: R,
+                    writer: W,
+                    read_capacity: usize,
+                    write_capacity: usize,
+                ) -> Self {
+                    let channel = ckb_script_ipc_common::channel::Channel::with_capacity(
+                        reader,
+                        writer,
+                        read_capacity,
+                        write_capacity,
+                    );
+                    Self { channel }
+                }
+
+                #vis fn channel(&self) -> &ckb_script_ipc_common::channel::Channel<R, W> {
+                    &self.channel
+                }
+
+                #vis fn channel_mut(&mut self) -> &mut ckb_script_ipc_common::channel::Channel<R, W> {
+                    &mut self.channel
+                }
             }
         }
     }
@@ -431,6 +477,57 @@ impl ServiceGenerator<'_> {
     }
 }
 
+impl ServiceGenerator<'_> {
+    fn const_c_header(&self) -> TokenStream2 {
+        let &Self {
+            service_ident,
+            vis,
+            ipcs,
+            args,
+            return_types,
+            camel_case_idents,
+            ..
+        } = self;
+
+        let methods = ipcs
+            .iter()
+            .zip(args.iter())
+            .zip(return_types.iter())
+            .zip(camel_case_idents.iter())
+            .map(|(((ipc, args), output), variant)| c_header::CMethod {
+                name: ipc.ident.unraw().to_string(),
+                variant: variant.to_string(),
+                args: args
+                    .iter()
+                    .map(|arg| {
+                        let name = match &*arg.pat {
+                            Pat::Ident(pat) => pat.ident.unraw().to_string(),
+                            pat => pat.to_token_stream().to_string(),
+                        };
+                        (name, &*arg.ty)
+                    })
+                    .collect(),
+                output,
+            })
+            .collect::<Vec<_>>();
+        let service = service_ident.unraw().to_string();
+        let header = c_header::c_header(&service, &methods);
+        let const_ident = format_ident!(
+            "{}_C_HEADER",
+            c_header::camel_to_snake(&service).to_uppercase()
+        );
+        let doc = format!(
+            "C header for calling and serving `{service}` from C scripts, on top of `c/ckb_script_ipc.h`."
+        );
+
+        quote! {
+            #[doc = #doc]
+            #[allow(dead_code)]
+            #vis const #const_ident: &str = #header;
+        }
+    }
+}
+
 impl ToTokens for ServiceGenerator<'_> {
     fn to_tokens(&self, output: &mut TokenStream2) {
         output.extend(vec![
@@ -442,6 +539,7 @@ impl ToTokens for ServiceGenerator<'_> {
             self.struct_client(),
             self.impl_client_new(),
             self.impl_client_ipc_methods(),
+            self.const_c_header(),
         ]);
     }
 }
@@ -452,3 +550,28 @@ fn snake_to_camel(ident_str: &str) -> String {
         .map(|word| word[..1].to_uppercase() + &word[1..].to_lowercase())
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reserved_method_names() {
+        for name in ["new", "with_capacity", "channel", "channel_mut"] {
+            let input = format!("pub trait World {{ fn {name}(size: u64) -> u64; }}");
+            let err = syn::parse_str::<Service>(&input).err().unwrap();
+            assert_eq!(
+                err.to_string(),
+                format!("method name conflicts with generated fn `WorldClient::{name}`")
+            );
+        }
+        let err = syn::parse_str::<Service>("pub trait World { fn serve(); }")
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "method name conflicts with generated fn `World::serve`"
+        );
+        assert!(syn::parse_str::<Service>("pub trait World { fn capacity(); }").is_ok());
+    }
+}
@@ -48,3 +48,36 @@ pub trait SerdeMolecule {
     fn f1_func(bytes: Vec<u8>, name: String, tests: [u8; 20]) -> Result<String, u64>;
     fn f2_func(s1: Struct1) -> Result<String, u64>;
 }
+
+#[ckb_script_ipc::service]
+pub trait Headers {
+    fn lookup(key: Option<u16>, raw: BTreeMap<u32, String>) -> Result<Option<i32>, String>;
+    fn ping();
+}
+
+#[test]
+fn test_c_header() {
+    let header = HEADERS_C_HEADER;
+    assert!(header.contains("#define HEADERS_LOOKUP 0\n#define HEADERS_PING 1\n"));
+    assert!(header.contains(
+        "typedef struct HeadersLookupRequest {\n    bool has_key;\n    uint16_t key;\n    CSIRawJson raw;\n}"
+    ));
+    assert!(header.contains(
+        "typedef struct HeadersLookupResponse {\n    bool is_ok;\n    bool has_ok;\n    int32_t ok;\n    CSIString err;\n}"
+    ));
+    // methods without arguments nor return values have no structs
+    assert!(!header.contains("HeadersPingRequest"));
+    assert!(!header.contains("HeadersPingResponse"));
+    assert!(header
+        .contains("static inline int headers_ping(CSIChannel* channel, CSIScratch* scratch) {"));
+    assert!(header.contains("int headers_serve_ping(CSIScratch* scratch);"));
+    assert!(header.contains("if (err == 0 && response->is_ok && response->has_ok) {"));
+    assert_eq!(
+        SERDE_MOLECULE_C_HEADER.matches("CSIBytes bytes;").count(),
+        1
+    );
+    assert_eq!(
+        SERDE_MOLECULE_C_HEADER.matches("CSIBytes tests;").count(),
+        1
+    );
+}
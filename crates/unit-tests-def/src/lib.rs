@@ -52,4 +52,6 @@ pub trait UnitTests {
     fn test_complex_types(arg1: Struct1);
     fn test_return_types() -> Result<u32, String>;
     fn test_large_input_output(input: Vec<u8>) -> Vec<u8>;
+    fn test_debug_print(line: String);
+    fn test_echo_integers(signed: i128, unsigned: u128) -> (i128, u128);
 }
@@ -0,0 +1,27 @@
+fn main() {
+    println!("cargo:rerun-if-changed=../c/ckb_script_ipc.c");
+    println!("cargo:rerun-if-changed=../c/ckb_script_ipc.h");
+    println!("cargo:rerun-if-changed=../c/native");
+    println!("cargo:rerun-if-changed=c/harness.c");
+
+    let mut build = cc::Build::new();
+    build
+        .file("../c/ckb_script_ipc.c")
+        .file("../c/native/ckb_syscalls_native.c")
+        .file("c/harness.c")
+        .include("../c/native")
+        .include("../c")
+        // silences the diagnostics printed by the library on malformed input
+        .define("printf", "csi_fuzz_printf")
+        .warnings(false);
+    // `cargo fuzz` builds with `--cfg fuzzing`. The C code only gets coverage feedback and
+    // sanitizers when it's built by clang, e.g. with `CC=clang`.
+    if std::env::var_os("CARGO_CFG_FUZZING").is_some() && build.get_compiler().is_like_clang() {
+        build.flag("-fsanitize=fuzzer-no-link");
+        let rustflags = std::env::var("CARGO_ENCODED_RUSTFLAGS").unwrap_or_default();
+        if rustflags.contains("sanitizer=address") {
+            build.flag("-fsanitize=address");
+        }
+    }
+    build.compile("ckb_script_ipc");
+}
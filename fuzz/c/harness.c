@@ -0,0 +1,88 @@
+// Drives the decoder of ckb_script_ipc.c over a byte slice, for the `c_decoder` fuzz target.
+#include <setjmp.h>
+#include <stdlib.h>
+#include <string.h>
+#include "ckb_consts.h"
+#include "ckb_script_ipc.h"
+
+// Not exported by ckb_script_ipc.h
+int csi_receive_request(CSIChannel* channel, CSIRequestPacket* request);
+int csi_receive_response(CSIChannel* channel, CSIResponsePacket* response);
+
+// Replaces printf, see `build.rs`.
+int csi_fuzz_printf(const char* format, ...) { return 0; }
+
+typedef void (*CSIFuzzOnPacket)(void* ctx, uint64_t version, uint64_t id, const uint8_t* payload, uint64_t len);
+
+typedef struct SliceReader {
+    const uint8_t* data;
+    size_t len;
+    size_t chunk;
+} SliceReader;
+
+// Returns at most `chunk` bytes per call, and CKB_OTHER_END_CLOSED at the end, like a closed pipe.
+static int slice_read(void* ctx, void* buf, size_t len, size_t* read_len) {
+    SliceReader* reader = (SliceReader*)ctx;
+    if (reader->len == 0) {
+        *read_len = 0;
+        return CKB_OTHER_END_CLOSED;
+    }
+    size_t n = len < reader->chunk ? len : reader->chunk;
+    n = n < reader->len ? n : reader->len;
+    memcpy(buf, reader->data, n);
+    reader->data += n;
+    reader->len -= n;
+    *read_len = n;
+    return 0;
+}
+
+static uint8_t g_payload_buf[64 * 1024];
+static jmp_buf g_panic_jmp;
+
+static void fuzz_panic(int exit_code) {
+    // running out of payload memory is how the library rejects payloads which are too large,
+    // any other panic is a bug
+    if (exit_code != CSI_ERROR_MALLOC) {
+        abort();
+    }
+    longjmp(g_panic_jmp, exit_code);
+}
+
+// Maximum payload length accepted by csi_fuzz_receive.
+size_t csi_fuzz_max_payload_len(void) { return sizeof(g_payload_buf) / 2; }
+
+// Decodes `data` as a stream of requests (or responses if `response` is set), reading at most
+// `chunk` bytes at a time, and calls `on_packet` for every packet. Non-canonical VLQs are
+// rejected if `canonical` is set. Decoding stops at the first error. Returns 1 if it stopped because a payload is larger than
+// csi_fuzz_max_payload_len(), otherwise 0.
+int csi_fuzz_receive(const uint8_t* data, size_t len, size_t chunk, int response, int canonical,
+                     CSIFuzzOnPacket on_packet, void* ctx) {
+    SliceReader slice = {.data = data, .len = len, .chunk = chunk == 0 ? 1 : chunk};
+    CSIChannel channel = {0};
+    channel.reader.ctx = &slice;
+    channel.reader.read = slice_read;
+    csi_init_panic(fuzz_panic);
+    csi_init_canonical_vlq(canonical);
+
+    if (setjmp(g_panic_jmp) != 0) {
+        return 1;
+    }
+    while (slice.len > 0) {
+        // one block for the payload, and a spare one
+        csi_init_payload(g_payload_buf, sizeof(g_payload_buf), 2);
+        if (response) {
+            CSIResponsePacket packet = {0};
+            if (csi_receive_response(&channel, &packet) != 0) {
+                break;
+            }
+            on_packet(ctx, packet.version, packet.error_code, packet.payload, packet.payload_len);
+        } else {
+            CSIRequestPacket packet = {0};
+            if (csi_receive_request(&channel, &packet) != 0) {
+                break;
+            }
+            on_packet(ctx, packet.version, packet.method_id, packet.payload, packet.payload_len);
+        }
+    }
+    return 0;
+}
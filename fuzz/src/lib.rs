@@ -0,0 +1,430 @@
+//! Fuzzing entry points, shared by the targets in `fuzz_targets` and the smoke tests below.
+//!
+//! Every function takes arbitrary bytes, as they could be written to a pipe by another
+//! script, and panics when it finds a bug.
+use ckb_script_ipc_common::{
+    channel::Channel,
+    error::IpcError,
+    io::{BufReader, Error, Read},
+    packet::{
+        inspect, read_next_vlq, read_packet_into, InspectError, Packet, PacketKind, RequestPacket,
+        ResponsePacket,
+    },
+    vlq::{vlq_decode, vlq_decode_canonical, vlq_decode_prefix, vlq_encode},
+};
+use std::collections::BTreeMap;
+use std::os::raw::{c_int, c_void};
+use unit_tests_def::{Struct1, UnitTests, UnitTestsRequest, UnitTestsResponse};
+
+/// A reader returning at most `chunk` bytes per call, to exercise partial reads.
+pub struct ChunkedReader<'a> {
+    data: &'a [u8],
+    chunk: usize,
+}
+
+impl<'a> ChunkedReader<'a> {
+    pub fn new(data: &'a [u8], chunk: usize) -> Self {
+        Self {
+            data,
+            chunk: chunk.max(1),
+        }
+    }
+}
+
+impl Read for ChunkedReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let n = buf.len().min(self.chunk).min(self.data.len());
+        buf[..n].copy_from_slice(&self.data[..n]);
+        self.data = &self.data[n..];
+        Ok(n)
+    }
+}
+
+// The first byte of an input is the chunk size of the reader, the rest is the stream.
+fn split_chunk(data: &[u8]) -> (usize, &[u8]) {
+    match data.split_first() {
+        Some((chunk, rest)) => (*chunk as usize, rest),
+        None => (1, data),
+    }
+}
+
+/// A decoded packet: version, method id or error code, and payload.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Decoded(u64, u64, Vec<u8>);
+
+/// Decodes `stream` with `Packet::read_from`, or `read_canonical_from` if `canonical` is
+/// set, until the first error.
+pub fn read_packets(
+    kind: PacketKind,
+    stream: &[u8],
+    chunk: usize,
+    canonical: bool,
+) -> (Vec<Decoded>, IpcError) {
+    let mut reader = ChunkedReader::new(stream, chunk);
+    let mut packets = Vec::new();
+    loop {
+        let packet = match (kind, canonical) {
+            (PacketKind::Request, false) => {
+                RequestPacket::read_from(&mut reader).map(Decoded::from)
+            }
+            (PacketKind::Request, true) => {
+                RequestPacket::read_canonical_from(&mut reader).map(Decoded::from)
+            }
+            (PacketKind::Response, false) => {
+                ResponsePacket::read_from(&mut reader).map(Decoded::from)
+            }
+            (PacketKind::Response, true) => {
+                ResponsePacket::read_canonical_from(&mut reader).map(Decoded::from)
+            }
+        };
+        match packet {
+            Ok(packet) => packets.push(packet),
+            Err(e) => return (packets, e),
+        }
+    }
+}
+
+impl From<RequestPacket> for Decoded {
+    fn from(packet: RequestPacket) -> Self {
+        let decoded = Decoded(
+            packet.version() as u64,
+            packet.method_id(),
+            packet.payload().to_vec(),
+        );
+        decoded.check(PacketKind::Request, &packet.serialize())
+    }
+}
+
+impl From<ResponsePacket> for Decoded {
+    fn from(packet: ResponsePacket) -> Self {
+        let decoded = Decoded(
+            packet.version() as u64,
+            packet.error_code(),
+            packet.payload().to_vec(),
+        );
+        decoded.check(PacketKind::Response, &packet.serialize())
+    }
+}
+
+impl Decoded {
+    // A decoded packet must serialize back to bytes which decode to the same packet.
+    fn check(self, kind: PacketKind, serialized: &[u8]) -> Self {
+        let inspection = inspect(kind, serialized);
+        assert_eq!(inspection.error, None);
+        assert_eq!(inspection.packets.len(), 1);
+        let packet = &inspection.packets[0];
+        assert_eq!((packet.version, packet.id), (self.0, self.1));
+        assert_eq!(packet.payload, self.2);
+        self
+    }
+}
+
+/// Decodes single VLQs, with `vlq_decode`, `vlq_decode_canonical` and `read_next_vlq`.
+pub fn fuzz_vlq(data: &[u8]) {
+    let decoded = vlq_decode(data);
+    if let Ok(value) = decoded {
+        assert_eq!(vlq_decode(&vlq_encode(value)).unwrap(), value);
+    }
+    // a canonical VLQ is the only encoding of its value
+    match vlq_decode_canonical(data) {
+        Ok((value, len)) => {
+            assert_eq!(vlq_encode(value), &data[..len]);
+            assert_eq!(decoded.clone().unwrap(), value);
+        }
+        Err(IpcError::NonCanonicalVlq) => {
+            let (value, len) = vlq_decode_prefix(data).unwrap();
+            assert!(vlq_encode(value).len() < len);
+        }
+        Err(_) => assert!(decoded.is_err()),
+    }
+    let mut reader = data;
+    let read = read_next_vlq(&mut reader);
+    // `read_next_vlq` stops at the end of the VLQ, which decodes the same way
+    let used = data.len() - reader.len();
+    assert_eq!(read.is_ok(), vlq_decode(&data[..used]).is_ok());
+    if let (Ok(a), Ok(b)) = (&decoded, &read) {
+        assert_eq!(a, b);
+    }
+}
+
+/// Decodes a stream as requests and as responses, and checks that `Packet::read_from`
+/// agrees with `packet::inspect`.
+pub fn fuzz_packets(data: &[u8]) {
+    let (chunk, stream) = split_chunk(data);
+    for kind in [PacketKind::Request, PacketKind::Response] {
+        let (packets, _) = read_packets(kind, stream, chunk, false);
+        let (canonical, _) = read_packets(kind, stream, chunk, true);
+        assert_eq!(packets[..canonical.len()], canonical);
+        // the buffered reader of `Channel` decodes the same packets
+        for canonical_vlq in [false, true] {
+            let mut reader = BufReader::with_capacity(chunk.max(1), stream);
+            let mut payload = Vec::new();
+            let expected = if canonical_vlq { &canonical } else { &packets };
+            for Decoded(version, id, expected_payload) in expected {
+                let decoded = read_packet_into(&mut reader, canonical_vlq, &mut payload).unwrap();
+                assert_eq!(decoded, (*version as u8, *id));
+                assert_eq!(&payload, expected_payload);
+            }
+            assert!(read_packet_into(&mut reader, canonical_vlq, &mut payload).is_err());
+        }
+        let inspection = inspect(kind, stream);
+        let inspected: Vec<_> = inspection
+            .packets
+            .iter()
+            .map(|p| Decoded(p.version, p.id, p.payload.clone()))
+            .collect();
+        match inspection.error {
+            // `read_from` truncates the version instead, and may go on
+            Some(InspectError::Malformed {
+                field: "version", ..
+            }) => assert_eq!(packets[..inspected.len()], inspected),
+            _ => assert_eq!(packets, inspected),
+        }
+    }
+}
+
+/// A `UnitTests` service which accepts any arguments.
+pub struct FuzzServer;
+
+impl UnitTests for FuzzServer {
+    fn test_primitive_types(
+        &mut self,
+        _arg1: i8,
+        _arg2: u8,
+        _arg3: i16,
+        _arg4: u16,
+        _arg5: i32,
+        _arg6: u32,
+        _arg7: i64,
+        _arg8: u64,
+        _arg9: i128,
+        _arg10: u128,
+        _arg11: bool,
+    ) {
+    }
+    fn test_vec(&mut self, _vec: Vec<i32>) {}
+    fn test_btree_map(&mut self, _map: BTreeMap<String, i32>) {}
+    fn test_complex_types(&mut self, _arg1: Struct1) {}
+    fn test_return_types(&mut self) -> Result<u32, String> {
+        Ok(42)
+    }
+    fn test_large_input_output(&mut self, input: Vec<u8>) -> Vec<u8> {
+        input
+    }
+    fn test_debug_print(&mut self, _line: String) {}
+    fn test_echo_integers(&mut self, signed: i128, unsigned: u128) -> (i128, u128) {
+        (signed, unsigned)
+    }
+}
+
+/// Runs the generated server of `FuzzServer` on a stream of requests. The server must stop
+/// at the end of the stream at the latest, and its output must be well-formed responses,
+/// with an error code in the last one only.
+pub fn fuzz_execute(data: &[u8]) {
+    let (chunk, stream) = split_chunk(data);
+    let mut output = Vec::new();
+    let result = Channel::new(ChunkedReader::new(stream, chunk), &mut output)
+        .execute(&mut FuzzServer.server());
+    assert!(result.is_err());
+
+    let inspection = inspect(PacketKind::Response, &output);
+    assert_eq!(inspection.error, None);
+    let (last, rest) = inspection.packets.split_last().unwrap();
+    assert_ne!(last.id, 0);
+    assert!(last.payload.is_empty());
+    assert!(rest.iter().all(|p| p.id == 0));
+}
+
+/// Calls a server which replies with a stream of arbitrary responses, until the first error.
+pub fn fuzz_response(data: &[u8]) {
+    let (chunk, stream) = split_chunk(data);
+    let mut output = Vec::new();
+    let mut channel = Channel::new(ChunkedReader::new(stream, chunk), &mut output);
+    for i in 0.. {
+        let result = if i % 2 == 0 {
+            channel
+                .call::<_, UnitTestsResponse>(
+                    "UnitTests.test_return_types",
+                    UnitTestsRequest::TestReturnTypes {},
+                )
+                .map(drop)
+        } else {
+            channel
+                .send_json_request("{\"TestReturnTypes\":{}}")
+                .and_then(|_| channel.receive_json_response())
+                .map(drop)
+        };
+        if result.is_err() {
+            break;
+        }
+    }
+}
+
+type OnPacket = extern "C" fn(*mut c_void, u64, u64, *const u8, u64);
+
+extern "C" {
+    fn csi_vlq_decode(
+        buf: *const c_void,
+        len: usize,
+        value: *mut u64,
+        out_len: *mut usize,
+    ) -> c_int;
+    fn csi_vlq_decode_canonical(
+        buf: *const c_void,
+        len: usize,
+        value: *mut u64,
+        out_len: *mut usize,
+    ) -> c_int;
+    fn csi_fuzz_max_payload_len() -> usize;
+    fn csi_fuzz_receive(
+        data: *const u8,
+        len: usize,
+        chunk: usize,
+        response: c_int,
+        canonical: c_int,
+        on_packet: OnPacket,
+        ctx: *mut c_void,
+    ) -> c_int;
+}
+
+extern "C" fn push_packet(ctx: *mut c_void, version: u64, id: u64, payload: *const u8, len: u64) {
+    let packets = unsafe { &mut *(ctx as *mut Vec<Decoded>) };
+    let payload = if len == 0 {
+        Vec::new()
+    } else {
+        unsafe { std::slice::from_raw_parts(payload, len as usize) }.to_vec()
+    };
+    packets.push(Decoded(version, id, payload));
+}
+
+/// Decodes a stream with the C implementation, and checks that it agrees with the Rust one.
+pub fn fuzz_c_decoder(data: &[u8]) {
+    let (chunk, stream) = split_chunk(data);
+
+    let mut value = 0u64;
+    let mut used = 0usize;
+    let err = unsafe { csi_vlq_decode(stream.as_ptr() as _, stream.len(), &mut value, &mut used) };
+    match vlq_decode_prefix(stream) {
+        Ok(v) => assert_eq!((err, (value, used)), (0, v)),
+        Err(_) => assert_ne!(err, 0),
+    }
+    let err = unsafe {
+        csi_vlq_decode_canonical(stream.as_ptr() as _, stream.len(), &mut value, &mut used)
+    };
+    match vlq_decode_canonical(stream) {
+        Ok(v) => assert_eq!((err, (value, used)), (0, v)),
+        Err(_) => assert_ne!(err, 0),
+    }
+
+    for (kind, canonical) in [
+        (PacketKind::Request, false),
+        (PacketKind::Response, false),
+        (PacketKind::Request, true),
+        (PacketKind::Response, true),
+    ] {
+        let mut packets: Vec<Decoded> = Vec::new();
+        let too_large = unsafe {
+            csi_fuzz_receive(
+                stream.as_ptr(),
+                stream.len(),
+                chunk,
+                (kind == PacketKind::Response) as c_int,
+                canonical as c_int,
+                push_packet,
+                &mut packets as *mut _ as *mut c_void,
+            )
+        } != 0;
+        let (expected, _) = read_packets(kind, stream, chunk, canonical);
+        // the C implementation keeps the whole version
+        let expected = expected
+            .into_iter()
+            .map(|Decoded(_, id, payload)| (id, payload));
+        let packets = packets
+            .into_iter()
+            .map(|Decoded(_, id, payload)| (id, payload));
+        if too_large {
+            assert!(packets.len() <= expected.len());
+            assert!(packets.zip(expected).all(|(a, b)| a == b));
+        } else {
+            assert_eq!(
+                packets.collect::<Vec<_>>(),
+                expected.collect::<Vec<_>>(),
+                "{:?}",
+                data
+            );
+        }
+    }
+    assert!(unsafe { csi_fuzz_max_payload_len() } > 0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(req: &UnitTestsRequest) -> Vec<u8> {
+        RequestPacket::new(serde_json::to_vec(req).unwrap()).serialize()
+    }
+
+    // Valid request streams, mutated by a simple PRNG below.
+    fn seeds() -> Vec<Vec<u8>> {
+        let mut stream = vec![];
+        stream.extend(request(&UnitTestsRequest::TestReturnTypes {}));
+        stream.extend(request(&UnitTestsRequest::TestVec { vec: vec![1, 2, 3] }));
+        stream.extend(request(&UnitTestsRequest::TestLargeInputOutput {
+            input: vec![0; 100],
+        }));
+        let mut response =
+            ResponsePacket::new(0, b"{\"TestReturnTypes\":{\"Ok\":42}}".to_vec()).serialize();
+        response.extend(ResponsePacket::new(27, vec![]).serialize());
+        vec![
+            vec![],
+            vec![1],
+            [&[1][..], &stream].concat(),
+            [&[7][..], &stream].concat(),
+            [&[255][..], &response].concat(),
+            vec![
+                0, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x01,
+            ],
+            vec![16, 0, 0, 0xff, 0xff, 0xff, 0xff, 0x0f],
+            vec![1, 0x80, 0x00, 0x00, 0x00, 0x00, 0x80, 0x00],
+        ]
+    }
+
+    fn mutations() -> impl Iterator<Item = Vec<u8>> {
+        let seeds = seeds();
+        let mut state = 0x2545f4914f6cdd1du64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        (0..2000).map(move |_| {
+            let mut data = seeds[next() as usize % seeds.len()].clone();
+            for _ in 0..next() % 4 {
+                if data.is_empty() {
+                    data.push(next() as u8);
+                    continue;
+                }
+                let pos = next() as usize % data.len();
+                match next() % 3 {
+                    0 => data[pos] = next() as u8,
+                    1 => data.truncate(pos),
+                    _ => data.insert(pos, next() as u8),
+                }
+            }
+            data
+        })
+    }
+
+    #[test]
+    fn test_fuzz_targets() {
+        for data in seeds().into_iter().chain(mutations()) {
+            fuzz_vlq(&data);
+            fuzz_packets(&data);
+            fuzz_execute(&data);
+            fuzz_response(&data);
+            fuzz_c_decoder(&data);
+        }
+    }
+}
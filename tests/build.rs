@@ -0,0 +1,18 @@
+fn main() {
+    println!("cargo:rerun-if-changed=../c/ckb_script_ipc.c");
+    println!("cargo:rerun-if-changed=../c/ckb_script_ipc.h");
+    println!("cargo:rerun-if-changed=../c/native");
+    println!("cargo:rerun-if-changed=c/conformance.c");
+
+    // The C implementation, built natively for the conformance tests.
+    cc::Build::new()
+        .file("../c/ckb_script_ipc.c")
+        .file("../c/native/ckb_syscalls_native.c")
+        .file("c/conformance.c")
+        .include("../c/native")
+        .include("../c")
+        // silences the diagnostics printed by the library on errors, which the tests trigger on purpose
+        .define("printf", "csi_conformance_printf")
+        .warnings(false)
+        .compile("ckb_script_ipc");
+}
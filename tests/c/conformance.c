@@ -0,0 +1,111 @@
+// Runs the decoder and encoder of ckb_script_ipc.c over in-memory streams, for the conformance tests in
+// `tests_conformance.rs`.
+#include <string.h>
+#include "ckb_script_ipc.h"
+
+// Not exported by ckb_script_ipc.h
+int csi_send_request(CSIChannel* channel, const CSIRequestPacket* request);
+int csi_send_response(CSIChannel* channel, const CSIResponsePacket* response);
+int csi_receive_request(CSIChannel* channel, CSIRequestPacket* request);
+int csi_receive_response(CSIChannel* channel, CSIResponsePacket* response);
+
+// Replaces printf, see `build.rs`.
+int csi_conformance_printf(const char* format, ...) { return 0; }
+
+typedef void (*CSIConformanceOnPacket)(void* ctx, uint64_t version, uint64_t id, const uint8_t* payload,
+                                       uint64_t len);
+
+typedef struct SliceReader {
+    const uint8_t* data;
+    size_t len;
+    size_t chunk;
+} SliceReader;
+
+// Returns at most `chunk` bytes per call, and 0 bytes at the end of the stream.
+static int slice_read(void* ctx, void* buf, size_t len, size_t* read_len) {
+    SliceReader* reader = (SliceReader*)ctx;
+    size_t n = len < reader->chunk ? len : reader->chunk;
+    n = n < reader->len ? n : reader->len;
+    memcpy(buf, reader->data, n);
+    reader->data += n;
+    reader->len -= n;
+    *read_len = n;
+    return 0;
+}
+
+typedef struct SliceWriter {
+    uint8_t* data;
+    size_t len;
+    size_t pos;
+} SliceWriter;
+
+static int slice_write(void* ctx, const void* buf, size_t len, size_t* written_len) {
+    SliceWriter* writer = (SliceWriter*)ctx;
+    if (len > writer->len - writer->pos) {
+        return CSI_ERROR_INTERNAL;
+    }
+    memcpy(writer->data + writer->pos, buf, len);
+    writer->pos += len;
+    *written_len = len;
+    return 0;
+}
+
+static int slice_flush(void* ctx) { return 0; }
+
+static uint8_t g_payload_buf[64 * 1024];
+
+// Decodes `data` as a stream of requests (or responses if `response` is set), reading at most `chunk` bytes at a
+// time, and calls `on_packet` for every packet. Non-canonical VLQs are rejected if `canonical` is set. Returns the
+// error which stopped decoding, CSI_ERROR_EOF at the end of the stream.
+int csi_conformance_decode(const uint8_t* data, size_t len, size_t chunk, int response, int canonical,
+                           CSIConformanceOnPacket on_packet, void* ctx) {
+    SliceReader slice = {.data = data, .len = len, .chunk = chunk == 0 ? 1 : chunk};
+    CSIChannel channel = {0};
+    channel.reader.ctx = &slice;
+    channel.reader.read = slice_read;
+    csi_init_canonical_vlq(canonical);
+
+    while (1) {
+        // one block for the payload, and a spare one
+        csi_init_payload(g_payload_buf, sizeof(g_payload_buf), 2);
+        int err = 0;
+        if (response) {
+            CSIResponsePacket packet = {0};
+            err = csi_receive_response(&channel, &packet);
+            if (err == 0) {
+                on_packet(ctx, packet.version, packet.error_code, packet.payload, packet.payload_len);
+            }
+        } else {
+            CSIRequestPacket packet = {0};
+            err = csi_receive_request(&channel, &packet);
+            if (err == 0) {
+                on_packet(ctx, packet.version, packet.method_id, packet.payload, packet.payload_len);
+            }
+        }
+        if (err) {
+            return err;
+        }
+    }
+}
+
+// Encodes a request (or a response if `response` is set) into `buf`, and returns its length in `encoded_len`.
+int csi_conformance_encode(int response, uint64_t version, uint64_t id, const uint8_t* payload, size_t payload_len,
+                           uint8_t* buf, size_t len, size_t* encoded_len) {
+    SliceWriter slice = {.data = buf, .len = len, .pos = 0};
+    CSIChannel channel = {0};
+    channel.writer.ctx = &slice;
+    channel.writer.write = slice_write;
+    channel.writer.flush = slice_flush;
+    int err = 0;
+    if (response) {
+        CSIResponsePacket packet = {
+            .version = version, .error_code = id, .payload_len = payload_len, .payload = (void*)payload};
+        err = csi_send_response(&channel, &packet);
+    } else {
+        CSIRequestPacket packet = {
+            .version = version, .method_id = id, .payload_len = payload_len, .payload = (void*)payload};
+        err = csi_send_request(&channel, &packet);
+    }
+    *encoded_len = slice.pos;
+    return err;
+}
@@ -17,6 +17,7 @@ pub mod service_def;
 mod tests;
 
 #[cfg(test)]
+#[allow(clippy::needless_borrows_for_generic_args)]
 mod tests_service;
 
 #[cfg(test)]
@@ -25,6 +26,12 @@ mod tests_native;
 #[cfg(test)]
 mod tests_c_impl;
 
+#[cfg(test)]
+mod tests_cli;
+
+#[cfg(test)]
+mod tests_conformance;
+
 // The exact same Loader code from capsule's template, except that
 // now we use MODE as the environment variable
 const TEST_ENV_VAR: &str = "MODE";
@@ -70,3 +70,31 @@ fn test_c_impl_examples() {
 fn test_c_impl_tests() {
     test_c_impl("../../c/build/tests/client", "../../c/build/tests/server");
 }
+
+// The C client calls the Rust server of `unit-tests` with JSON payloads.
+#[test]
+fn test_c_impl_json_client() {
+    test_c_impl("../../c/build/tests/json_client", "unit-tests");
+}
+
+// The Rust client of `unit-tests` calls the C server with JSON payloads.
+#[test]
+fn test_c_impl_json_server() {
+    test_c_impl("unit-tests", "../../c/build/tests/json_server");
+}
+
+// `c/generated/ckb_crypto.h` is checked in for C scripts calling the crypto service. Run with
+// `UPDATE_C_HEADERS=1` to regenerate it after changing `CkbCrypto`.
+#[test]
+fn test_c_header_up_to_date() {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../c/generated/ckb_crypto.h");
+    if std::env::var("UPDATE_C_HEADERS").is_ok() {
+        std::fs::write(&path, ckb_crypto_interface::CKB_CRYPTO_C_HEADER).unwrap();
+    }
+    let header = std::fs::read_to_string(&path).unwrap_or_default();
+    assert!(
+        header == ckb_crypto_interface::CKB_CRYPTO_C_HEADER,
+        "{:?} is outdated, regenerate it with `UPDATE_C_HEADERS=1 cargo test`",
+        path
+    );
+}
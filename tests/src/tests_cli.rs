@@ -0,0 +1,98 @@
+use ckb_script_ipc_cli::{format_response, parse_hex, CallError, Session};
+use ckb_script_ipc_common::{
+    error::{IpcError, ProtocolErrorCode},
+    native::{DebugSink, NativeSpawnOptions},
+};
+
+fn new_session() -> Session {
+    let script_binary = std::fs::read("../build/release/unit-tests").unwrap();
+    let options = NativeSpawnOptions {
+        debug_sink: DebugSink::Discard,
+        ..Default::default()
+    };
+    Session::new(script_binary, vec!["server_entry".into()], options).unwrap()
+}
+
+#[test]
+fn test_cli_session() {
+    let mut session = new_session();
+    let response = session.call(r#"{"TestReturnTypes":{}}"#).unwrap();
+    assert_eq!(response, r#"{"TestReturnTypes":{"Ok":42}}"#);
+    assert_eq!(
+        format_response(&response, false),
+        "{\n  \"TestReturnTypes\": {\n    \"Ok\": 42\n  }\n}"
+    );
+
+    // invalid JSON is rejected before sending
+    assert!(matches!(
+        session.call("{TestReturnTypes"),
+        Err(CallError::InvalidJson(_))
+    ));
+
+    // the server stops after an error and is respawned on the next call
+    let err = session.call(r#"{"UnknownMethod":{}}"#).unwrap_err();
+    assert!(matches!(
+        err,
+        CallError::Ipc {
+            error: IpcError::ProtocolError(ProtocolErrorCode::DeserializeError),
+            ..
+        }
+    ));
+    assert_eq!(err.to_string(), "DeserializeError (error code 27)");
+    let response = session.call(r#"{"TestVec":{"vec":[1,2,3,4,5]}}"#).unwrap();
+    assert_eq!(response, r#"{"TestVec":null}"#);
+}
+
+#[test]
+fn test_cli_large_integers() {
+    let mut session = new_session();
+    // beyond the range of u64 and i64, and of the integers an f64 represents exactly
+    let request = format!(
+        "{{ \"TestEchoIntegers\": {{\"signed\": {}, \"unsigned\": {} }} }}",
+        i128::MIN + 1,
+        u128::MAX - 1
+    );
+    let response = session.call(&request).unwrap();
+    let expected = format!(
+        r#"{{"TestEchoIntegers":[{},{}]}}"#,
+        i128::MIN + 1,
+        u128::MAX - 1
+    );
+    assert_eq!(response, expected);
+    assert_eq!(format_response(&response, true), expected);
+    assert_eq!(
+        format_response(&response, false),
+        format!(
+            "{{\n  \"TestEchoIntegers\": [\n    {},\n    {}\n  ]\n}}",
+            i128::MIN + 1,
+            u128::MAX - 1
+        )
+    );
+}
+
+#[test]
+fn test_cli_format_response() {
+    let response = r#"{"b":{},"a":[ ],"s":"x, \"{[:"}"#;
+    assert_eq!(
+        format_response(response, false),
+        "{\n  \"b\": {},\n  \"a\": [],\n  \"s\": \"x, \\\"{[:\"\n}"
+    );
+    assert_eq!(
+        format_response(response, true),
+        r#"{"b":{},"a":[],"s":"x, \"{[:"}"#
+    );
+    assert_eq!(format_response("not json", false), "not json");
+}
+
+#[test]
+fn test_cli_parse_hex() {
+    assert_eq!(
+        parse_hex("0x00 0X7b,22:ff\n").unwrap(),
+        vec![0, 0x7b, 0x22, 0xff]
+    );
+    assert_eq!(parse_hex("").unwrap(), Vec::<u8>::new());
+    assert!(parse_hex("123").is_err());
+    assert!(parse_hex("zz").is_err());
+    assert!(parse_hex("aé0").is_err());
+    assert!(parse_hex("+f").is_err());
+}
@@ -0,0 +1,278 @@
+//! Checks the Rust and C implementations of the wire format against the golden streams of
+//! `conformance/wire_v1.json`. The C implementation is built natively by `build.rs`.
+use ckb_script_ipc_common::{
+    error::IpcError,
+    io::{Error, Read},
+    packet::{inspect, write_packet, Packet, PacketKind, RequestPacket, ResponsePacket},
+};
+use serde_json::Value;
+use std::os::raw::{c_int, c_void};
+
+const CSI_ERROR_VLQ: c_int = 52;
+const CSI_ERROR_READ_VLQ: c_int = 56;
+const CSI_ERROR_VLQ_NOT_CANONICAL: c_int = 65;
+const CSI_ERROR_EOF: c_int = 70;
+const CSI_ERROR_UNEXPECTED_EOF: c_int = 71;
+
+type OnPacket = extern "C" fn(*mut c_void, u64, u64, *const u8, u64);
+
+extern "C" {
+    fn csi_conformance_decode(
+        data: *const u8,
+        len: usize,
+        chunk: usize,
+        response: c_int,
+        canonical: c_int,
+        on_packet: OnPacket,
+        ctx: *mut c_void,
+    ) -> c_int;
+    fn csi_conformance_encode(
+        response: c_int,
+        version: u64,
+        id: u64,
+        payload: *const u8,
+        payload_len: usize,
+        buf: *mut u8,
+        len: usize,
+        encoded_len: *mut usize,
+    ) -> c_int;
+}
+
+/// A decoded packet: version, method id or error code, and payload.
+#[derive(Debug, PartialEq, Eq)]
+struct Decoded(u64, u64, Vec<u8>);
+
+/// The expected decoding of a stream: its packets, and why decoding stopped, `None` at the
+/// end of the stream.
+#[derive(Debug, PartialEq, Eq)]
+struct Decoding {
+    packets: Vec<Decoded>,
+    end: Option<String>,
+}
+
+struct Vector {
+    name: String,
+    kind: PacketKind,
+    stream: Vec<u8>,
+    expected: Decoding,
+    expected_canonical: Decoding,
+}
+
+fn hex(value: &Value) -> Vec<u8> {
+    let s = value.as_str().unwrap();
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+fn load_vectors() -> Vec<Vector> {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../conformance/wire_v1.json");
+    let corpus: Value = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+    assert_eq!(corpus["version"], 1);
+    corpus["vectors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|v| {
+            let packets = || -> Vec<Decoded> {
+                v["packets"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|p| {
+                        Decoded(
+                            p["version"].as_u64().unwrap(),
+                            p["id"].as_u64().unwrap(),
+                            hex(&p["payload"]),
+                        )
+                    })
+                    .collect()
+            };
+            let expected = Decoding {
+                packets: packets(),
+                end: v["end"].as_str().map(String::from),
+            };
+            // decoding with canonical VLQs only differs when a vector says so
+            let expected_canonical = match v.get("canonical") {
+                Some(canonical) => {
+                    let mut packets = packets();
+                    packets.truncate(canonical["packets"].as_u64().unwrap() as usize);
+                    Decoding {
+                        packets,
+                        end: canonical["end"].as_str().map(String::from),
+                    }
+                }
+                None => Decoding {
+                    packets: packets(),
+                    end: expected.end.clone(),
+                },
+            };
+            Vector {
+                name: v["name"].as_str().unwrap().into(),
+                kind: match v["kind"].as_str().unwrap() {
+                    "request" => PacketKind::Request,
+                    "response" => PacketKind::Response,
+                    kind => panic!("unknown kind {kind}"),
+                },
+                stream: hex(&v["stream"]),
+                expected,
+                expected_canonical,
+            }
+        })
+        .collect()
+}
+
+/// A reader returning at most `chunk` bytes per call.
+struct ChunkedReader<'a> {
+    data: &'a [u8],
+    chunk: usize,
+}
+
+impl Read for ChunkedReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let n = buf.len().min(self.chunk).min(self.data.len());
+        buf[..n].copy_from_slice(&self.data[..n]);
+        self.data = &self.data[n..];
+        Ok(n)
+    }
+}
+
+fn rust_decode(kind: PacketKind, stream: &[u8], chunk: usize, canonical: bool) -> Decoding {
+    let mut reader = ChunkedReader {
+        data: stream,
+        chunk,
+    };
+    let mut packets = Vec::new();
+    loop {
+        let remaining = reader.data.len();
+        let packet = match (kind, canonical) {
+            (PacketKind::Request, false) => RequestPacket::read_from(&mut reader)
+                .map(|p| Decoded(p.version() as u64, p.method_id(), p.payload().to_vec())),
+            (PacketKind::Request, true) => RequestPacket::read_canonical_from(&mut reader)
+                .map(|p| Decoded(p.version() as u64, p.method_id(), p.payload().to_vec())),
+            (PacketKind::Response, false) => ResponsePacket::read_from(&mut reader)
+                .map(|p| Decoded(p.version() as u64, p.error_code(), p.payload().to_vec())),
+            (PacketKind::Response, true) => ResponsePacket::read_canonical_from(&mut reader)
+                .map(|p| Decoded(p.version() as u64, p.error_code(), p.payload().to_vec())),
+        };
+        let end = match packet {
+            Ok(packet) => {
+                packets.push(packet);
+                continue;
+            }
+            // the end of the stream between two packets
+            Err(IpcError::IncompleteVlqSeq) if remaining == 0 => None,
+            Err(
+                IpcError::IncompleteVlqSeq | IpcError::UnexpectedEof | IpcError::ReadExactError,
+            ) => Some("truncated".into()),
+            Err(IpcError::DecodeVlqOverflow) => Some("vlq_overflow".into()),
+            Err(IpcError::NonCanonicalVlq) => Some("non_canonical_vlq".into()),
+            Err(e) => Some(format!("{e:?}")),
+        };
+        return Decoding { packets, end };
+    }
+}
+
+extern "C" fn on_packet(ctx: *mut c_void, version: u64, id: u64, payload: *const u8, len: u64) {
+    let packets = unsafe { &mut *(ctx as *mut Vec<Decoded>) };
+    let payload = if len == 0 {
+        Vec::new()
+    } else {
+        unsafe { std::slice::from_raw_parts(payload, len as usize) }.to_vec()
+    };
+    packets.push(Decoded(version, id, payload));
+}
+
+fn c_decode(kind: PacketKind, stream: &[u8], chunk: usize, canonical: bool) -> Decoding {
+    let mut packets: Vec<Decoded> = Vec::new();
+    let err = unsafe {
+        csi_conformance_decode(
+            stream.as_ptr(),
+            stream.len(),
+            chunk,
+            (kind == PacketKind::Response) as c_int,
+            canonical as c_int,
+            on_packet,
+            &mut packets as *mut Vec<Decoded> as *mut c_void,
+        )
+    };
+    let end = match err {
+        CSI_ERROR_EOF => None,
+        CSI_ERROR_UNEXPECTED_EOF => Some("truncated".into()),
+        CSI_ERROR_VLQ | CSI_ERROR_READ_VLQ => Some("vlq_overflow".into()),
+        CSI_ERROR_VLQ_NOT_CANONICAL => Some("non_canonical_vlq".into()),
+        err => Some(format!("C error {err}")),
+    };
+    Decoding { packets, end }
+}
+
+fn c_encode(kind: PacketKind, packet: &Decoded) -> Vec<u8> {
+    let mut buf = vec![0u8; packet.2.len() + 64];
+    let mut len = 0;
+    let err = unsafe {
+        csi_conformance_encode(
+            (kind == PacketKind::Response) as c_int,
+            packet.0,
+            packet.1,
+            packet.2.as_ptr(),
+            packet.2.len(),
+            buf.as_mut_ptr(),
+            buf.len(),
+            &mut len,
+        )
+    };
+    assert_eq!(err, 0);
+    buf.truncate(len);
+    buf
+}
+
+#[test]
+fn test_conformance_vectors() {
+    let vectors = load_vectors();
+    assert!(!vectors.is_empty());
+    for v in &vectors {
+        for chunk in [1, 3, 4096] {
+            for (canonical, expected) in [(false, &v.expected), (true, &v.expected_canonical)] {
+                let context = format!("{} (chunk {chunk}, canonical {canonical})", v.name);
+                assert_eq!(
+                    &rust_decode(v.kind, &v.stream, chunk, canonical),
+                    expected,
+                    "Rust: {context}"
+                );
+                assert_eq!(
+                    &c_decode(v.kind, &v.stream, chunk, canonical),
+                    expected,
+                    "C: {context}"
+                );
+            }
+        }
+
+        // `inspect` finds the same packets
+        let inspection = inspect(v.kind, &v.stream);
+        let inspected: Vec<_> = inspection
+            .packets
+            .iter()
+            .map(|p| Decoded(p.version, p.id, p.payload.clone()))
+            .collect();
+        assert_eq!(inspected, v.expected.packets, "inspect: {}", v.name);
+        assert_eq!(
+            inspection.error.is_none(),
+            v.expected.end.is_none(),
+            "inspect: {}",
+            v.name
+        );
+
+        // streams of canonically encoded packets are what both implementations write
+        if v.expected.end.is_none() && v.expected == v.expected_canonical {
+            let mut rust = Vec::new();
+            let mut c = Vec::new();
+            for packet in &v.expected.packets {
+                write_packet(&mut rust, packet.0 as u8, packet.1, &packet.2).unwrap();
+                c.extend(c_encode(v.kind, packet));
+            }
+            assert_eq!(rust, v.stream, "Rust encoding: {}", v.name);
+            assert_eq!(c, v.stream, "C encoding: {}", v.name);
+        }
+    }
+}
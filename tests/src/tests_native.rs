@@ -1,8 +1,26 @@
-use ckb_script_ipc_common::{channel::Channel, native::spawn_server};
+use ckb_script_ipc_common::{
+    channel::Channel,
+    error::IpcError,
+    io::{ErrorKind, Read, Write},
+    ipc::Serve,
+    native::{
+        set_default_timeout, spawn_server, spawn_server_with_options, DebugLine, DebugSink,
+        MachineKind, NativeError, NativeSpawnOptions, Pipe, RISCV_MAX_MEMORY, VERSION1,
+    },
+    packet::Packet,
+    record::{parse_transcript, Direction, PacketKind, Recorder, Replay, Role},
+};
+use std::time::Duration;
 use unit_tests_def::UnitTestsClient;
 
+// Fail instead of hanging the test run when a server stops responding.
+fn init_timeout() {
+    set_default_timeout(Some(Duration::from_secs(30)));
+}
+
 #[test]
 fn test_native() {
+    init_timeout();
     let script_binary = std::fs::read("../build/release/unit-tests").unwrap();
     let (read_pipe, write_pipe) = spawn_server(&script_binary, &["server_entry"]).unwrap();
 
@@ -21,6 +39,7 @@ fn test_native() {
 
 #[test]
 fn test_native_json() {
+    init_timeout();
     let script_binary = std::fs::read("../build/release/unit-tests").unwrap();
     let (read_pipe, write_pipe) = spawn_server(&script_binary, &["server_entry"]).unwrap();
 
@@ -36,6 +55,7 @@ fn test_native_json() {
 
 #[test]
 fn test_native_stress() {
+    init_timeout();
     let script_binary = std::fs::read("../build/release/unit-tests").unwrap();
     let (read_pipe, write_pipe) = spawn_server(&script_binary, &["server_entry"]).unwrap();
 
@@ -47,3 +67,255 @@ fn test_native_stress() {
     let output = client.test_large_input_output(input.clone());
     assert_eq!(output, input.into_iter().map(|x| x + 1).collect::<Vec<_>>());
 }
+
+#[test]
+fn test_native_with_capacity() {
+    init_timeout();
+    let script_binary = std::fs::read("../build/release/unit-tests").unwrap();
+    for (read_capacity, write_capacity) in [(1, 1), (16, 64), (64 * 1024, 64 * 1024)] {
+        let (read_pipe, write_pipe) = spawn_server(&script_binary, &["server_entry"]).unwrap();
+        let mut client =
+            UnitTestsClient::with_capacity(read_pipe, write_pipe, read_capacity, write_capacity);
+        assert_eq!(client.channel().capacity(), (read_capacity, write_capacity));
+        client.test_primitive_types(1, 2, 3, 4, 5, 6, 7, 8, 9, 10, true);
+        // payloads larger than the buffers
+        for len in [4 * 1024, 32 * 1024] {
+            let input = vec![0; len];
+            let output = client.test_large_input_output(input.clone());
+            assert_eq!(output, input.into_iter().map(|x| x + 1).collect::<Vec<_>>());
+        }
+        assert_eq!(client.test_return_types(), Ok(42));
+    }
+}
+
+#[test]
+fn test_native_options() {
+    init_timeout();
+    let script_binary = std::fs::read("../build/release/unit-tests").unwrap();
+    let options = NativeSpawnOptions {
+        version: VERSION1,
+        memory_size: RISCV_MAX_MEMORY / 2,
+        machine: MachineKind::Interpreter,
+        ..Default::default()
+    };
+    let (read_pipe, write_pipe, _handle) =
+        spawn_server_with_options(&script_binary, &["server_entry"], &options).unwrap();
+
+    let mut client = UnitTestsClient::new(read_pipe, write_pipe);
+    let result = client.test_return_types();
+    assert_eq!(result, Ok(42));
+}
+
+#[test]
+fn test_native_max_cycles() {
+    init_timeout();
+    let script_binary = std::fs::read("../build/release/unit-tests").unwrap();
+    let options = NativeSpawnOptions {
+        max_cycles: 100_000,
+        ..Default::default()
+    };
+    let (read_pipe, write_pipe, handle) =
+        spawn_server_with_options(&script_binary, &["server_entry"], &options).unwrap();
+
+    let mut channel = Channel::new(read_pipe, write_pipe);
+    let input = vec![0u8; 16 * 1024];
+    let json = format!("{{\"TestLargeInputOutput\":{{\"input\":{:?}}}}}", input);
+    let _ = channel.send_json_request(&json);
+    assert!(channel.receive_json_response().is_err());
+    drop(channel);
+
+    assert!(matches!(
+        handle.join(),
+        Err(NativeError::CyclesExceeded {
+            max_cycles: 100_000
+        })
+    ));
+}
+
+#[test]
+fn test_native_invalid_options() {
+    let options = NativeSpawnOptions {
+        memory_size: 1000,
+        ..Default::default()
+    };
+    assert!(matches!(
+        spawn_server_with_options(&[], &[], &options),
+        Err(NativeError::InvalidMemorySize(1000))
+    ));
+}
+
+#[test]
+fn test_native_cycle_stats() {
+    init_timeout();
+    let script_binary = std::fs::read("../build/release/unit-tests").unwrap();
+    let (read_pipe, write_pipe, handle) = spawn_server_with_options(
+        &script_binary,
+        &["server_entry"],
+        &NativeSpawnOptions::default(),
+    )
+    .unwrap();
+
+    let mut client = UnitTestsClient::new(read_pipe, write_pipe);
+    let counter = handle.cycle_counter();
+    client
+        .channel_mut()
+        .enable_cycle_stats_with(move || counter.get());
+    for _ in 0..3 {
+        assert_eq!(client.test_return_types(), Ok(42));
+    }
+    client.test_large_input_output(vec![0u8; 1024]);
+
+    // the server goes on to wait for the next request, which must not be counted
+    let after_response = handle.cycles();
+    std::thread::sleep(Duration::from_millis(50));
+    assert_eq!(handle.cycles(), after_response);
+
+    let stats = client.channel_mut().take_cycle_stats().unwrap();
+    let method = stats.get("UnitTests.test_return_types").unwrap();
+    assert_eq!(method.calls, 3);
+    assert!(method.min_cycles > 0);
+    assert!(method.min_cycles <= method.max_cycles);
+    assert!(
+        stats
+            .get("UnitTests.test_large_input_output")
+            .unwrap()
+            .total_cycles
+            > method.max_cycles
+    );
+    assert!(handle.cycles() > 0);
+    println!("{}", stats);
+}
+
+#[test]
+fn test_native_pipe_timeout() {
+    let timeout = Duration::from_millis(50);
+
+    let (mut reader, _writer) = Pipe::new_pair();
+    reader.set_read_timeout(Some(timeout));
+    let mut buf = [0u8; 8];
+    assert_eq!(
+        reader.read(&mut buf).unwrap_err().kind(),
+        ErrorKind::TimedOut
+    );
+
+    let (_reader, mut writer) = Pipe::new_pair();
+    writer.set_write_timeout(Some(timeout));
+    assert_eq!(writer.write(&buf).unwrap_err().kind(), ErrorKind::TimedOut);
+
+    // a server which never reads or responds
+    let (_server_reader, mut client_writer) = Pipe::new_pair();
+    let (mut client_reader, _server_writer) = Pipe::new_pair();
+    client_writer.set_write_timeout(Some(timeout));
+    client_reader.set_read_timeout(Some(timeout));
+    let mut channel = Channel::new(client_reader, client_writer);
+    assert!(matches!(
+        channel.send_json_request("{}"),
+        Err(IpcError::Timeout)
+    ));
+    assert!(matches!(
+        channel.receive_json_response(),
+        Err(IpcError::Timeout)
+    ));
+}
+
+#[test]
+fn test_native_debug_sink() {
+    init_timeout();
+    let script_binary = std::fs::read("../build/release/unit-tests").unwrap();
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let options = NativeSpawnOptions {
+        name: "unit-tests".into(),
+        debug_sink: DebugSink::Channel(sender),
+        ..Default::default()
+    };
+    let (read_pipe, write_pipe, _handle) =
+        spawn_server_with_options(&script_binary, &["server_entry"], &options).unwrap();
+
+    let mut client = UnitTestsClient::new(read_pipe, write_pipe);
+    client.test_debug_print("hello debug sink".into());
+    let lines: Vec<DebugLine> = receiver.try_iter().collect();
+    assert!(lines.contains(&DebugLine {
+        name: "unit-tests".into(),
+        line: "hello debug sink".into(),
+    }));
+}
+
+// Answers the requests recorded in `test_native_record_replay` like the unit-tests server.
+struct JsonServer;
+
+impl Serve for JsonServer {
+    type Req = serde_json::Value;
+    type Resp = serde_json::Value;
+
+    fn serve(&mut self, req: Self::Req) -> Result<Self::Resp, IpcError> {
+        if req.get("TestReturnTypes").is_some() {
+            Ok(serde_json::json!({"TestReturnTypes": {"Ok": 42}}))
+        } else if req.get("TestVec").is_some() {
+            Ok(serde_json::json!({"TestVec": null}))
+        } else {
+            Err(IpcError::DeserializeError)
+        }
+    }
+}
+
+#[test]
+fn test_native_record_replay() {
+    init_timeout();
+    let script_binary = std::fs::read("../build/release/unit-tests").unwrap();
+    let (read_pipe, write_pipe) = spawn_server(&script_binary, &["server_entry"]).unwrap();
+
+    let recorder = Recorder::new(Role::Client, Vec::new());
+    let mut client = UnitTestsClient::new(recorder.reader(read_pipe), recorder.writer(write_pipe));
+    assert_eq!(client.test_return_types(), Ok(42));
+    client.test_vec(vec![1, 2, 3, 4, 5]);
+    let records = recorder.with_sink(|records| records.clone());
+    assert_eq!(recorder.pending_len(), 0);
+
+    assert_eq!(records.len(), 4);
+    for (i, record) in records.iter().enumerate() {
+        assert_eq!(record.seq, i as u64);
+        let (direction, kind) = if i % 2 == 0 {
+            (Direction::Sent, PacketKind::Request)
+        } else {
+            (Direction::Received, PacketKind::Response)
+        };
+        assert_eq!((record.direction, record.kind), (direction, kind));
+    }
+    assert_eq!(
+        records[0].request().unwrap().payload(),
+        br#"{"TestReturnTypes":{}}"#
+    );
+    assert_eq!(records[1].response().unwrap().error_code(), 0);
+
+    // transcript round trip, with the records mixed into other output
+    let transcript: Vec<String> = records
+        .iter()
+        .map(|r| format!("[server] ipc-record: {}", r))
+        .collect();
+    let transcript = format!("# session\n[server] hello\n{}", transcript.join("\n"));
+    assert_eq!(parse_transcript(&transcript).unwrap(), records);
+    assert_eq!(parse_transcript("0 > request 0g"), Err(1));
+
+    // replay the responses to a client, without a server
+    let replay = Replay::new(records.clone());
+    let mut client = UnitTestsClient::new(replay.clone(), replay.clone());
+    assert_eq!(client.test_return_types(), Ok(42));
+    client.test_vec(vec![1, 2, 3, 4, 5]);
+    assert!(replay.is_finished());
+    assert!(replay.mismatch().is_none());
+
+    // replay the requests to a server
+    let replay = Replay::mirrored(&records);
+    let channel = Channel::new(replay.clone(), replay.clone());
+    // the session ends with EOF, which stops the server
+    assert!(channel.execute(&mut JsonServer).is_err());
+    assert!(replay.is_finished());
+    assert!(replay.mismatch().is_none());
+
+    // a different request is detected
+    let replay = Replay::new(records);
+    let mut channel = Channel::new(replay.clone(), replay.clone());
+    assert!(channel.send_json_request(r#"{"TestVec":{}}"#).is_err());
+    assert!(replay.mismatch().is_some());
+    assert!(!replay.is_finished());
+}
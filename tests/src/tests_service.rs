@@ -8,6 +8,11 @@ use ckb_testtool::ckb_types::{
 use ckb_testtool::context::Context;
 
 fn run_service_test(cmd: Cmd, args: Vec<u8>, witness: Vec<u8>) {
+    run_service_test_with_cycles(cmd, args, witness, 10_000_000);
+}
+
+/// Runs a test of up to `max_cycles` cycles, and returns the consumed cycles.
+fn run_service_test_with_cycles(cmd: Cmd, args: Vec<u8>, witness: Vec<u8>, max_cycles: u64) -> u64 {
     let mut context = Context::default();
 
     let service_outpoint = context.deploy_cell_by_name("ckb-crypto-service");
@@ -77,9 +82,10 @@ fn run_service_test(cmd: Cmd, args: Vec<u8>, witness: Vec<u8>) {
 
     // run
     let cycles = context
-        .verify_tx(&tx, 10_000_000)
+        .verify_tx(&tx, max_cycles)
         .expect("pass verification");
     println!("consume cycles: {}", cycles);
+    cycles
 }
 
 #[test]
@@ -124,6 +130,77 @@ fn test_ripemd160() {
     run_service_test(Cmd::Ripemd160, hash, buffer.to_vec())
 }
 
+#[test]
+fn test_keccak256() {
+    // Keccak-256 of the empty input, as used by Ethereum, not SHA3-256
+    let hash = [
+        0xc5, 0xd2, 0x46, 0x01, 0x86, 0xf7, 0x23, 0x3c, 0x92, 0x7e, 0x7d, 0xb2, 0xdc, 0xc7, 0x03,
+        0xc0, 0xe5, 0x00, 0xb6, 0x53, 0xca, 0x82, 0x27, 0x3b, 0x7b, 0xfa, 0xd8, 0x04, 0x5d, 0x85,
+        0xa4, 0x70,
+    ];
+    run_service_test(Cmd::Keccak256, hash.to_vec(), Vec::new());
+
+    let buffer = [1u8; 256];
+
+    use sha3::{Digest, Keccak256};
+    let hash = Keccak256::digest(buffer).to_vec();
+    run_service_test(Cmd::Keccak256, hash, buffer.to_vec())
+}
+
+#[test]
+fn test_sha512() {
+    let buffer = [1u8; 256];
+
+    use sha2::{Digest, Sha512};
+    let hash = Sha512::digest(buffer).to_vec();
+    run_service_test(Cmd::Sha512, hash, buffer.to_vec())
+}
+
+#[test]
+fn test_sha3_256() {
+    let buffer = [1u8; 256];
+
+    use sha3::{Digest, Sha3_256};
+    let hash = Sha3_256::digest(buffer).to_vec();
+    run_service_test(Cmd::Sha3_256, hash, buffer.to_vec())
+}
+
+#[test]
+fn test_blake3() {
+    let buffer = [1u8; 4096];
+    let hash = blake3::hash(&buffer).as_bytes().to_vec();
+    run_service_test(Cmd::Blake3, hash, buffer.to_vec())
+}
+
+#[test]
+fn test_blake2b_custom() {
+    let buffer = [1u8; 256];
+
+    for (out_len, personal) in [(20u8, &b"ckb-default-hash"[..]), (64, b""), (1, b"custom")] {
+        let mut ctx = blake2b_ref::Blake2bBuilder::new(out_len as usize)
+            .personal(personal)
+            .build();
+        ctx.update(&buffer);
+        let mut hash = vec![0u8; out_len as usize];
+        ctx.finalize(&mut hash);
+
+        let args = [vec![out_len, personal.len() as u8], personal.to_vec(), hash].concat();
+        run_service_test(Cmd::Blake2bCustom, args, buffer.to_vec())
+    }
+}
+
+#[test]
+fn test_hash() {
+    let buffer = [1u8; 256];
+    let hash = ckb_testtool::ckb_hash::blake2b_256(buffer);
+    run_service_test(Cmd::Hash, hash.to_vec(), buffer.to_vec())
+}
+
+#[test]
+fn test_hasher_contexts() {
+    run_service_test(Cmd::HasherContexts, Vec::new(), Vec::new())
+}
+
 #[test]
 fn test_secp256k1_recovery() {
     // recv
@@ -225,3 +302,723 @@ fn test_ed25519_verfiy() {
 
     run_service_test(Cmd::Ed25519Verfiy, pubkey.to_bytes().to_vec(), witness)
 }
+
+fn push_field(witness: &mut Vec<u8>, field: &[u8]) {
+    witness.push(field.len() as u8);
+    witness.extend_from_slice(field);
+}
+
+#[test]
+fn test_secp256r1_verify() {
+    use p256::ecdsa::{signature::hazmat::PrehashSigner, Signature, SigningKey};
+    let prehash = [0u8; 32];
+
+    let prikey_byte: [u8; 32] = [
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 1,
+    ];
+    let prikey = SigningKey::from_slice(&prikey_byte).unwrap();
+    let pubkey = prikey.verifying_key();
+    let sig: Signature = prikey.sign_prehash(&prehash).unwrap();
+
+    let mut witness = Vec::new();
+    push_field(&mut witness, &prehash);
+    push_field(&mut witness, &sig.to_bytes());
+
+    run_service_test(
+        Cmd::Secp256r1Verify,
+        pubkey.to_sec1_bytes().to_vec(),
+        witness,
+    )
+}
+
+fn run_webauthn_test(
+    flags: u8,
+    client_data_type: &str,
+    signed_challenge: &[u8],
+    challenge: &[u8],
+    tamper: bool,
+    expected: &str,
+) {
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+    use p256::ecdsa::{signature::Signer, Signature, SigningKey};
+    use sha2::{Digest, Sha256};
+
+    let prikey_byte: [u8; 32] = [
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 2,
+    ];
+    let prikey = SigningKey::from_slice(&prikey_byte).unwrap();
+    let pubkey = prikey.verifying_key();
+
+    // rpIdHash, flags and signCount
+    let mut authenticator_data = [Sha256::digest(b"example.com").to_vec(), vec![flags]].concat();
+    authenticator_data.extend_from_slice(&1u32.to_be_bytes());
+    let client_data_json = format!(
+        r#"{{"type":"{}","challenge":"{}","origin":"https://example.com","crossOrigin":false}}"#,
+        client_data_type,
+        URL_SAFE_NO_PAD.encode(signed_challenge)
+    );
+    let message = [
+        authenticator_data.as_slice(),
+        &Sha256::digest(client_data_json.as_bytes()),
+    ]
+    .concat();
+    let sig: Signature = prikey.sign(&message);
+    if tamper {
+        authenticator_data[36] += 1;
+    }
+
+    let mut witness = Vec::new();
+    push_field(&mut witness, &authenticator_data);
+    push_field(&mut witness, client_data_json.as_bytes());
+    push_field(&mut witness, challenge);
+    push_field(&mut witness, sig.to_der().as_bytes());
+
+    let mut args = Vec::new();
+    push_field(&mut args, expected.as_bytes());
+    args.extend_from_slice(&pubkey.to_sec1_bytes());
+
+    run_service_test(Cmd::WebauthnVerify, args, witness)
+}
+
+#[test]
+fn test_webauthn_verify() {
+    let challenge = [7u8; 32];
+    // user present and user verified
+    run_webauthn_test(
+        0x05,
+        "webauthn.get",
+        &challenge,
+        &challenge,
+        false,
+        "Ok(())",
+    );
+    run_webauthn_test(
+        0x05,
+        "webauthn.get",
+        &challenge,
+        &[8u8; 32],
+        false,
+        "Err(ChallengeMismatch)",
+    );
+    run_webauthn_test(
+        0x04,
+        "webauthn.get",
+        &challenge,
+        &challenge,
+        false,
+        "Err(UserNotPresent)",
+    );
+    run_webauthn_test(
+        0x05,
+        "webauthn.create",
+        &challenge,
+        &challenge,
+        false,
+        "Err(InvalidClientData)",
+    );
+    run_webauthn_test(
+        0x05,
+        "webauthn.get",
+        &challenge,
+        &challenge,
+        true,
+        "Err(VerifyFailed)",
+    );
+}
+
+/// Address of the secp256k1 private key 1.
+const ETH_ADDRESS: [u8; 20] = [
+    0x7e, 0x5f, 0x45, 0x52, 0x09, 0x1a, 0x69, 0x12, 0x5d, 0x5d, 0xfc, 0xb7, 0xb8, 0xc2, 0x65, 0x90,
+    0x29, 0x39, 0x5b, 0xdf,
+];
+
+/// Signs `digest` with the private key 1, as r, s and v, with v being 27 or 28.
+fn eth_sign(digest: &[u8]) -> Vec<u8> {
+    let mut prikey_byte = [0u8; 32];
+    prikey_byte[31] = 1;
+    let prikey = k256::ecdsa::SigningKey::from_slice(&prikey_byte).unwrap();
+    let (sig, recovery_id) = prikey.sign_prehash_recoverable(digest).unwrap();
+    [sig.to_bytes().to_vec(), vec![27 + recovery_id.to_byte()]].concat()
+}
+
+/// The expected address, or the name of the expected `CryptoError`.
+type EthExpected<'a> = Result<&'a [u8], &'a str>;
+
+fn eth_args(expected: EthExpected, address: Option<&[u8]>) -> Vec<u8> {
+    let expected = match expected {
+        Ok(address) => format!("Ok({:?})", address),
+        Err(e) => format!("Err({})", e),
+    };
+    let mut args = Vec::new();
+    push_field(&mut args, expected.as_bytes());
+    args.extend_from_slice(address.unwrap_or_default());
+    args
+}
+
+#[test]
+fn test_eth_personal_recover() {
+    use sha3::{Digest, Keccak256};
+
+    let message = b"hello ckb";
+    let mut prefixed = format!("\x19Ethereum Signed Message:\n{}", message.len()).into_bytes();
+    prefixed.extend_from_slice(message);
+    let signature = eth_sign(&Keccak256::digest(&prefixed));
+
+    let mut wrong_v = signature.clone();
+    wrong_v[64] += 2;
+    let mut raw_v = signature.clone();
+    raw_v[64] -= 27;
+
+    let run = |signature: &[u8], expected: EthExpected, address: Option<&[u8]>| {
+        let mut witness = Vec::new();
+        push_field(&mut witness, message);
+        push_field(&mut witness, signature);
+        run_service_test(
+            Cmd::EthPersonalRecover,
+            eth_args(expected, address),
+            witness,
+        );
+    };
+    run(&signature, Ok(&ETH_ADDRESS), Some(&ETH_ADDRESS));
+    run(&signature, Ok(&ETH_ADDRESS), None);
+    run(&raw_v, Ok(&ETH_ADDRESS), Some(&ETH_ADDRESS));
+    run(&signature, Err("AddressMismatch"), Some(&[0u8; 20]));
+    run(&wrong_v, Err("InvalidRecoveryId"), Some(&ETH_ADDRESS));
+}
+
+#[test]
+fn test_eth_typed_data_recover() {
+    use sha3::{Digest, Keccak256};
+
+    let domain_separator = Keccak256::digest(b"domain");
+    let struct_hash = Keccak256::digest(b"struct");
+    let digest = Keccak256::digest([&[0x19, 0x01], &domain_separator[..], &struct_hash].concat());
+    let signature = eth_sign(&digest);
+
+    let cases: [(&[u8], EthExpected); 2] = [
+        (&struct_hash, Ok(&ETH_ADDRESS)),
+        (&struct_hash[1..], Err("InvalidPrehash")),
+    ];
+    for (struct_hash, expected) in cases {
+        let mut witness = Vec::new();
+        push_field(&mut witness, &domain_separator);
+        push_field(&mut witness, struct_hash);
+        push_field(&mut witness, &signature);
+        run_service_test(
+            Cmd::EthTypedDataRecover,
+            eth_args(expected, Some(&ETH_ADDRESS)),
+            witness,
+        );
+    }
+}
+
+fn hex(s: &str) -> Vec<u8> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+fn run_bitcoin_test(
+    cmd: Cmd,
+    address_type: u8,
+    pubkey_hash: &[u8],
+    message: &[u8],
+    signature: &[u8],
+    expected: &str,
+) {
+    let mut args = Vec::new();
+    push_field(&mut args, expected.as_bytes());
+    args.push(address_type);
+    args.extend_from_slice(pubkey_hash);
+
+    let mut witness = Vec::new();
+    push_field(&mut witness, message);
+    push_field(&mut witness, signature);
+
+    run_service_test(cmd, args, witness)
+}
+
+#[test]
+fn test_bitcoin_message_verify() {
+    use ripemd::Ripemd160;
+    use sha2::{Digest, Sha256};
+
+    // P2PKH hashes of the compressed and uncompressed public keys of the
+    // private key 1
+    let compressed = hex("751e76e8199196d454941c45d1b3a323f1433bd6");
+    let uncompressed = hex("91b24bf9f5288532960ac687abb035127b1d28a5");
+    let p2sh_p2wpkh = Ripemd160::digest(Sha256::digest([&[0, 0x14], &compressed[..]].concat()));
+
+    let message = b"hello ckb";
+    let mut data = vec![24];
+    data.extend_from_slice(b"Bitcoin Signed Message:\n");
+    data.push(message.len() as u8);
+    data.extend_from_slice(message);
+    let digest = Sha256::digest(Sha256::digest(&data));
+
+    let mut prikey_byte = [0u8; 32];
+    prikey_byte[31] = 1;
+    let prikey = k256::ecdsa::SigningKey::from_slice(&prikey_byte).unwrap();
+    let (sig, recovery_id) = prikey.sign_prehash_recoverable(&digest).unwrap();
+    let sign = |header: u8| [vec![header + recovery_id.to_byte()], sig.to_vec()].concat();
+
+    let run = |address_type, pubkey_hash: &[u8], signature: &[u8], expected| {
+        run_bitcoin_test(
+            Cmd::BitcoinMessageVerify,
+            address_type,
+            pubkey_hash,
+            message,
+            signature,
+            expected,
+        )
+    };
+    run(0, &compressed, &sign(31), "Ok(())");
+    run(0, &uncompressed, &sign(27), "Ok(())");
+    run(1, &p2sh_p2wpkh, &sign(35), "Ok(())");
+    run(2, &compressed, &sign(39), "Ok(())");
+    run(0, &uncompressed, &sign(31), "Err(AddressMismatch)");
+    run(2, &compressed, &sign(27), "Err(InvalidAddressType)");
+    run(3, &compressed, &sign(31), "Err(InvalidAddressType)");
+    run(0, &compressed, &sign(23), "Err(InvalidRecoveryId)");
+}
+
+#[test]
+fn test_bip322_verify() {
+    // test vectors of BIP-322, signed by bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l
+    // and bc1ppv609nr0vr25u07u95waq5lucwfm6tde4nydujnu8npg4q75mr5sxq8lt3
+    let p2wpkh = hex("2b05d564e6a7a33c087f16e0f730d1440123799d");
+    let p2wpkh_empty = hex(concat!(
+        "024730440220336801010aaf657d79662cac98a990a43ac6f376af2c84f8f76401ccb9d023160220",
+        "1693a4e683db4a91944ca5cb11527840366daf583a2c695fccf8e93483b52e34012102c7f1200319",
+        "6442943d8588e01aee840423cc54fc1521526a3b85c2b0cbd58872"
+    ));
+    let p2wpkh_hello = hex(concat!(
+        "0247304402206517c8637a7bfc3a154edcba6196d64bbd5b73955cb7da7d1626bcdde466c3640220",
+        "22bf10d19fc0bb69b4596e306b362acaa835293cf693bb176f7324b531f5afec012102c7f1200319",
+        "6442943d8588e01aee840423cc54fc1521526a3b85c2b0cbd58872"
+    ));
+    let p2tr = hex("0b34f2cc6f60d54e3fdc2d1dd053fcc393bd2db9acc8de4a7c3cc28a83d4d8e9");
+    let p2tr_hello = hex(concat!(
+        "0141ddebd3eb25012ffa82937d9f25f9644e047bb2f472ab6c5089bbb53588ada2884cb5bcc53911",
+        "f32d8dcf9548733b694d120db6a4e485194559e8d8fe668d269f01"
+    ));
+    let mut p2tr_sighash_none = p2tr_hello.clone();
+    *p2tr_sighash_none.last_mut().unwrap() = 0x02;
+
+    let run = |address_type, pubkey_hash: &[u8], message: &[u8], signature: &[u8], expected| {
+        run_bitcoin_test(
+            Cmd::Bip322Verify,
+            address_type,
+            pubkey_hash,
+            message,
+            signature,
+            expected,
+        )
+    };
+    run(2, &p2wpkh, b"", &p2wpkh_empty, "Ok(())");
+    run(2, &p2wpkh, b"Hello World", &p2wpkh_hello, "Ok(())");
+    run(3, &p2tr, b"Hello World", &p2tr_hello, "Ok(())");
+    run(2, &p2wpkh, b"", &p2wpkh_hello, "Err(VerifyFailed)");
+    run(3, &p2tr, b"", &p2tr_hello, "Err(VerifyFailed)");
+    run(2, &p2wpkh[..19], b"", &p2wpkh_empty, "Err(InvalidAddress)");
+    run(2, &[0u8; 20], b"", &p2wpkh_empty, "Err(AddressMismatch)");
+    run(0, &p2wpkh, b"", &p2wpkh_empty, "Err(InvalidAddressType)");
+    run(2, &p2wpkh, b"", &p2wpkh_empty[..100], "Err(InvalidWitness)");
+    run(
+        3,
+        &p2tr,
+        b"Hello World",
+        &p2tr_sighash_none,
+        "Err(InvalidSighashType)",
+    );
+}
+
+/// Signs `prehash` with the private key `n` of a scheme of `batch_verify`,
+/// and returns the item as the scheme byte, public key, prehash and signature.
+fn batch_item(scheme: u8, n: u8, prehash: &[u8]) -> Vec<u8> {
+    let mut prikey_byte = [0u8; 32];
+    prikey_byte[31] = n;
+    let (public_key, signature) = match scheme {
+        0 => {
+            let prikey = k256::ecdsa::SigningKey::from_slice(&prikey_byte).unwrap();
+            let (sig, _) = prikey.sign_prehash_recoverable(prehash).unwrap();
+            let public_key = prikey.verifying_key().to_sec1_bytes().to_vec();
+            (public_key, sig.to_vec())
+        }
+        1 => {
+            use k256::schnorr::{signature::Signer, SigningKey};
+            let prikey = SigningKey::from_bytes(&prikey_byte).unwrap();
+            let sig = prikey.sign(prehash).to_bytes().to_vec();
+            (prikey.verifying_key().to_bytes().to_vec(), sig)
+        }
+        2 => {
+            use ed25519_dalek::{Signer, SigningKey};
+            let prikey = SigningKey::from_bytes(&prikey_byte);
+            let sig = prikey.sign(prehash).to_vec();
+            (prikey.verifying_key().to_bytes().to_vec(), sig)
+        }
+        _ => {
+            use p256::ecdsa::{signature::hazmat::PrehashSigner, Signature, SigningKey};
+            let prikey = SigningKey::from_slice(&prikey_byte).unwrap();
+            let sig: Signature = prikey.sign_prehash(prehash).unwrap();
+            let public_key = prikey.verifying_key().to_sec1_bytes().to_vec();
+            (public_key, sig.to_vec())
+        }
+    };
+    let mut item = vec![scheme];
+    push_field(&mut item, &public_key);
+    push_field(&mut item, prehash);
+    push_field(&mut item, &signature);
+    item
+}
+
+#[test]
+fn test_batch_verify() {
+    let prehash = [3u8; 32];
+    let ed25519: Vec<Vec<u8>> = (1..=4).map(|n| batch_item(2, n, &prehash)).collect();
+
+    // all Ed25519 signatures pass the batch
+    let items = [
+        batch_item(0, 1, &prehash),
+        batch_item(1, 1, &prehash),
+        batch_item(3, 1, &prehash),
+    ]
+    .iter()
+    .chain(&ed25519)
+    .flatten()
+    .copied()
+    .collect();
+    run_service_test_with_cycles(
+        Cmd::BatchVerify,
+        b"Ok([Ok(()), Ok(()), Ok(()), Ok(()), Ok(()), Ok(()), Ok(())])".to_vec(),
+        items,
+        100_000_000,
+    );
+
+    // the batch is cheaper than verifying the Ed25519 signatures one by one
+    let single = run_service_test_with_cycles(
+        Cmd::BatchVerify,
+        b"Ok([Ok(())])".to_vec(),
+        ed25519[0].clone(),
+        100_000_000,
+    );
+    let batch = run_service_test_with_cycles(
+        Cmd::BatchVerify,
+        b"Ok([Ok(()), Ok(()), Ok(()), Ok(())])".to_vec(),
+        ed25519.concat(),
+        100_000_000,
+    );
+    println!(
+        "cycles of 1 Ed25519 signature: {}, of a batch of 4: {}",
+        single, batch
+    );
+    assert!(batch < 4 * single);
+
+    // a bad Ed25519 signature fails the batch, and is found one by one
+    let mut bad_ed25519 = batch_item(2, 5, &prehash);
+    let len = bad_ed25519.len();
+    bad_ed25519[len - 1] ^= 1;
+    // a 31 byte public key
+    let mut bad_public_key = batch_item(2, 6, &prehash);
+    bad_public_key[1] = 31;
+    bad_public_key.remove(2);
+    // signed another prehash: scheme, 33 byte public key and prehash
+    let mut bad_secp256k1 = batch_item(0, 2, &[4u8; 32]);
+    bad_secp256k1[36..68].copy_from_slice(&prehash);
+    let items = [
+        ed25519[0].clone(),
+        bad_ed25519,
+        ed25519[1].clone(),
+        bad_public_key,
+        bad_secp256k1,
+    ]
+    .concat();
+    run_service_test_with_cycles(
+        Cmd::BatchVerify,
+        b"Ok([Ok(()), Err(VerifyFailed), Ok(()), Err(InvalidPubkey), Err(VerifyFailed)])".to_vec(),
+        items,
+        100_000_000,
+    );
+
+    // a key of order 2, and a signature of R = identity and s = 0, which
+    // `ed25519_verify` accepts for half of the prehashes, and the batch
+    // equation for some of the others
+    use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+    let mut public_key = [0xffu8; 32];
+    public_key[0] = 0xec;
+    public_key[31] = 0x7f;
+    let mut signature = [0u8; 64];
+    signature[0] = 1;
+    let vk = VerifyingKey::from_bytes(&public_key).unwrap();
+    let sig = Signature::from_bytes(&signature);
+    let small_order_item = |prehash: &[u8]| {
+        let mut item = vec![2];
+        push_field(&mut item, &public_key);
+        push_field(&mut item, prehash);
+        push_field(&mut item, &signature);
+        item
+    };
+    let prehashes = (0u8..=255).map(|n| [n; 32]);
+    let accepted = prehashes
+        .clone()
+        .find(|p| vk.verify(p, &sig).is_ok())
+        .unwrap();
+    let batch_only = prehashes
+        .clone()
+        .find(|p| {
+            vk.verify(p, &sig).is_err() && ed25519_dalek::verify_batch(&[p], &[sig], &[vk]).is_ok()
+        })
+        .unwrap();
+    run_service_test_with_cycles(
+        Cmd::BatchVerify,
+        b"Ok([Err(VerifyFailed)])".to_vec(),
+        small_order_item(&batch_only),
+        100_000_000,
+    );
+    let items = [
+        ed25519[0].clone(),
+        small_order_item(&batch_only),
+        small_order_item(&accepted),
+        ed25519[1].clone(),
+    ]
+    .concat();
+    run_service_test_with_cycles(
+        Cmd::BatchVerify,
+        b"Ok([Ok(()), Err(VerifyFailed), Ok(()), Ok(())])".to_vec(),
+        items,
+        100_000_000,
+    );
+
+    // R plus a point of order 2, with s computed for R: `ed25519_verify`
+    // always rejects it, and the batch equation for half of the prehashes
+    use curve25519_dalek::scalar::Scalar;
+    use curve25519_dalek::{constants::ED25519_BASEPOINT_POINT, edwards::CompressedEdwardsY};
+    use sha2::{Digest, Sha512};
+    let secret = Scalar::from(7u64);
+    let nonce = Scalar::from(11u64);
+    let order_2 = CompressedEdwardsY(public_key).decompress().unwrap();
+    let public_key = (ED25519_BASEPOINT_POINT * secret).compress().to_bytes();
+    let mixed_r = (ED25519_BASEPOINT_POINT * nonce + order_2)
+        .compress()
+        .to_bytes();
+    let vk = VerifyingKey::from_bytes(&public_key).unwrap();
+    let mixed_order_item = |prehash: &[u8]| {
+        let k = Scalar::from_hash(
+            Sha512::new()
+                .chain_update(mixed_r)
+                .chain_update(public_key)
+                .chain_update(prehash),
+        );
+        let mut signature = mixed_r.to_vec();
+        signature.extend((nonce + k * secret).to_bytes());
+        let sig = Signature::from_slice(&signature).unwrap();
+        let mut item = vec![2];
+        push_field(&mut item, &public_key);
+        push_field(&mut item, prehash);
+        push_field(&mut item, &signature);
+        (item, sig)
+    };
+    let batch_only = prehashes
+        .clone()
+        .find(|p| ed25519_dalek::verify_batch(&[p], &[mixed_order_item(p).1], &[vk]).is_ok())
+        .unwrap();
+    let (item, sig) = mixed_order_item(&batch_only);
+    assert!(vk.verify(&batch_only, &sig).is_err());
+    run_service_test_with_cycles(
+        Cmd::BatchVerify,
+        b"Ok([Err(VerifyFailed)])".to_vec(),
+        item.clone(),
+        100_000_000,
+    );
+    let items = [ed25519[0].clone(), item, ed25519[1].clone()].concat();
+    run_service_test_with_cycles(
+        Cmd::BatchVerify,
+        b"Ok([Ok(()), Err(VerifyFailed), Ok(())])".to_vec(),
+        items,
+        100_000_000,
+    );
+}
+
+#[test]
+fn test_multisig_verify() {
+    let message = [5u8; 32];
+    let prikeys: Vec<k256::ecdsa::SigningKey> = (1..=3u8)
+        .map(|n| {
+            let mut prikey_byte = [0u8; 32];
+            prikey_byte[31] = n;
+            k256::ecdsa::SigningKey::from_slice(&prikey_byte).unwrap()
+        })
+        .collect();
+    let pubkey_hashes: Vec<u8> = prikeys
+        .iter()
+        .flat_map(|prikey| {
+            let pubkey = prikey.verifying_key().to_encoded_point(true);
+            ckb_testtool::ckb_hash::blake2b_256(pubkey.as_bytes())[..20].to_vec()
+        })
+        .collect();
+    let sign = |signers: &[usize]| -> Vec<u8> {
+        signers
+            .iter()
+            .flat_map(|&i| {
+                let (sig, recovery_id) = prikeys[i].sign_prehash_recoverable(&message).unwrap();
+                [sig.to_vec(), vec![recovery_id.to_byte()]].concat()
+            })
+            .collect()
+    };
+
+    let run_message =
+        |script_header: [u8; 4], message: &[u8], signatures: &[u8], expected: &str| {
+            let mut witness = Vec::new();
+            push_field(&mut witness, &[&script_header[..], &pubkey_hashes].concat());
+            push_field(&mut witness, message);
+            push_field(&mut witness, signatures);
+            run_service_test_with_cycles(
+                Cmd::MultisigVerify,
+                expected.as_bytes().to_vec(),
+                witness,
+                100_000_000,
+            );
+        };
+    let run = |script_header: [u8; 4], signatures: &[u8], expected: &str| {
+        run_message(script_header, &message, signatures, expected)
+    };
+    // the first key required, 2 of 3
+    let header = [0, 1, 2, 3];
+    run(header, &sign(&[0, 2]), "Ok([0, 2])");
+    run(header, &sign(&[2, 0]), "Ok([2, 0])");
+    run(header, &sign(&[1, 2]), "Err(RequiredSignerMissing)");
+    run(header, &sign(&[0, 0]), "Err(UnknownSigner)");
+    run(header, &sign(&[0]), "Err(InvalidSig)");
+    run([0, 0, 2, 3], &sign(&[1, 2]), "Ok([1, 2])");
+    run([0, 3, 2, 3], &sign(&[0, 1]), "Err(InvalidMultisigScript)");
+    run([0, 0, 4, 3], &sign(&[0, 1]), "Err(InvalidMultisigScript)");
+    run([1, 1, 2, 3], &sign(&[0, 1]), "Err(InvalidMultisigScript)");
+    // the message must be a 32-byte hash
+    run_message(
+        header,
+        &message[..31],
+        &sign(&[0, 2]),
+        "Err(InvalidPrehash)",
+    );
+    run_message(header, &[5u8; 33], &sign(&[0, 2]), "Err(InvalidPrehash)");
+}
+
+const BLS_DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+
+fn bls_secret_key(n: u8) -> blst::min_pk::SecretKey {
+    blst::min_pk::SecretKey::key_gen(&[n; 32], &[]).unwrap()
+}
+
+fn bls_aggregate(signatures: &[blst::min_pk::Signature]) -> Vec<u8> {
+    let signatures: Vec<&blst::min_pk::Signature> = signatures.iter().collect();
+    blst::min_pk::AggregateSignature::aggregate(&signatures, true)
+        .unwrap()
+        .to_signature()
+        .to_bytes()
+        .to_vec()
+}
+
+#[test]
+fn test_bls_verify() {
+    let message = b"hello ckb";
+    let sk = bls_secret_key(1);
+    let public_key = sk.sk_to_pk().to_bytes();
+    let signature = sk.sign(message, BLS_DST, &[]).to_bytes();
+    let other_signature = sk.sign(b"other", BLS_DST, &[]).to_bytes();
+
+    let run = |public_key: &[u8], signature: &[u8], expected: &str| {
+        let mut witness = Vec::new();
+        push_field(&mut witness, public_key);
+        push_field(&mut witness, message);
+        push_field(&mut witness, signature);
+        run_service_test_with_cycles(
+            Cmd::BlsVerify,
+            expected.as_bytes().to_vec(),
+            witness,
+            1_000_000_000,
+        )
+    };
+    let cycles = run(&public_key, &signature, "Ok(())");
+    // see the cycles of BLS in the README of ckb-crypto-service
+    assert!(cycles < 55_000_000, "bls_verify: {} cycles", cycles);
+    run(&public_key, &other_signature, "Err(VerifyFailed)");
+    run(&public_key[1..], &signature, "Err(InvalidPubkey)");
+    run(&public_key, &signature[..95], "Err(InvalidSig)");
+    // the compressed identity of G1
+    let mut identity = [0u8; 48];
+    identity[0] = 0xc0;
+    run(&identity, &signature, "Err(InvalidPubkey)");
+}
+
+#[test]
+fn test_bls_aggregate_verify() {
+    let keys: Vec<_> = (1..=3).map(bls_secret_key).collect();
+    let messages: Vec<Vec<u8>> = (1..=3u8).map(|n| vec![n; 32]).collect();
+    let signatures: Vec<_> = keys
+        .iter()
+        .zip(&messages)
+        .map(|(sk, message)| sk.sign(message, BLS_DST, &[]))
+        .collect();
+    let signature = bls_aggregate(&signatures);
+    let partial_signature = bls_aggregate(&signatures[..2]);
+
+    let run = |count: usize, signature: &[u8], expected: &str| {
+        let mut witness = vec![count as u8];
+        for (sk, message) in keys.iter().zip(&messages).take(count) {
+            push_field(&mut witness, &sk.sk_to_pk().to_bytes());
+            push_field(&mut witness, message);
+        }
+        push_field(&mut witness, signature);
+        run_service_test_with_cycles(
+            Cmd::BlsAggregateVerify,
+            expected.as_bytes().to_vec(),
+            witness,
+            1_000_000_000,
+        )
+    };
+    let cycles = run(3, &signature, "Ok(())");
+    assert!(
+        cycles < 95_000_000,
+        "bls_aggregate_verify: {} cycles",
+        cycles
+    );
+    run(3, &partial_signature, "Err(VerifyFailed)");
+    run(0, &signature, "Err(InvalidAggregate)");
+}
+
+#[test]
+fn test_bls_fast_aggregate_verify() {
+    let message = [9u8; 32];
+    let keys: Vec<_> = (1..=8).map(bls_secret_key).collect();
+    let signatures: Vec<_> = keys
+        .iter()
+        .map(|sk| sk.sign(&message, BLS_DST, &[]))
+        .collect();
+    let signature = bls_aggregate(&signatures);
+
+    let run = |count: usize, signature: &[u8], expected: &str| {
+        let mut witness = vec![count as u8];
+        for sk in keys.iter().take(count) {
+            push_field(&mut witness, &sk.sk_to_pk().to_bytes());
+        }
+        push_field(&mut witness, &message);
+        push_field(&mut witness, signature);
+        run_service_test_with_cycles(
+            Cmd::BlsFastAggregateVerify,
+            expected.as_bytes().to_vec(),
+            witness,
+            1_000_000_000,
+        )
+    };
+    let cycles = run(8, &signature, "Ok(())");
+    assert!(
+        cycles < 70_000_000,
+        "bls_fast_aggregate_verify: {} cycles",
+        cycles
+    );
+    run(7, &signature, "Err(VerifyFailed)");
+    run(0, &signature, "Err(InvalidAggregate)");
+}